@@ -0,0 +1,168 @@
+use crate::{ray::Ray, vector4::Vector4};
+use std::sync::Arc;
+
+/// Axis-aligned bounding box in `R^3` described by its minimal and maximal corners.
+/// The `w` components of the corners are expected to be `0`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vector4,
+    pub max: Vector4
+}
+
+impl Aabb {
+    /// Constructs the smallest box containing both `a` and `b`.
+    pub fn new(a: Vector4, b: Vector4) -> Self {
+        Self { min: a.min(b), max: a.max(b) }
+    }
+
+    /// Constructs a box containing no points, i.e. the identity of `Aabb::union`.
+    pub fn empty() -> Self {
+        Self {
+            min: Vector4::new(f32::INFINITY, f32::INFINITY, f32::INFINITY, 0.0),
+            max: Vector4::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY, 0.0)
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0..3).any(|axis| self.min[axis] > self.max[axis])
+    }
+
+    /// Returns `true` if all corners of the box are finite, i.e. the box is neither empty nor extends to infinity.
+    pub fn is_finite(&self) -> bool {
+        (0..3).all(|axis| self.min[axis].is_finite() && self.max[axis].is_finite())
+    }
+
+    /// Returns the smallest box containing both `self` and `other`.
+    pub fn union(&self, other: Self) -> Self {
+        Self { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+
+    /// Returns the smallest box containing both `self` and `p`.
+    pub fn include(&self, p: Vector4) -> Self {
+        self.union(Self::new(p, p))
+    }
+
+    /// Returns `true` if `p` lies in the box or on its boundary.
+    pub fn contains(&self, p: Vector4) -> bool {
+        (0..3).all(|axis| self.min[axis] <= p[axis] && p[axis] <= self.max[axis])
+    }
+
+    pub fn centroid(&self) -> Vector4 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn diagonal(&self) -> Vector4 {
+        self.max - self.min
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let d = self.diagonal();
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    /// Returns the index of the axis (`0`, `1` or `2` for `x`, `y` and `z` respectively) along which the box is the longest.
+    pub fn longest_axis(&self) -> usize {
+        let d = self.diagonal();
+        if d.x() >= d.y() && d.x() >= d.z() {
+            0
+        } else if d.y() >= d.z() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Returns `true` if `r` passes through the box for some `t` in `[t_min, t_max]`, `false` otherwise.
+    /// 
+    /// `inv_direction` must hold the reciprocals of the components of `r.direction`.
+    /// The far slab distances are enlarged slightly (see PBR 4th ed., section 6.8.2) so that rounding
+    /// errors never cause a box to be missed by a ray that hits a primitive inside of it.
+    pub fn intersect(&self, r: Ray, inv_direction: Vector4, t_min: f32, t_max: f32) -> bool {
+        // 1 + 2 * gamma(3), where gamma(n) = n * eps / (1 - n * eps) and eps = f32::EPSILON / 2.
+        const FAR_SCALE: f32 = 1.0 + 2.0 * (3.0 * f32::EPSILON / 2.0) / (1.0 - 3.0 * f32::EPSILON / 2.0);
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+        for axis in 0..3 {
+            // The ray is parallel to the slab, so it either lies between its planes for all t or for none.
+            if r.direction[axis] == 0.0 {
+                if r.origin[axis] < self.min[axis] || r.origin[axis] > self.max[axis] {
+                    return false;
+                }
+                continue;
+            }
+            let t_0 = (self.min[axis] - r.origin[axis]) * inv_direction[axis];
+            let t_1 = (self.max[axis] - r.origin[axis]) * inv_direction[axis];
+            let (t_near, t_far) = if t_0 <= t_1 { (t_0, t_1) } else { (t_1, t_0) };
+            t_min = t_min.max(t_near);
+            t_max = t_max.min(t_far * FAR_SCALE);
+            if t_min > t_max {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Trait for objects that occupy a bounded region of space.
+pub trait Bounded {
+    /// Returns an axis-aligned box containing the entire object.
+    fn bounding_box(&self) -> Aabb;
+}
+
+impl<T: Bounded + ?Sized> Bounded for Box<T> {
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+}
+
+impl<T: Bounded + ?Sized> Bounded for Arc<T> {
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_union() {
+        let a = Aabb::new(Vector4::new(0.0, 0.0, 0.0, 0.0), Vector4::new(1.0, 1.0, 1.0, 0.0));
+        let b = Aabb::new(Vector4::new(2.0, -1.0, 0.5, 0.0), Vector4::new(3.0, 0.5, 0.7, 0.0));
+        let c = a.union(b);
+        assert_eq!(c.min, Vector4::new(0.0, -1.0, 0.0, 0.0));
+        assert_eq!(c.max, Vector4::new(3.0, 1.0, 1.0, 0.0));
+        assert_eq!(Aabb::empty().union(a), a);
+        assert!(Aabb::empty().is_empty());
+        assert!(a.is_finite() && !Aabb::empty().is_finite());
+        assert_eq!(Aabb::empty().surface_area(), 0.0);
+    }
+
+    #[test]
+    fn test_surface_area() {
+        let a = Aabb::new(Vector4::new(0.0, 0.0, 0.0, 0.0), Vector4::new(1.0, 2.0, 3.0, 0.0));
+        assert_eq!(a.surface_area(), 2.0 * (2.0 + 6.0 + 3.0));
+        assert_eq!(a.longest_axis(), 2);
+    }
+
+    #[test]
+    fn test_intersect() {
+        let a = Aabb::new(Vector4::new(-1.0, -1.0, -1.0, 0.0), Vector4::new(1.0, 1.0, 1.0, 0.0));
+        let inv = |r: Ray| Vector4::new(r.direction.x().recip(), r.direction.y().recip(), r.direction.z().recip(), 0.0);
+
+        let r = Ray::new(Vector4::new(-5.0, 0.0, 0.0, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0));
+        assert!(a.intersect(r, inv(r), 0.0, f32::INFINITY));
+        assert!(!a.intersect(r, inv(r), 0.0, 3.0));
+        assert!(!a.intersect(r, inv(r), 7.0, f32::INFINITY));
+
+        let r = Ray::new(Vector4::new(-5.0, 2.0, 0.0, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0));
+        assert!(!a.intersect(r, inv(r), 0.0, f32::INFINITY));
+
+        // Ray lying in the plane of a face.
+        let r = Ray::new(Vector4::new(-5.0, 1.0, 0.0, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0));
+        assert!(a.intersect(r, inv(r), 0.0, f32::INFINITY));
+    }
+}
@@ -0,0 +1,347 @@
+use crate::{
+    aabb::{Aabb, Bounded},
+    intersectable::{HitRecord, Intersectable},
+    materials::Tangible,
+    ray::Ray,
+    renderable_list::RenderableList,
+    vector4::Vector4
+};
+use rand::Rng;
+
+// Surface area heuristic parameters.
+const BIN_COUNT: usize = 16;
+const MAX_PRIMITIVES_PER_LEAF: usize = 4;
+const TRAVERSAL_COST: f32 = 0.125;      // Cost of traversing a node relative to the cost of intersecting a primitive.
+const INITIAL_STACK_CAPACITY: usize = 64;
+
+/// A node of the flattened hierarchy. Leaves store `count > 0` primitives starting at `offset`, interior nodes store
+/// `count == 0`, have their first child directly after themselves and their second child at `offset`.
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    bounds: Aabb,
+    offset: usize,
+    count: usize,
+    axis: usize
+}
+
+/// Bookkeeping for a primitive during construction.
+#[derive(Clone, Copy, Debug)]
+struct BuildPrimitive {
+    bounds: Aabb,
+    centroid: Vector4,
+    index: usize
+}
+
+/// Bounding volume hierarchy over a collection of bounded primitives, built using the surface area heuristic.
+///
+/// Primitives whose bounding boxes are not finite, e.g. infinite planes, can not be placed in the hierarchy. They are
+/// kept apart and tested against every ray.
+pub struct Bvh<P> {
+    nodes: Vec<Node>,
+    primitives: Vec<P>,     // Primitives in the hierarchy, followed by the unbounded ones.
+    indices: Vec<usize>,    // Position of each primitive in the collection the hierarchy was built from.
+    bounded_count: usize    // Number of primitives in the hierarchy.
+}
+
+impl<P: Bounded> Bvh<P> {
+    pub fn new(primitives: Vec<P>) -> Self {
+        Self::with_bounds(primitives, |p| p.bounding_box())
+    }
+}
+
+impl<P> Bvh<P> {
+    /// Builds a hierarchy over primitives that do not implement `Bounded` themselves, e.g. indices into a
+    /// collection of primitives stored elsewhere, using `bounds` to compute their bounding boxes.
+    pub fn with_bounds(primitives: Vec<P>, bounds: impl Fn(&P) -> Aabb) -> Self {
+        let mut build_primitives = Vec::with_capacity(primitives.len());
+        let mut unbounded_indices = Vec::new();
+        for (index, p) in primitives.iter().enumerate() {
+            let bounds = bounds(p);
+            if bounds.is_finite() {
+                build_primitives.push(BuildPrimitive { bounds, centroid: bounds.centroid(), index });
+            } else {
+                unbounded_indices.push(index);
+            }
+        }
+
+        let mut nodes = Vec::with_capacity(2 * build_primitives.len());
+        if !build_primitives.is_empty() {
+            Self::build(&mut nodes, &mut build_primitives, 0);
+        }
+
+        // Reorder the primitives so that each leaf refers to a contiguous range.
+        let bounded_count = build_primitives.len();
+        let indices: Vec<usize> = build_primitives.iter().map(|p| p.index).chain(unbounded_indices).collect();
+        let mut slots: Vec<Option<P>> = primitives.into_iter().map(Some).collect();
+        let primitives = indices.iter().map(|&i| slots[i].take().unwrap()).collect();
+
+        Self { nodes, primitives, indices, bounded_count }
+    }
+
+    /// Recursively builds the subtree for `build_primitives`, whose first element is at position `offset` of the
+    /// final primitive order, and returns the index of its root node.
+    fn build(nodes: &mut Vec<Node>, build_primitives: &mut [BuildPrimitive], offset: usize) -> usize {
+        let bounds = build_primitives.iter().fold(Aabb::empty(), |acc, p| acc.union(p.bounds));
+        let centroid_bounds = build_primitives.iter().fold(Aabb::empty(), |acc, p| acc.include(p.centroid));
+        let count = build_primitives.len();
+        let node_index = nodes.len();
+        nodes.push(Node { bounds, offset, count, axis: 0 });
+
+        // All centroids coincide, so no split can separate the primitives.
+        let axis = centroid_bounds.longest_axis();
+        if count == 1 || centroid_bounds.max[axis] == centroid_bounds.min[axis] {
+            return node_index;
+        }
+
+        let (split_axis, split_cost, split_bin) = Self::find_split(build_primitives, bounds, centroid_bounds);
+        let leaf_cost = count as f32;
+        if count <= MAX_PRIMITIVES_PER_LEAF && leaf_cost <= split_cost {
+            return node_index;
+        }
+
+        let bin = |p: &BuildPrimitive| Self::bin_index(p.centroid, centroid_bounds, split_axis);
+        let mut mid = partition(build_primitives, |p| bin(p) <= split_bin);
+        if mid == 0 || mid == count {
+            // The binned split failed to separate the primitives, fall back to a median split.
+            build_primitives.sort_by(|a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+            mid = count / 2;
+        }
+
+        let (left, right) = build_primitives.split_at_mut(mid);
+        Self::build(nodes, left, offset);
+        let second_child = Self::build(nodes, right, offset + mid);
+        nodes[node_index] = Node { bounds, offset: second_child, count: 0, axis: split_axis };
+        node_index
+    }
+
+    /// Evaluates the surface area heuristic for the bin boundaries along every axis and returns the axis,
+    /// cost, and index of the last bin of the first child for the cheapest split.
+    fn find_split(build_primitives: &[BuildPrimitive], bounds: Aabb, centroid_bounds: Aabb) -> (usize, f32, usize) {
+        let mut best = (0, f32::INFINITY, 0);
+        for axis in 0..3 {
+            if centroid_bounds.max[axis] == centroid_bounds.min[axis] {
+                continue;
+            }
+
+            let mut bin_bounds = [Aabb::empty(); BIN_COUNT];
+            let mut bin_counts = [0usize; BIN_COUNT];
+            for p in build_primitives {
+                let b = Self::bin_index(p.centroid, centroid_bounds, axis);
+                bin_bounds[b] = bin_bounds[b].union(p.bounds);
+                bin_counts[b] += 1;
+            }
+
+            // Sweep from the right to accumulate the areas and counts of the second child for each split.
+            let mut right_areas = [0.0; BIN_COUNT];
+            let mut right_counts = [0usize; BIN_COUNT];
+            let mut acc_bounds = Aabb::empty();
+            let mut acc_count = 0;
+            for b in (1..BIN_COUNT).rev() {
+                acc_bounds = acc_bounds.union(bin_bounds[b]);
+                acc_count += bin_counts[b];
+                right_areas[b] = acc_bounds.surface_area();
+                right_counts[b] = acc_count;
+            }
+
+            let mut acc_bounds = Aabb::empty();
+            let mut acc_count = 0;
+            for b in 0..BIN_COUNT - 1 {
+                acc_bounds = acc_bounds.union(bin_bounds[b]);
+                acc_count += bin_counts[b];
+                let cost = TRAVERSAL_COST
+                    + (acc_count as f32 * acc_bounds.surface_area() + right_counts[b + 1] as f32 * right_areas[b + 1])
+                    / bounds.surface_area();
+                if cost < best.1 {
+                    best = (axis, cost, b);
+                }
+            }
+        }
+        best
+    }
+
+    fn bin_index(centroid: Vector4, centroid_bounds: Aabb, axis: usize) -> usize {
+        let extent = centroid_bounds.max[axis] - centroid_bounds.min[axis];
+        let b = (BIN_COUNT as f32 * (centroid[axis] - centroid_bounds.min[axis]) / extent) as usize;
+        b.min(BIN_COUNT - 1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.primitives.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.primitives.is_empty()
+    }
+
+    /// Finds the smallest value of `t` such that `r` intersects a primitive and `t` lies in `[t_min, t_max]`.
+    ///
+    /// `intersect_primitive(p, r, t_min, t_max)` must return `Some((t, hit))` if `r` intersects `p` at some smallest `t`
+    /// in `[t_min, t_max]`, and `None` otherwise. Ties are resolved in favour of the primitive that came first in the
+    /// collection the hierarchy was built from, so the result is identical to that of a linear scan.
+    pub fn traverse<'a, H>(
+        &'a self,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+        mut intersect_primitive: impl FnMut(&'a P, Ray, f32, f32) -> Option<(f32, H)>
+    ) -> Option<(f32, H)> {
+        let mut closest: Option<(f32, H)> = None;
+        let mut closest_index = usize::MAX;
+        let mut t_max = t_max;
+        for i in self.bounded_count..self.primitives.len() {
+            if let Some((t, hit)) = intersect_primitive(&self.primitives[i], r, t_min, t_max)
+                && (t < t_max || self.indices[i] < closest_index) {
+                t_max = t;
+                closest_index = self.indices[i];
+                closest = Some((t, hit));
+            }
+        }
+        if self.nodes.is_empty() {
+            return closest;
+        }
+
+        let inv_direction = Vector4::new(r.direction.x().recip(), r.direction.y().recip(), r.direction.z().recip(), 0.0);
+        let mut stack = Vec::with_capacity(INITIAL_STACK_CAPACITY);
+        stack.push(0);
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if !node.bounds.intersect(r, inv_direction, t_min, t_max) {
+                continue;
+            }
+
+            if node.count > 0 {
+                for i in node.offset..node.offset + node.count {
+                    if let Some((t, hit)) = intersect_primitive(&self.primitives[i], r, t_min, t_max)
+                        && (t < t_max || self.indices[i] < closest_index) {
+                        t_max = t;
+                        closest_index = self.indices[i];
+                        closest = Some((t, hit));
+                    }
+                }
+            } else {
+                // Visit the child nearest to the ray origin first so that t_max shrinks as early as possible.
+                let first_child = node_index + 1;
+                let (near, far) = if r.direction[node.axis] < 0.0 {
+                    (node.offset, first_child)
+                } else {
+                    (first_child, node.offset)
+                };
+                stack.push(far);
+                stack.push(near);
+            }
+        }
+
+        closest
+    }
+}
+
+impl<R: Rng + ?Sized> From<RenderableList<R>> for Bvh<Box<dyn Tangible<R> + Send + Sync>> {
+    fn from(list: RenderableList<R>) -> Self {
+        Self::new(list.into_elements())
+    }
+}
+
+impl<R: Rng + ?Sized> Intersectable<R> for Bvh<Box<dyn Tangible<R> + Send + Sync>> {
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        self.traverse(r, t_min, t_max, |e, r, t_min, t_max| e.intersect(r, t_min, t_max).map(|hit| (hit.t, hit)))
+        .map(|(_, hit)| hit)
+    }
+}
+
+/// Moves the elements satisfying `predicate` to the front of `slice` and returns their count.
+fn partition<T>(slice: &mut [T], predicate: impl Fn(&T) -> bool) -> usize {
+    let mut mid = 0;
+    for i in 0..slice.len() {
+        if predicate(&slice[i]) {
+            slice.swap(i, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        materials::{Material, lambertian::Lambertian},
+        random::sample_unit_sphere_uniform,
+        surfaces::sphere::Sphere
+    };
+    use rand_pcg::Pcg64Mcg;
+    use std::sync::Arc;
+
+    fn random_sphere_scene(rng: &mut Pcg64Mcg, sphere_count: usize) -> RenderableList<Pcg64Mcg> {
+        let material: Arc<dyn Material<Pcg64Mcg> + Send + Sync> = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        let mut scene = RenderableList::new();
+        for _ in 0..sphere_count {
+            let center = Vector4::new(
+                rng.random_range(-20.0..20.0),
+                rng.random_range(-20.0..20.0),
+                rng.random_range(-20.0..20.0),
+                0.0
+            );
+            let radius = rng.random_range(0.05..2.0);
+            scene.push(Box::new(Sphere::new(center, radius, material.clone())));
+        }
+        scene
+    }
+
+    #[test]
+    fn test_bvh_matches_linear_scan() {
+        const SPHERE_COUNT: usize = 1000;
+        const RAY_COUNT: usize = 20000;
+
+        // Build identical scenes for the list and the hierarchy.
+        let list = random_sphere_scene(&mut Pcg64Mcg::new(0xcafef00dd15ea5e5), SPHERE_COUNT);
+        let bvh = Bvh::from(random_sphere_scene(&mut Pcg64Mcg::new(0xcafef00dd15ea5e5), SPHERE_COUNT));
+        assert_eq!(bvh.len(), SPHERE_COUNT);
+
+        let mut rng = Pcg64Mcg::new(0x323030372d30382d33314d696b753339);
+        let mut hit_count = 0;
+        for _ in 0..RAY_COUNT {
+            let origin = 30.0 * sample_unit_sphere_uniform(&mut rng);
+            let target = Vector4::new(rng.random_range(-10.0..10.0), rng.random_range(-10.0..10.0), rng.random_range(-10.0..10.0), 0.0);
+            let r = Ray::new(origin, target - origin);
+            let expected = list.intersect(r, 0.001, f32::INFINITY);
+            let actual = bvh.intersect(r, 0.001, f32::INFINITY);
+            match (expected, actual) {
+                (Some(expected), Some(actual)) => {
+                    assert_eq!(expected.t, actual.t);
+                    assert_eq!(expected.normal, actual.normal);
+                    assert_eq!(expected.object.bounding_box(), actual.object.bounding_box());
+                    hit_count += 1;
+                }
+                (None, None) => {}
+                _ => panic!("BVH and linear scan disagree for {:?}", r)
+            }
+        }
+        // Make sure that the test is not vacuous.
+        assert!(hit_count > RAY_COUNT / 2);
+    }
+
+    #[test]
+    fn test_bvh_resolves_ties_like_linear_scan() {
+        let material: Arc<dyn Material<Pcg64Mcg> + Send + Sync> = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        let mut list = RenderableList::<Pcg64Mcg>::new();
+        for i in 0..16 {
+            list.push(Box::new(Sphere::new(Vector4::new(i as f32 * 0.01, 0.0, 0.0, 0.0), 1.0, material.clone())));
+            list.push(Box::new(Sphere::new(Vector4::new(i as f32 * 0.01, 0.0, 0.0, 0.0), 1.0, material.clone())));
+        }
+        let expected = list.intersect(Ray::new(Vector4::new(0.0, 0.0, -5.0, 0.0), Vector4::new(0.0, 0.0, 1.0, 0.0)), 0.0, f32::INFINITY);
+        let first = list.get(0) as *const _ as *const u8;
+        assert!(std::ptr::eq(expected.unwrap().object as *const _ as *const u8, first));
+
+        let bvh = Bvh::from(list);
+        let actual = bvh.intersect(Ray::new(Vector4::new(0.0, 0.0, -5.0, 0.0), Vector4::new(0.0, 0.0, 1.0, 0.0)), 0.0, f32::INFINITY);
+        assert!(std::ptr::eq(actual.unwrap().object as *const _ as *const u8, first));
+    }
+
+    #[test]
+    fn test_empty_bvh() {
+        let bvh = Bvh::from(RenderableList::<Pcg64Mcg>::new());
+        assert!(bvh.is_empty());
+        assert!(bvh.intersect(Ray::new(Vector4::new(0.0, 0.0, 0.0, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0)), 0.0, f32::INFINITY).is_none());
+    }
+}
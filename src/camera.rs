@@ -1,5 +1,14 @@
 use crate::{
-    color::*, random::sample_unit_disk_uniform, ray::Ray, renderable_list::RenderableList, vector4::Vector4
+    color::*,
+    film::{Film, PixelSamples, RunningStatistics},
+    intersectable::{HitRecord, Intersectable},
+    light_list::LightList,
+    materials::{Lobe, Tangible},
+    random::{sample_unit_disk_uniform, stream_seed},
+    ray::Ray,
+    sampleable::power_heuristic,
+    tiles::TileQueue,
+    vector4::Vector4
 };
 use rand::{
     self, 
@@ -7,11 +16,113 @@ use rand::{
     SeedableRng,
 };
 use std::{
-    iter,
-    sync::{Arc, mpsc},
-    thread
+    sync::{
+        Arc,
+        Mutex,
+        atomic::{AtomicUsize, Ordering}
+    },
+    thread,
+    time::{Duration, Instant}
 };
 
+// Number of standard errors on either side of the mean covered by a 95% confidence interval.
+const CONFIDENCE_INTERVAL_Z: f32 = 1.96;
+
+/// Settings of adaptive sampling, which stops sampling a pixel once it has at least `min_samples` samples and the half
+/// width of the 95% confidence interval of the mean luminance of its samples, relative to the mean, is at most
+/// `threshold`. Pixels that do not converge are sampled up to the `samples_per_pixel` of the camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdaptiveSampling {
+    pub min_samples: usize,
+    pub threshold: f32
+}
+
+impl AdaptiveSampling {
+    /// Panics if `min_samples` is less than two, the fewest samples from which a variance can be estimated.
+    pub fn new(min_samples: usize, threshold: f32) -> Self {
+        assert!(min_samples >= 2, "adaptive sampling needs at least two samples per pixel");
+        Self { min_samples, threshold }
+    }
+
+    fn is_converged(&self, statistics: &RunningStatistics) -> bool {
+        statistics.count() as usize >= self.min_samples && CONFIDENCE_INTERVAL_Z * statistics.relative_error() <= self.threshold
+    }
+}
+
+/// Settings of `Camera::render_progressive`. Rendering stops once `max_samples` samples per pixel have been taken, or
+/// earlier if the time budget is used up or the noise of the image, see `Film::noise`, falls below the threshold.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProgressiveSettings {
+    pub samples_per_pass: usize,
+    pub max_samples: usize,                     // Samples per pixel.
+    pub time_budget: Option<Duration>,
+    pub noise_threshold: Option<f32>,
+    pub snapshot_interval: Option<Duration>     // None to take a snapshot after every pass.
+}
+
+impl ProgressiveSettings {
+    /// Constructs settings that stop after `max_samples` samples per pixel and take a snapshot after every pass.
+    ///
+    /// Panics if `samples_per_pass` is zero.
+    pub fn new(samples_per_pass: usize, max_samples: usize) -> Self {
+        assert!(samples_per_pass > 0, "passes must take samples");
+        Self { samples_per_pass, max_samples, time_budget: None, noise_threshold: None, snapshot_interval: None }
+    }
+
+    pub fn with_time_budget(mut self, time_budget: Duration) -> Self {
+        self.time_budget = Some(time_budget);
+        self
+    }
+
+    pub fn with_noise_threshold(mut self, noise_threshold: f32) -> Self {
+        self.noise_threshold = Some(noise_threshold);
+        self
+    }
+
+    pub fn with_snapshot_interval(mut self, snapshot_interval: Duration) -> Self {
+        self.snapshot_interval = Some(snapshot_interval);
+        self
+    }
+}
+
+/// Stop condition that ended a progressive render.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    SampleCount,
+    TimeBudget,
+    NoiseThreshold
+}
+
+/// Radiance arriving along rays that do not intersect the scene.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Background {
+    /// No light arrives from the background, so the scene is only lit by emissive objects.
+    None,
+    /// Uniform radiance from all directions.
+    Solid(Vector4),
+    /// Linear gradient from `horizon` (for rays pointing along -z) to `zenith` (for rays pointing along +z).
+    Sky { horizon: Vector4, zenith: Vector4 }
+}
+
+impl Background {
+    pub fn radiance(&self, r: Ray) -> Vector4 {
+        match *self {
+            Self::None => Vector4::new(0.0, 0.0, 0.0, 0.0),
+            Self::Solid(radiance) => radiance,
+            Self::Sky { horizon, zenith } => {
+                let t = (r.direction.normalize().z() + 1.0) / 2.0;
+                lerp(horizon, zenith, t)
+            }
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self::Sky { horizon: Vector4::new(1.0, 1.0, 1.0, 0.0), zenith: Vector4::new(0.5, 0.7, 1.0, 0.0) }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Camera {
     // Image.
@@ -36,15 +147,23 @@ pub struct Camera {
     viewport_delta_v: Vector4,
     // Sampling.
     samples_per_pixel: usize,
+    adaptive_sampling: Option<AdaptiveSampling>,    // None to take samples_per_pixel samples of every pixel.
     anti_aliasing_disk_radius: f32,
     defocus_disk_radius: f32,
+    shutter_open: f32,      // Interval of time over which rays are spread, to blur moving objects.
+    shutter_close: f32,
     // Ray intersections.
     max_depth: usize,
     t_min: f32,
-    t_max: f32
+    t_max: f32,
+    // Lighting.
+    background: Background,
+    // Scheduling.
+    tile_size: usize        // Side length of the tiles that render_concurrent splits the image into.
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         // Image.
         aspect_ratio: f32,
@@ -65,22 +184,8 @@ impl Camera {
         // Ensure that image_height is at least 1.
         let image_height = if aspect_ratio > image_width as f32 { 1 } else { (image_width as f32 / aspect_ratio) as usize };
 
-        let viewport_width = 2.0 * focus_distance * f32::tan(hfov_rad / 2.0);
-        let viewport_height = viewport_width / (image_width as f32 / image_height as f32);
-
-        // Form an orthonormal basis describing the orientation of the camera.
-        let w = (look_at - look_from).normalize();
-        let u = vup.cross(w).normalize();
-        let v = w.cross(u);
-
-        let viewport_u = viewport_width * u;
-        let viewport_v = -viewport_height * v;
-        let viewport_top_left = look_from + focus_distance * w - (viewport_u + viewport_v) / 2.0;
-
-        let viewport_delta_u = viewport_u / (image_width as f32);
-        let viewport_delta_v = viewport_v / (image_height as f32);
-
-        Self {
+        let zero = Vector4::new(0.0, 0.0, 0.0, 0.0);
+        let mut camera = Self {
             aspect_ratio,
             image_width,
             image_height,
@@ -91,37 +196,123 @@ impl Camera {
             look_from,
             look_at,
             vup,
-            u,
-            v,
-            w,
-            viewport_00: viewport_top_left + (viewport_delta_u + viewport_delta_v) / 2.0,
-            viewport_delta_u,
-            viewport_delta_v,
+            u: zero,
+            v: zero,
+            w: zero,
+            viewport_00: zero,
+            viewport_delta_u: zero,
+            viewport_delta_v: zero,
             samples_per_pixel,
-            anti_aliasing_disk_radius: f32::max(viewport_delta_u.norm(), viewport_delta_v.norm()),
+            adaptive_sampling: None,
+            anti_aliasing_disk_radius: 0.0,
             defocus_disk_radius: focus_distance * f32::tan(defocus_angle_rad / 2.0),
+            shutter_open: 0.0,
+            shutter_close: 0.0,
             max_depth,
             t_min,
-            t_max
-        }
+            t_max,
+            background: Background::default(),
+            tile_size: 32
+        };
+        camera.update_viewport();
+        camera
     }
 
-    pub fn render<R: Rng + ?Sized>(&self, rng: &mut R, scene: &RenderableList<R>) -> Image {
+    /// Computes the orientation of the camera and the viewport from the image size, field of view and orientation parameters.
+    fn update_viewport(&mut self) {
+        let viewport_width = 2.0 * self.focus_distance * f32::tan(self.hfov_rad / 2.0);
+        let viewport_height = viewport_width / (self.image_width as f32 / self.image_height as f32);
+
+        // Form an orthonormal basis describing the orientation of the camera.
+        self.w = (self.look_at - self.look_from).normalize();
+        self.u = self.vup.cross(self.w).normalize();
+        self.v = self.w.cross(self.u);
+
+        let viewport_u = viewport_width * self.u;
+        let viewport_v = -viewport_height * self.v;
+        let viewport_top_left = self.look_from + self.focus_distance * self.w - (viewport_u + viewport_v) / 2.0;
+
+        self.viewport_delta_u = viewport_u / (self.image_width as f32);
+        self.viewport_delta_v = viewport_v / (self.image_height as f32);
+        self.viewport_00 = viewport_top_left + (self.viewport_delta_u + self.viewport_delta_v) / 2.0;
+        self.anti_aliasing_disk_radius = f32::max(self.viewport_delta_u.norm(), self.viewport_delta_v.norm());
+    }
+
+    pub fn image_width(&self) -> usize {
+        self.image_width
+    }
+
+    pub fn image_height(&self) -> usize {
+        self.image_height
+    }
+
+    pub fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    /// Changes the size of the rendered image, keeping the horizontal field of view.
+    ///
+    /// Panics if either dimension is zero.
+    pub fn with_resolution(mut self, image_width: usize, image_height: usize) -> Self {
+        assert!(image_width > 0 && image_height > 0, "image must not be empty");
+        self.image_width = image_width;
+        self.image_height = image_height;
+        self.aspect_ratio = image_width as f32 / image_height as f32;
+        self.update_viewport();
+        self
+    }
+
+    pub fn with_samples_per_pixel(mut self, samples_per_pixel: usize) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    /// Makes `render` and `render_concurrent` sample each pixel adaptively, with `samples_per_pixel` as the maximum.
+    pub fn with_adaptive_sampling(mut self, adaptive_sampling: AdaptiveSampling) -> Self {
+        self.adaptive_sampling = Some(adaptive_sampling);
+        self
+    }
+
+    /// Opens the shutter from `open` to `close`, spreading the times of rays uniformly over the interval so that objects
+    /// moving during it are blurred. By default, the shutter opens and closes at time zero.
+    ///
+    /// Panics if `close` is before `open`.
+    pub fn with_shutter(mut self, open: f32, close: f32) -> Self {
+        assert!(open <= close, "the shutter must not close before it opens");
+        self.shutter_open = open;
+        self.shutter_close = close;
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the radiance arriving along rays that escape the scene, the default is a blue-white `Background::Sky`.
+    pub fn with_background(mut self, background: Background) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Sets the side length of the square tiles that `render_concurrent` hands out to its threads, the default is 32 pixels.
+    ///
+    /// Panics if `tile_size` is zero.
+    pub fn with_tile_size(mut self, tile_size: usize) -> Self {
+        assert!(tile_size > 0, "tiles must not be empty");
+        self.tile_size = tile_size;
+        self
+    }
+
+    /// Renders `scene`, sampling the objects in `lights` explicitly to estimate the direct lighting at each diffuse surface.
+    /// Every light must also be part of `scene`.
+    pub fn render<R: Rng + ?Sized, S: Intersectable<R> + ?Sized>(&self, rng: &mut R, scene: &S, lights: &LightList<R>) -> Image {
         let mut image = Image::new(self.image_width, self.image_height, self.color_depth, self.decoding_gamma.recip());
 
         for i in 0..self.image_height {
             eprintln!("Scan lines remaining: {}", self.image_height - i);
             for j in 0..self.image_width {
-                let mut acc_color = Vector4::new(0.0, 0.0, 0.0, 0.0);
-                for _ in 0..self.samples_per_pixel {
-                    let ray = self.ray(rng, i, j);
-                    acc_color += self.ray_color(
-                        rng, 
-                        ray, 
-                        scene
-                    );
-                }
-                image.set_pixel(acc_color / self.samples_per_pixel as f32, i, j);
+                image.set_pixel(self.pixel_color(rng, i, j, scene, lights), i, j);
             }
         }
         eprintln!("Finished rendering.");
@@ -129,59 +320,182 @@ impl Camera {
         image
     }
 
-    /// Each thread has its own RNG initialised using `SeedableRng::from_os_rng()`.
-    pub fn render_concurrent<R: Rng + SeedableRng + 'static>(
+    /// Renders `scene` like `render`, but on `thread_count` threads, or as many as the machine can run in parallel if
+    /// `thread_count` is zero. The image is split into tiles that the threads pull from a shared queue and write straight
+    /// into the image, so that every thread keeps working until the last tile is taken.
+    ///
+    /// Each pixel is sampled with its own RNG, seeded from `seed` and the position of the pixel, so the image only depends
+    /// on `seed` and is bit-identical whatever the thread count and tile size.
+    pub fn render_concurrent<R: Rng + SeedableRng, S: Intersectable<R> + Send + Sync + ?Sized>(
         self, 
-        scene: Arc<RenderableList<R>>, 
+        seed: u64,
+        scene: Arc<S>, 
+        lights: Arc<LightList<R>>,
         thread_count: usize
     ) -> Image {
-        let mut image = Image::new(
-            self.image_width, 
-            self.image_height, 
-            self.color_depth, 
-            self.decoding_gamma.recip()
-        );
-        let mut handles = Vec::new();
-        let (tx, rx) = mpsc::sync_channel::<(usize, Vec<Vector4>)>(thread_count);
-
-        for t in 0..thread_count {
-            let tx = tx.clone();
-            let scene = scene.clone();
-            let handle = thread::spawn(
-                move || {
-                    let mut rng = R::from_os_rng();
-                    let scan_lines: Vec<Vector4> = (t..self.image_height)
-                    .step_by(thread_count)
-                    .flat_map(|i| iter::repeat_n(i, self.image_width))
-                    .zip((0..self.image_width).cycle())
-                    .map(|(i, j)| {
-                        let mut acc_color = Vector4::new(0.0, 0.0, 0.0, 0.0);
-                        for _ in 0..self.samples_per_pixel {
-                            let ray = self.ray(&mut rng, i, j);
-                            acc_color += self.ray_color(
-                                &mut rng, 
-                                ray, 
-                                &scene
-                            );
-                        }
-                        acc_color / self.samples_per_pixel as f32
-                    })
-                    .collect();
-                    tx.send((t, scan_lines)).unwrap();
-                }
-            );
-            handles.push(handle);
-        }
+        let image = self.render_concurrent_with_progress(seed, scene, lights, thread_count, &|finished, total| {
+            eprintln!("Tiles remaining: {}", total - finished);
+        });
+        eprintln!("Finished rendering.");
+        image
+    }
 
-        for _ in 0..thread_count {
-            let (t, scan_lines) = rx.recv().unwrap();
-            (t..self.image_height)
-            .step_by(thread_count)
-            .enumerate()
-            .for_each(|(j, i)| image.set_row(&scan_lines[j * self.image_width..(j + 1) * self.image_width], i))
+    /// Renders `scene` like `render_concurrent`, but instead of printing the number of remaining tiles, calls `progress`
+    /// with the numbers of finished and total tiles whenever a thread finishes a tile.
+    pub fn render_concurrent_with_progress<R: Rng + SeedableRng, S: Intersectable<R> + Send + Sync + ?Sized>(
+        self, 
+        seed: u64,
+        scene: Arc<S>, 
+        lights: Arc<LightList<R>>,
+        thread_count: usize,
+        progress: &(dyn Fn(usize, usize) + Sync)
+    ) -> Image {
+        self.image(&self.render_film(seed, &*scene, &lights, thread_count, progress))
+    }
+
+    /// Renders `scene` like `render_concurrent_with_progress`, but returns the film holding the samples of each pixel, e.g.
+    /// to look at the number of samples adaptive sampling took of each pixel.
+    pub fn render_film<R: Rng + SeedableRng, S: Intersectable<R> + Sync + ?Sized>(
+        &self,
+        seed: u64,
+        scene: &S,
+        lights: &LightList<R>,
+        thread_count: usize,
+        progress: &(dyn Fn(usize, usize) + Sync)
+    ) -> Film {
+        let mut film = Film::new(self.image_width, self.image_height);
+        let samples = self.samples_per_pixel;
+        self.render_pass(seed, scene, lights, thread_count, samples, self.adaptive_sampling, &mut film, progress);
+        film
+    }
+
+    /// Renders `scene` progressively into `film`, in passes of `settings.samples_per_pass` samples per pixel, until one of
+    /// the stop conditions of `settings` is met. Passes are not interrupted, so the render may overrun its time budget by
+    /// up to one pass. After each pass, `snapshot` is called with the film if the snapshot interval has passed.
+    ///
+    /// Rendering continues from the passes already in `film`, and the random numbers for each pixel are seeded from `seed`,
+    /// the pass and the position of the pixel, so the result only depends on `seed` and `settings` like `render_concurrent`,
+    /// whose image equals a single pass of `samples_per_pixel` samples. Adaptive sampling is not used, since the passes
+    /// already stop once the noise threshold is reached.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_progressive<R: Rng + SeedableRng, S: Intersectable<R> + Sync + ?Sized>(
+        &self,
+        seed: u64,
+        scene: &S,
+        lights: &LightList<R>,
+        thread_count: usize,
+        settings: &ProgressiveSettings,
+        film: &mut Film,
+        snapshot: &mut dyn FnMut(&Film)
+    ) -> StopReason {
+        assert_eq!((film.width(), film.height()), (self.image_width, self.image_height), "film must match the image size");
+        let start = Instant::now();
+        let mut last_snapshot = start;
+        loop {
+            // Every pass takes the same number of samples of each pixel.
+            let sample_count = film.mean_sample_count().round() as usize;
+            if sample_count >= settings.max_samples {
+                return StopReason::SampleCount;
+            }
+            let samples = usize::min(settings.samples_per_pass, settings.max_samples - sample_count);
+            self.render_pass(seed, scene, lights, thread_count, samples, Option::None, film, &|_, _| {});
+
+            if settings.snapshot_interval.is_none_or(|interval| last_snapshot.elapsed() >= interval) {
+                snapshot(film);
+                last_snapshot = Instant::now();
+            }
+            if settings.time_budget.is_some_and(|budget| start.elapsed() >= budget) {
+                return StopReason::TimeBudget;
+            }
+            if settings.noise_threshold.is_some_and(|threshold| film.noise() <= threshold) {
+                return StopReason::NoiseThreshold;
+            }
         }
+    }
 
-        image
+    /// Returns the image of the pixel means of `film`.
+    pub fn image(&self, film: &Film) -> Image {
+        film.to_image(self.color_depth, self.decoding_gamma.recip())
+    }
+
+    /// Takes `samples` samples of every pixel, or up to `samples` with `adaptive` sampling, on `thread_count` threads (or
+    /// one per core if zero) and adds them to `film` as the next pass, calling `progress` with the numbers of finished and total tiles whenever a tile is finished.
+    #[allow(clippy::too_many_arguments)]
+    fn render_pass<R: Rng + SeedableRng, S: Intersectable<R> + Sync + ?Sized>(
+        &self,
+        seed: u64,
+        scene: &S,
+        lights: &LightList<R>,
+        thread_count: usize,
+        samples: usize,
+        adaptive: Option<AdaptiveSampling>,
+        film: &mut Film,
+        progress: &(dyn Fn(usize, usize) + Sync)
+    ) {
+        let thread_count = match thread_count {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n
+        };
+        let tiles = TileQueue::new(self.image_width, self.image_height, self.tile_size);
+        let finished_tile_count = AtomicUsize::new(0);
+        // The first pass uses the streams 0..pixel_count, the second pixel_count..2 * pixel_count and so on.
+        let first_stream = film.passes() * self.image_width * self.image_height;
+        let film = Mutex::new(film);
+
+        thread::scope(|s| {
+            for _ in 0..thread_count {
+                s.spawn(|| {
+                    while let Some(tile) = tiles.pop() {
+                        let pixel_samples: Vec<PixelSamples> = tile
+                            .pixels()
+                            .map(|(i, j)| {
+                                let stream = first_stream + i * self.image_width + j;
+                                let mut rng = R::seed_from_u64(stream_seed(seed, stream as u64));
+                                self.sample_pixel(&mut rng, i, j, samples, adaptive, scene, lights)
+                            })
+                            .collect();
+                        film.lock().unwrap().add_tile(&pixel_samples, tile);
+                        progress(finished_tile_count.fetch_add(1, Ordering::Relaxed) + 1, tiles.len());
+                    }
+                });
+            }
+        });
+
+        film.into_inner().unwrap().finish_pass();
+    }
+
+    /// Estimates the colour of the pixel in row `i` and column `j` by averaging `samples_per_pixel` samples, or fewer with
+    /// adaptive sampling.
+    fn pixel_color<R: Rng + ?Sized, S: Intersectable<R> + ?Sized>(&self, rng: &mut R, i: usize, j: usize, scene: &S, lights: &LightList<R>) -> Vector4 {
+        let samples = self.sample_pixel(rng, i, j, self.samples_per_pixel, self.adaptive_sampling, scene, lights);
+        samples.sum / samples.count as f32
+    }
+
+    /// Takes `max_samples` samples of the pixel in row `i` and column `j`, or stops earlier once the pixel has converged
+    /// if `adaptive` is given. The mean and variance of the luminance of the samples are tracked with Welford's algorithm.
+    #[allow(clippy::too_many_arguments)]
+    fn sample_pixel<R: Rng + ?Sized, S: Intersectable<R> + ?Sized>(
+        &self,
+        rng: &mut R,
+        i: usize,
+        j: usize,
+        max_samples: usize,
+        adaptive: Option<AdaptiveSampling>,
+        scene: &S,
+        lights: &LightList<R>
+    ) -> PixelSamples {
+        let mut pixel_samples = PixelSamples::new();
+        let mut statistics = RunningStatistics::new();
+        for _ in 0..max_samples {
+            if adaptive.is_some_and(|a| a.is_converged(&statistics)) {
+                break;
+            }
+            let ray = self.ray(rng, i, j);
+            let sample = self.ray_color(rng, ray, scene, lights);
+            pixel_samples.add(sample);
+            statistics.add(luminance(sample));
+        }
+        pixel_samples
     }
 
     fn ray<R: Rng + ?Sized>(&self, rng: &mut R, i: usize, j: usize) -> Ray {
@@ -191,30 +505,438 @@ impl Camera {
         let ray_origin_offset = self.defocus_disk_radius * (defocus_disk_sample.x() * self.u + defocus_disk_sample.y() * self.v);
         let ray_direction_offset = self.anti_aliasing_disk_radius * Vector4::new(anti_aliasing_disk_sample.x(), 0.0, anti_aliasing_disk_sample.y(), 0.0);
         let ray_origin = self.look_from + ray_origin_offset;
-        Ray::new(ray_origin, viewport_ij + ray_direction_offset - ray_origin)
+        // Without motion blur, no random number is drawn for the time, so that such renders do not change.
+        let time = if self.shutter_close > self.shutter_open {
+            rng.random_range(self.shutter_open..self.shutter_close)
+        } else {
+            self.shutter_open
+        };
+        Ray::new(ray_origin, viewport_ij + ray_direction_offset - ray_origin).with_time(time)
     }
 
-    fn ray_color<R: Rng + ?Sized>(&self, rng: &mut R, r: Ray, scene: &RenderableList<R>) -> Vector4 {
+    /// Estimates the radiance arriving along `r`. At each vertex of the path, light is sampled both explicitly through
+    /// `lights` and by following the ray scattered by the material, and the two estimates are combined using multiple
+    /// importance sampling with the power heuristic.
+    fn ray_color<R: Rng + ?Sized, S: Intersectable<R> + ?Sized>(&self, rng: &mut R, r: Ray, scene: &S, lights: &LightList<R>) -> Vector4 {
         let mut ray = r;
         let mut ray_attenuation = Vector4::new(1.0, 1.0, 1.0, 0.0);
+        let mut radiance = Vector4::new(0.0, 0.0, 0.0, 0.0);
+        // Density with which the previous vertex scattered ray, None if it was scattered by a specular lobe.
+        let mut scatter_pdf: Option<f32> = None;
         for _ in 0..self.max_depth {
-            let (t, object) = scene.intersect(ray, self.t_min, self.t_max);
-            if t.is_finite() {
-                ray_attenuation *= object.attenuation(rng, ray, t);
-                if let Some(r) = object.scatter(rng, ray, t) {
-                    ray = r;
+            if let Some(hit) = scene.intersect(ray, self.t_min, self.t_max) {
+                let emitted = hit.material.emitted(ray, &hit);
+                let weight = match scatter_pdf {
+                    Some(pdf) => power_heuristic(pdf, lights.pdf(hit.object, ray.origin, ray.direction.normalize())),
+                    None => 1.0
+                };
+                radiance += ray_attenuation * weight * emitted;
+                radiance += ray_attenuation * self.sample_direct_light(rng, ray, &hit, scene, lights);
+                if let Some(scattered) = hit.material.scatter(rng, ray, &hit) {
+                    ray_attenuation *= scattered.weight;
+                    scatter_pdf = (scattered.lobe != Lobe::Specular).then_some(scattered.pdf);
+                    ray = Ray { origin: hit.p, direction: scattered.direction, ..ray };
                 } else {
                     break;
                 }
             } else {
-                let t = (ray.direction.normalize().z() + 1.0) / 2.0;
-                return ray_attenuation * lerp(Vector4::new(1.0, 1.0, 1.0, 0.0), Vector4::new(0.5, 0.7, 1.0, 0.0), t);
+                return radiance + ray_attenuation * self.background.radiance(ray);
             }
         }
-        Vector4::new(0.0, 0.0, 0.0, 0.0)
+        radiance
+    }
+
+    /// Estimates the radiance scattered along `-r` at `hit` that arrives directly from a randomly chosen light,
+    /// using a shadow ray to test its visibility. The estimate is weighted for combination with the estimate
+    /// obtained by following the scattered ray.
+    fn sample_direct_light<R: Rng + ?Sized, S: Intersectable<R> + ?Sized>(
+        &self,
+        rng: &mut R,
+        r: Ray,
+        hit: &HitRecord<R>,
+        scene: &S,
+        lights: &LightList<R>
+    ) -> Vector4 {
+        let black = Vector4::new(0.0, 0.0, 0.0, 0.0);
+        let Some((light, choice_probability)) = lights.choose(rng) else {
+            return black;
+        };
+        let Some(sample) = light.sample(rng, hit.p) else {
+            return black;
+        };
+        let to_light = sample.p - hit.p;
+        let distance = to_light.norm();
+        let direction = to_light / distance;
+        // Specular lobes do not contribute, since they scatter light from the light sample with probability zero.
+        let f = hit.material.eval(r, hit, direction);
+        if f == black {
+            return black;
+        }
+
+        let shadow_ray = Ray { origin: hit.p, direction, ..r };
+        if scene.intersect(shadow_ray, self.t_min, distance - self.t_min).is_some() {
+            return black;
+        }
+        let light: &(dyn Tangible<R> + Send + Sync) = light;
+        let mut light_hit = HitRecord::new(shadow_ray, distance, sample.normal, sample.uv, light);
+        light_hit.p = sample.p;
+        let light_pdf = sample.pdf * choice_probability;
+        f * light.material().emitted(shadow_ray, &light_hit) * power_heuristic(light_pdf, hit.material.pdf(r, hit, direction)) / light_pdf
     }
 }
 
 pub fn vfov_to_hfov(vfov_rad: f32, aspect_ratio: f32) -> f32 {
     2.0 * f32::atan(aspect_ratio * f32::tan(vfov_rad / 2.0))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constant_medium::ConstantMedium,
+        film::Checkpoint,
+        materials::{
+            Material,
+            diffuse::Diffuse,
+            diffuse_light::DiffuseLight,
+            fuzzy_specular::FuzzySpecular,
+            henyey_greenstein::HenyeyGreenstein,
+            isotropic::Isotropic,
+            lambertian::Lambertian
+        },
+        random::sample_unit_sphere_uniform,
+        renderable_list::RenderableList,
+        surfaces::{moving_sphere::MovingSphere, sphere::Sphere}
+    };
+    use rand_pcg::Pcg64Mcg;
+    use std::sync::Arc;
+
+    fn test_camera(image_width: usize, samples_per_pixel: usize) -> Camera {
+        Camera::new(
+            1.0,
+            image_width,
+            255,
+            2.2,
+            90.0_f32.to_radians(),
+            1.0,
+            Vector4::new(0.0, -3.0, 0.0, 0.0),
+            Vector4::new(0.0, 0.0, 0.0, 0.0),
+            Vector4::new(0.0, 0.0, 1.0, 0.0),
+            samples_per_pixel,
+            0.0,
+            8,
+            0.001,
+            f32::INFINITY
+        )
+    }
+
+    #[test]
+    fn test_emission_without_sky() {
+        let light = Vector4::new(4.0, 2.0, 1.0, 0.0);
+        let mut scene = RenderableList::<Pcg64Mcg>::new();
+        scene.push(Box::new(Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 1.5, Arc::new(DiffuseLight::new(light)))));
+        let camera = test_camera(9, 4).with_background(Background::None);
+        let image = camera.render(&mut Pcg64Mcg::new(0xcafef00dd15ea5e5), &scene, &LightList::new());
+
+        // The centre pixel only sees the light, the corner pixels only see the (black) background.
+        assert_eq!(image.pixel(4, 4), light);
+        assert_eq!(image.pixel(0, 0), Vector4::new(0.0, 0.0, 0.0, 0.0));
+        assert_eq!(image.pixel(8, 8), Vector4::new(0.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_motion_blur() {
+        // A sphere crossing the view from x = -2 to x = 2 while the shutter is open covers the centre of the image for
+        // about a quarter of the time, and is not seen there at all by a camera whose shutter only opens at time zero.
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        let sphere = MovingSphere::<Pcg64Mcg>::new(Vector4::new(-2.0, 0.0, 0.0, 0.0), Vector4::new(2.0, 0.0, 0.0, 0.0), 0.0, 1.0, 0.5, material);
+        let camera = test_camera(99, 1);
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        assert!((0..1000).all(|_| sphere.intersect(camera.ray(&mut rng, 49, 49), 0.001, f32::INFINITY).is_none()));
+
+        const SAMPLE_COUNT: usize = 100000;
+        let camera = camera.with_shutter(0.0, 1.0);
+        let mut hits = 0;
+        for _ in 0..SAMPLE_COUNT {
+            let r = camera.ray(&mut rng, 49, 49);
+            assert!((0.0..1.0).contains(&r.time));
+            hits += sphere.intersect(r, 0.001, f32::INFINITY).is_some() as usize;
+        }
+        let fraction = hits as f32 / SAMPLE_COUNT as f32;
+        assert!((fraction - 0.25).abs() < 0.01, "the sphere covers the centre for {} of the time", fraction);
+    }
+
+    #[test]
+    fn test_render_concurrent_fills_every_tile() {
+        // Every primary ray sees either the light or the background, so the image is deterministic whichever thread
+        // renders a tile. Pixels that no tile covers would stay black.
+        let light = Vector4::new(4.0, 2.0, 1.0, 0.0);
+        let mut scene = RenderableList::<Pcg64Mcg>::new();
+        scene.push(Box::new(Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 1.5, Arc::new(DiffuseLight::new(light)))));
+        let scene = Arc::new(scene);
+        let background = Vector4::new(0.5, 0.5, 0.5, 0.0);
+        let camera = test_camera(23, 1).with_background(Background::Solid(background));
+        let expected = camera.render(&mut Pcg64Mcg::new(0xcafef00dd15ea5e5), &*scene, &LightList::new());
+        for (thread_count, tile_size) in [(1, 32), (3, 4), (8, 5), (0, 1)] {
+            let image = camera.with_tile_size(tile_size).render_concurrent(0xcafef00dd15ea5e5, scene.clone(), Arc::new(LightList::new()), thread_count);
+            for i in 0..23 {
+                for j in 0..23 {
+                    let pixel = image.pixel(i, j);
+                    assert!(pixel == light || pixel == background, "{:?} at ({}, {})", pixel, i, j);
+                }
+            }
+            assert_eq!(image.pixel(11, 11), expected.pixel(11, 11));
+            assert_eq!(image.pixel(22, 22), expected.pixel(22, 22));
+        }
+    }
+
+    #[test]
+    fn test_render_concurrent_is_reproducible() {
+        // Noisy diffuse lighting, so that any difference in the random numbers used for a pixel changes its colour.
+        let mut scene = RenderableList::<Pcg64Mcg>::new();
+        scene.push(Box::new(Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 1.0, Arc::new(Lambertian::new(Vector4::new(0.8, 0.6, 0.4, 0.0))))));
+        scene.push(Box::new(Sphere::new(Vector4::new(0.0, 0.0, -101.0, 0.0), 100.0, Arc::new(Diffuse::new(Vector4::new(0.5, 0.5, 0.5, 0.0))))));
+        let scene = Arc::new(scene);
+        let camera = test_camera(20, 3);
+        let render = |seed, thread_count, tile_size| {
+            camera.with_tile_size(tile_size).render_concurrent(seed, scene.clone(), Arc::new(LightList::new()), thread_count)
+        };
+        let expected = render(1, 1, 32);
+        for (thread_count, tile_size) in [(1, 32), (2, 7), (4, 3), (16, 1)] {
+            assert!(render(1, thread_count, tile_size) == expected, "{} threads, {}px tiles", thread_count, tile_size);
+        }
+        assert!(render(2, 4, 3) != expected);
+    }
+
+    #[test]
+    fn test_render_progressive() {
+        let mut scene = RenderableList::<Pcg64Mcg>::new();
+        scene.push(Box::new(Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 1.0, Arc::new(Lambertian::new(Vector4::new(0.8, 0.6, 0.4, 0.0))))));
+        let lights = LightList::new();
+        let camera = test_camera(12, 6);
+
+        // Passes are cut short to end at max_samples, and a snapshot is taken after each one.
+        let settings = ProgressiveSettings::new(4, 10);
+        let mut film = Film::new(12, 12);
+        let mut snapshots = Vec::new();
+        let reason = camera.render_progressive(3, &scene, &lights, 2, &settings, &mut film, &mut |film| snapshots.push(film.mean_sample_count()));
+        assert_eq!(reason, StopReason::SampleCount);
+        assert_eq!(snapshots, vec![4.0, 8.0, 10.0]);
+        assert_eq!(film.passes(), 3);
+
+        // The result does not depend on the thread count, and a single pass equals render_concurrent.
+        let mut other_film = Film::new(12, 12);
+        camera.render_progressive(3, &scene, &lights, 5, &settings, &mut other_film, &mut |_| {});
+        assert!(film == other_film);
+        let mut single_pass = Film::new(12, 12);
+        camera.render_progressive(3, &scene, &lights, 0, &ProgressiveSettings::new(6, 6), &mut single_pass, &mut |_| {});
+        assert!(camera.image(&single_pass) == camera.render_concurrent(3, Arc::new(scene), Arc::new(lights), 3));
+    }
+
+    #[test]
+    fn test_adaptive_sampling() {
+        let mut scene = RenderableList::<Pcg64Mcg>::new();
+        scene.push(Box::new(Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 1.0, Arc::new(Lambertian::new(Vector4::new(0.8, 0.6, 0.4, 0.0))))));
+        scene.push(Box::new(Sphere::new(Vector4::new(0.0, 0.0, -101.0, 0.0), 100.0, Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0))))));
+        let lights = LightList::new();
+        let camera = test_camera(10, 64).with_background(Background::Solid(Vector4::new(0.5, 0.7, 1.0, 0.0)));
+        let adaptive = camera.with_adaptive_sampling(AdaptiveSampling::new(4, 0.05));
+        let film = adaptive.render_film(7, &scene, &lights, 2, &|_, _| {});
+
+        // Pixels of the uniform background converge at once, while those of the sphere, which partly reflects the ground, keep
+        // sampling.
+        assert_eq!(film.sample_count(0, 0), 4);
+        assert_eq!(film.pixel(0, 0), Vector4::new(0.5, 0.7, 1.0, 0.0));
+        assert!(film.sample_count(5, 5) > 4);
+        assert!(film.mean_sample_count() < 64.0);
+        assert!((0..10).all(|i| (0..10).all(|j| film.sample_count(i, j) <= 64)));
+
+        // Adaptive renders are still independent of the thread count, and single-threaded renders sample alike.
+        assert!(adaptive.render_film(7, &scene, &lights, 5, &|_, _| {}) == film);
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        assert_eq!(adaptive.render(&mut rng, &scene, &lights).pixel(0, 0), film.pixel(0, 0));
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let mut scene = RenderableList::<Pcg64Mcg>::new();
+        scene.push(Box::new(Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 1.0, Arc::new(Lambertian::new(Vector4::new(0.8, 0.6, 0.4, 0.0))))));
+        let lights = LightList::new();
+        let camera = test_camera(10, 1);
+        let settings = ProgressiveSettings::new(3, 9);
+        let mut uninterrupted = Film::new(10, 10);
+        camera.render_progressive(5, &scene, &lights, 2, &settings, &mut uninterrupted, &mut |_| {});
+
+        // Interrupt the render after its first pass, save a checkpoint and resume from it on a different number of threads.
+        let mut data = Vec::new();
+        let interrupted = ProgressiveSettings::new(3, 9).with_time_budget(Duration::ZERO);
+        let mut film = Film::new(10, 10);
+        camera.render_progressive(5, &scene, &lights, 2, &interrupted, &mut film, &mut |_| {});
+        assert_eq!(film.passes(), 1);
+        Checkpoint::new(5, 3, film).write(&mut data).unwrap();
+
+        let mut checkpoint = Checkpoint::read(&data).unwrap();
+        let settings = ProgressiveSettings::new(checkpoint.samples_per_pass, 9);
+        camera.render_progressive(checkpoint.seed, &scene, &lights, 3, &settings, &mut checkpoint.film, &mut |_| {});
+        assert_eq!(checkpoint.film.passes(), 3);
+        assert!(checkpoint.film == uninterrupted);
+    }
+
+    #[test]
+    fn test_render_progressive_stop_conditions() {
+        let mut scene = RenderableList::<Pcg64Mcg>::new();
+        scene.push(Box::new(Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 1.0, Arc::new(Lambertian::new(Vector4::new(0.8, 0.6, 0.4, 0.0))))));
+        let lights = LightList::new();
+        let camera = test_camera(8, 1);
+
+        let settings = ProgressiveSettings::new(2, 1000).with_time_budget(Duration::ZERO);
+        let mut film = Film::new(8, 8);
+        assert_eq!(camera.render_progressive(1, &scene, &lights, 0, &settings, &mut film, &mut |_| {}), StopReason::TimeBudget);
+        assert_eq!(film.passes(), 1);
+
+        // The noise falls as samples are added.
+        let settings = ProgressiveSettings::new(2, 1000).with_noise_threshold(0.01);
+        let mut film = Film::new(8, 8);
+        let mut noise = Vec::new();
+        let reason = camera.render_progressive(1, &scene, &lights, 0, &settings, &mut film, &mut |film| noise.push(film.noise()));
+        assert_eq!(reason, StopReason::NoiseThreshold);
+        assert!(film.passes() > 1 && film.passes() < 500);
+        assert!(noise.last().unwrap() <= &0.01 && noise[0] > 0.01);
+
+        // Snapshots are only taken once the interval has passed.
+        let settings = ProgressiveSettings::new(1, 3).with_snapshot_interval(Duration::from_secs(3600));
+        let mut snapshot_count = 0;
+        camera.render_progressive(1, &scene, &lights, 0, &settings, &mut Film::new(8, 8), &mut |_| snapshot_count += 1);
+        assert_eq!(snapshot_count, 0);
+    }
+
+    #[test]
+    fn test_emission_is_attenuated() {
+        // A diffuse sphere lit only by a surrounding two-sided light sphere, so every path ends at the light.
+        let light = Vector4::new(1.0, 1.0, 1.0, 0.0);
+        let albedo = Vector4::new(0.5, 0.25, 0.125, 0.0);
+        let mut scene = RenderableList::<Pcg64Mcg>::new();
+        scene.push(Box::new(Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 1.5, Arc::new(Lambertian::new(albedo)))));
+        scene.push(Box::new(Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 10.0, Arc::new(DiffuseLight::two_sided(light)))));
+        let camera = test_camera(9, 4).with_background(Background::None);
+        let image = camera.render(&mut Pcg64Mcg::new(0xcafef00dd15ea5e5), &scene, &LightList::new());
+
+        // Rays leaving the diffuse sphere can not hit it again, so they are attenuated exactly once.
+        assert_eq!(image.pixel(4, 4), albedo * light);
+        assert_eq!(image.pixel(0, 0), light);
+    }
+
+    #[test]
+    fn test_direct_light_sampling() {
+        // A small light above a large diffuse ground sphere, which is locally flat and can not see itself, so all light
+        // arriving at the ground comes directly from the light. The light subtends a cone of half-angle alpha with
+        // sin(alpha) = r / d about a direction at angle theta to the normal, so the reflected radiance is
+        // albedo / pi * Le * pi * sin^2(alpha) * cos(theta).
+        let albedo = 0.5;
+        let le = 10.0;
+        let light = Arc::new(Sphere::<Pcg64Mcg>::new(
+            Vector4::new(2.0, 0.0, 3.0, 0.0),
+            0.5,
+            Arc::new(DiffuseLight::new(Vector4::new(le, le, le, 0.0)))
+        ));
+        let mut scene = RenderableList::<Pcg64Mcg>::new();
+        scene.push(Box::new(Sphere::new(
+            Vector4::new(0.0, 0.0, -1000.0, 0.0),
+            1000.0,
+            Arc::new(Lambertian::new(Vector4::new(albedo, albedo, albedo, 0.0)))
+        )));
+        scene.push(Box::new(light.clone()));
+        let mut lights = LightList::new();
+        lights.push(light);
+        let expected = albedo * le * 0.25 / 13.0 * 3.0 / 13.0_f32.sqrt();
+
+        let camera = test_camera(1, 1).with_background(Background::None);
+        let r = Ray::new(Vector4::new(-1.0, 0.0, 1.0, 0.0), Vector4::new(1.0, 0.0, -1.0, 0.0));
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        const SAMPLE_COUNT: usize = 100000;
+        let mut estimate = |lights: &LightList<Pcg64Mcg>| {
+            let samples: Vec<f32> = (0..SAMPLE_COUNT).map(|_| camera.ray_color(&mut rng, r, &scene, lights).x()).collect();
+            let mean = samples.iter().sum::<f32>() / SAMPLE_COUNT as f32;
+            let variance = samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f32>() / (SAMPLE_COUNT - 1) as f32;
+            (mean, variance)
+        };
+        let (mean_nee, variance_nee) = estimate(&lights);
+        let (mean_bsdf, variance_bsdf) = estimate(&LightList::new());
+
+        assert!((mean_nee / expected - 1.0).abs() < 0.02, "{} != {}", mean_nee, expected);
+        assert!((mean_bsdf / expected - 1.0).abs() < 0.15, "{} != {}", mean_bsdf, expected);
+        assert!(variance_nee < variance_bsdf);
+    }
+
+    #[test]
+    fn test_furnace() {
+        // A sphere inside a two-sided light enclosing it, so that radiance Le arrives at the sphere from every direction
+        // and the reflected radiance is Le times the fraction of rays that the material scatters rather than absorbs.
+        let le = 1.0;
+        let albedo = 0.8;
+        let attenuation = Vector4::new(albedo, albedo, albedo, 0.0);
+        // The ray hits the sphere at (0, -1, 0) at an angle with cosine h to the normal.
+        let h: f32 = 0.25;
+        let direction = Vector4::new(f32::sqrt(1.0 - h * h), h, 0.0, 0.0);
+        let r = Ray::new(Vector4::new(0.0, -1.0, 0.0, 0.0) - 2.0 * direction, direction);
+        // Fuzzing with radius f accepts an attempt with probability (1 + h / f) / 2, and two attempts are made.
+        let fuzzy_albedo = albedo * (1.0 - (1.0 - (1.0 + h / 2.0) / 2.0_f32).powi(2));
+        let materials: [(Arc<dyn Material<Pcg64Mcg> + Send + Sync>, f32); 3] = [
+            (Arc::new(Lambertian::new(attenuation)), albedo),
+            (Arc::new(Diffuse::new(attenuation)), albedo),
+            (Arc::new(FuzzySpecular::new(attenuation, 2.0, 2)), fuzzy_albedo)
+        ];
+
+        let camera = test_camera(1, 1).with_background(Background::None);
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        for (material, expected) in materials {
+            let light = Arc::new(Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 10.0, Arc::new(DiffuseLight::two_sided(Vector4::new(le, le, le, 0.0)))));
+            let mut scene = RenderableList::<Pcg64Mcg>::new();
+            scene.push(Box::new(Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 1.0, material)));
+            scene.push(Box::new(light.clone()));
+            let mut lights = LightList::new();
+            lights.push(light);
+
+            const SAMPLE_COUNT: usize = 100000;
+            let mean = (0..SAMPLE_COUNT).map(|_| camera.ray_color(&mut rng, r, &scene, &lights).x()).sum::<f32>() / SAMPLE_COUNT as f32;
+            assert!((mean / (expected * le) - 1.0).abs() < 0.01, "{} != {}", mean, expected * le);
+        }
+    }
+
+    #[test]
+    fn test_medium_furnace() {
+        // Rays starting inside fog that scatters without absorbing, which is enclosed by a two-sided light, arrive at the
+        // light after any number of scattering events, so the radiance is that of the light. Shadow rays towards the
+        // light must find their way through the fog like scattered rays, and paths may take many bounces.
+        let le = 1.0;
+        let white = Vector4::new(1.0, 1.0, 1.0, 0.0);
+        let phases: [Arc<dyn Material<Pcg64Mcg> + Send + Sync>; 2] = [
+            Arc::new(Isotropic::new(white)),
+            Arc::new(HenyeyGreenstein::new(white, 0.6))
+        ];
+        let camera = test_camera(1, 1).with_background(Background::None).with_max_depth(64);
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        for phase in phases {
+            let light = Arc::new(Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 10.0, Arc::new(DiffuseLight::two_sided(Vector4::new(le, le, le, 0.0)))));
+            let boundary = Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 4.0, Arc::new(crate::materials::None));
+            let mut scene = RenderableList::<Pcg64Mcg>::new();
+            scene.push(Box::new(ConstantMedium::new(boundary, 0.5, phase)));
+            scene.push(Box::new(light.clone()));
+            let mut lights = LightList::new();
+            lights.push(light);
+
+            const SAMPLE_COUNT: usize = 100000;
+            let mut scattered = 0;
+            let mut sum = 0.0;
+            for _ in 0..SAMPLE_COUNT {
+                let r = Ray::new(Vector4::new(0.0, 0.0, 0.0, 0.0), sample_unit_sphere_uniform(&mut rng)).with_time(rng.random());
+                scattered += scene.intersect(r, 0.001, f32::INFINITY).is_some_and(|hit| hit.t < 4.0) as usize;
+                sum += camera.ray_color(&mut rng, r, &scene, &lights).x();
+            }
+            let fraction = scattered as f32 / SAMPLE_COUNT as f32;
+            assert!((fraction - (1.0 - f32::exp(-2.0))).abs() < 0.01, "{} of the rays are scattered", fraction);
+            let mean = sum / SAMPLE_COUNT as f32;
+            assert!((mean / le - 1.0).abs() < 0.01, "{} != {}", mean, le);
+        }
+    }
+}
@@ -1,4 +1,166 @@
-use crate::vector4::Vector4;
+use crate::{
+    hdr::{self, ExrCompression},
+    textures::image_texture::{ImageTexture, WrapMode},
+    tiles::Tile,
+    vector4::Vector4
+};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    str::FromStr
+};
+
+/// Formats that an `Image` can be written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// ASCII PPM.
+    P3,
+    /// Binary PPM, with two bytes per sample if the colour depth of the image exceeds 255.
+    P6,
+    /// PNG with 8 bits per sample.
+    Png,
+    /// PNG with 16 bits per sample.
+    Png16,
+    /// Portable Float Map with 32-bit float samples.
+    Pfm,
+    /// Radiance RGBE.
+    Hdr,
+    /// Uncompressed OpenEXR with 32-bit float samples.
+    Exr,
+    /// ZIP compressed OpenEXR with 32-bit float samples.
+    ExrZip
+}
+
+impl ImageFormat {
+    /// Determines the format from the extension of `path`, i.e. `P6` for `.ppm`, `Png` for `.png`, `Pfm` for `.pfm`,
+    /// `Hdr` for `.hdr` and `ExrZip` for `.exr`. PNG images with a colour depth exceeding 255 are written with 16 bits per sample.
+    pub fn from_path<P: AsRef<Path>>(path: P, color_depth: usize) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(Self::P6),
+            "png" if color_depth > 255 => Some(Self::Png16),
+            "png" => Some(Self::Png),
+            "pfm" => Some(Self::Pfm),
+            "hdr" => Some(Self::Hdr),
+            "exr" => Some(Self::ExrZip),
+            _ => None
+        }
+    }
+}
+
+/// Curves compressing linear colours of unbounded brightness into `[0, 1]`, applied to each component separately.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMapOperator {
+    /// Clamps components to `[0, 1]`, leaving darker colours unchanged.
+    Clamp,
+    /// Reinhard's operator `x / (1 + x)`, which never reaches white.
+    Reinhard,
+    /// Reinhard's operator extended to map `white` (and anything brighter) to 1.
+    ReinhardExtended { white: f32 },
+    /// John Hable's filmic curve from Uncharted 2, normalized so that a linear white point of 11.2 maps to 1.
+    Hable,
+    /// Krzysztof Narkowicz's fit of the ACES filmic reference rendering transform.
+    Aces
+}
+
+impl ToneMapOperator {
+    /// Maps a non-negative linear colour component into `[0, 1]`.
+    pub fn apply(&self, x: f32) -> f32 {
+        let x = f32::max(x, 0.0);
+        let y = match *self {
+            Self::Clamp => x,
+            Self::Reinhard => x / (1.0 + x),
+            Self::ReinhardExtended { white } => x * (1.0 + x / (white * white)) / (1.0 + x),
+            Self::Hable => {
+                const WHITE: f32 = 11.2;
+                hable_partial(x) / hable_partial(WHITE)
+            },
+            Self::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+        };
+        f32::clamp(y, 0.0, 1.0)
+    }
+}
+
+/// Filmic curve of `ToneMapOperator::Hable` before normalization.
+fn hable_partial(x: f32) -> f32 {
+    const A: f32 = 0.15;    // Shoulder strength.
+    const B: f32 = 0.50;    // Linear strength.
+    const C: f32 = 0.10;    // Linear angle.
+    const D: f32 = 0.20;    // Toe strength.
+    const E: f32 = 0.02;    // Toe numerator.
+    const F: f32 = 0.30;    // Toe denominator.
+    (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F
+}
+
+/// Functions encoding linear colour components in `[0, 1]` for display.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransferFunction {
+    /// Pure power law with the given encoding exponent, see `linear_to_gamma`.
+    Gamma(f32),
+    /// Piecewise sRGB transfer function, which is linear near black and a power law with exponent `1 / 2.4` elsewhere.
+    Srgb
+}
+
+impl TransferFunction {
+    /// Encodes a linear colour component in `[0, 1]`.
+    pub fn encode(&self, l: f32) -> f32 {
+        match *self {
+            Self::Gamma(encoding_gamma) => linear_to_gamma(l, encoding_gamma),
+            Self::Srgb if l <= 0.0031308 => 12.92 * l,
+            Self::Srgb => 1.055 * l.powf(2.4_f32.recip()) - 0.055
+        }
+    }
+
+    /// Decodes an encoded colour component in `[0, 1]`, inverting `encode`.
+    pub fn decode(&self, c: f32) -> f32 {
+        match *self {
+            Self::Gamma(encoding_gamma) => gamma_to_linear(c, encoding_gamma.recip()),
+            Self::Srgb if c <= 0.04045 => c / 12.92,
+            Self::Srgb => ((c + 0.055) / 1.055).powf(2.4)
+        }
+    }
+}
+
+/// Conversion of the linear colours of a render into colours in `[0, 1]` ready to be quantized for display: the colours are
+/// scaled by the exposure, compressed by the tone map operator and then encoded by the transfer function.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToneMapping {
+    pub exposure: f32,                  // Exposure value in stops, i.e. colours are scaled by 2^exposure.
+    pub operator: ToneMapOperator,
+    pub transfer: TransferFunction
+}
+
+impl ToneMapping {
+    pub fn new(exposure: f32, operator: ToneMapOperator, transfer: TransferFunction) -> Self {
+        Self { exposure, operator, transfer }
+    }
+
+    /// Returns the display colour, with components in `[0, 1]`, of the linear colour `color`.
+    pub fn apply(&self, color: Vector4) -> [f32; 3] {
+        let scale = self.exposure.exp2();
+        [color.x(), color.y(), color.z()].map(|c| self.transfer.encode(self.operator.apply(scale * c)))
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    /// Parses the lowercase name of a format, i.e. `p3`, `p6` (or `ppm`), `png`, `png16`, `pfm`, `hdr`, `exr` or `exr-zip`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "p3" => Ok(Self::P3),
+            "p6" | "ppm" => Ok(Self::P6),
+            "png" => Ok(Self::Png),
+            "png16" => Ok(Self::Png16),
+            "pfm" => Ok(Self::Pfm),
+            "hdr" => Ok(Self::Hdr),
+            "exr" => Ok(Self::Exr),
+            "exr-zip" => Ok(Self::ExrZip),
+            _ => Err(format!("unknown image format '{}'", s))
+        }
+    }
+}
 
 /// Representation of an RGB image.
 /// `pixels` should be read in row-major order.
@@ -8,6 +170,7 @@ pub struct Image {
     height: usize,
     color_depth: usize,
     encoding_gamma: f32,
+    tone_mapping: ToneMapping,  // Used by `save` and `write` for formats that are not high dynamic range.
     pixels: Vec<Vector4>
 }
 
@@ -28,10 +191,54 @@ impl Image {
             height,
             color_depth,
             encoding_gamma,
+            tone_mapping: Self::default_tone_mapping(encoding_gamma),
             pixels
         }
     }
 
+    /// Tone mapping that clamps colours and gamma encodes them with `encoding_gamma`.
+    fn default_tone_mapping(encoding_gamma: f32) -> ToneMapping {
+        ToneMapping::new(0.0, ToneMapOperator::Clamp, TransferFunction::Gamma(encoding_gamma))
+    }
+
+    /// Loads an image, e.g. a previous render, from any of the formats supported by `ImageTexture::load`. PPM and PNG
+    /// images are gamma decoded using the reciprocal of `encoding_gamma`, while the linear colours of high dynamic range
+    /// images are used as they are.
+    pub fn load<P: AsRef<Path>>(path: P, color_depth: usize, encoding_gamma: f32) -> io::Result<Self> {
+        let texture = ImageTexture::load(path, encoding_gamma.recip(), WrapMode::Clamp)?;
+        Ok(Self {
+            width: texture.width(),
+            height: texture.height(),
+            color_depth,
+            encoding_gamma,
+            tone_mapping: Self::default_tone_mapping(encoding_gamma),
+            pixels: texture.pixels().to_vec()
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn tone_mapping(&self) -> ToneMapping {
+        self.tone_mapping
+    }
+
+    /// Sets the tone mapping used by `save` and `write`. By default, colours are clamped and gamma encoded with the
+    /// encoding gamma of the image.
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping) {
+        self.tone_mapping = tone_mapping;
+    }
+
+    /// Returns the linear colour of the pixel in row `i` and column `j`.
+    pub fn pixel(&self, i: usize, j: usize) -> Vector4 {
+        self.pixels[i * self.width + j]
+    }
+
     pub fn set_pixel(&mut self, value: Vector4, i: usize, j: usize) {
         self.pixels[i * self.width + j] = value;
     }
@@ -40,20 +247,91 @@ impl Image {
         (0..self.width).for_each(|j| self.pixels[i * self.width + j] = values[j]);
     }
 
-    pub fn write_p3_image_stdout(&self) {
-        println!("P3\n{} {}\n{}", self.width, self.height, self.color_depth);
-        self.pixels.iter()
-        .for_each(|color| {
-            let r = f32::clamp(color.x(), 0.0, 1.0);
-            let g = f32::clamp(color.y(), 0.0, 1.0);
-            let b = f32::clamp(color.z(), 0.0, 1.0);
-            println!(
-                "{} {} {}", 
-                (linear_to_gamma(r, self.encoding_gamma) * self.color_depth as f32) as usize,
-                (linear_to_gamma(g, self.encoding_gamma) * self.color_depth as f32) as usize,
-                (linear_to_gamma(b, self.encoding_gamma) * self.color_depth as f32) as usize
-            );
-        });
+    /// Sets the pixels of `tile` to `values`, given in row-major order.
+    pub fn set_tile(&mut self, values: &[Vector4], tile: Tile) {
+        for (k, row) in values.chunks_exact(tile.width).take(tile.height).enumerate() {
+            let start = (tile.row + k) * self.width + tile.column;
+            self.pixels[start..start + tile.width].copy_from_slice(row);
+        }
+    }
+
+    /// Writes the image to standard output as an ASCII PPM.
+    pub fn write_p3_image_stdout(&self) -> io::Result<()> {
+        self.write(BufWriter::new(io::stdout().lock()), ImageFormat::P3)
+    }
+
+    /// Writes the image to `path` in the format given by its extension, see `ImageFormat::from_path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.save_tone_mapped(path, &self.tone_mapping)
+    }
+
+    /// Writes the image to `path` in `format`, whatever the extension of `path`.
+    pub fn save_as<P: AsRef<Path>>(&self, path: P, format: ImageFormat) -> io::Result<()> {
+        self.write(BufWriter::new(File::create(path)?), format)
+    }
+
+    /// Writes the image to `path` like `save`, but with `tone_mapping` in place of the tone mapping of the image.
+    pub fn save_tone_mapped<P: AsRef<Path>>(&self, path: P, tone_mapping: &ToneMapping) -> io::Result<()> {
+        let format = ImageFormat::from_path(&path, self.color_depth).ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unsupported image file extension: {}", path.as_ref().display())
+        ))?;
+        self.write_tone_mapped(BufWriter::new(File::create(path)?), format, tone_mapping)
+    }
+
+    /// Writes the image to `writer` in `format`. PPM and PNG images are tone mapped, with PPM images using the colour
+    /// depth of the image as their maximum value and PNG images the full range of their sample size. High dynamic range
+    /// formats store the linear colours without clamping them.
+    pub fn write<W: Write>(&self, writer: W, format: ImageFormat) -> io::Result<()> {
+        self.write_tone_mapped(writer, format, &self.tone_mapping)
+    }
+
+    /// Writes the image to `writer` like `write`, but with `tone_mapping` in place of the tone mapping of the image.
+    pub fn write_tone_mapped<W: Write>(&self, mut writer: W, format: ImageFormat, tone_mapping: &ToneMapping) -> io::Result<()> {
+        let encode = |color: Vector4, max_value: usize| tone_mapping.apply(color).map(|c| (c * max_value as f32) as usize);
+        match format {
+            ImageFormat::P3 => {
+                writeln!(writer, "P3\n{} {}\n{}", self.width, self.height, self.color_depth)?;
+                for color in &self.pixels {
+                    let [r, g, b] = encode(*color, self.color_depth);
+                    writeln!(writer, "{} {} {}", r, g, b)?;
+                }
+            },
+            ImageFormat::P6 => {
+                write!(writer, "P6\n{} {}\n{}\n", self.width, self.height, self.color_depth)?;
+                let sample_size = if self.color_depth > 255 { 2 } else { 1 };
+                let mut data = Vec::with_capacity(self.pixels.len() * 3 * sample_size);
+                for color in &self.pixels {
+                    for sample in encode(*color, self.color_depth) {
+                        if sample_size == 2 {
+                            data.extend_from_slice(&(sample as u16).to_be_bytes());
+                        } else {
+                            data.push(sample as u8);
+                        }
+                    }
+                }
+                writer.write_all(&data)?;
+            },
+            ImageFormat::Png | ImageFormat::Png16 => {
+                let mut encoder = png::Encoder::new(&mut writer, self.width as u32, self.height as u32);
+                encoder.set_color(png::ColorType::Rgb);
+                let data: Vec<u8> = if format == ImageFormat::Png16 {
+                    encoder.set_depth(png::BitDepth::Sixteen);
+                    self.pixels.iter().flat_map(|c| encode(*c, 65535)).flat_map(|s| (s as u16).to_be_bytes()).collect()
+                } else {
+                    encoder.set_depth(png::BitDepth::Eight);
+                    self.pixels.iter().flat_map(|c| encode(*c, 255)).map(|s| s as u8).collect()
+                };
+                let mut png_writer = encoder.write_header().map_err(io::Error::other)?;
+                png_writer.write_image_data(&data).map_err(io::Error::other)?;
+                png_writer.finish().map_err(io::Error::other)?;
+            },
+            ImageFormat::Pfm => hdr::write_pfm(&mut writer, self.width, self.height, &self.pixels)?,
+            ImageFormat::Hdr => hdr::write_hdr(&mut writer, self.width, self.height, &self.pixels)?,
+            ImageFormat::Exr => hdr::write_exr(&mut writer, self.width, self.height, &self.pixels, ExrCompression::None)?,
+            ImageFormat::ExrZip => hdr::write_exr(&mut writer, self.width, self.height, &self.pixels, ExrCompression::Zip)?
+        }
+        writer.flush()
     }
 }
 
@@ -62,7 +340,202 @@ pub fn lerp(a: Vector4, b: Vector4, t: f32) -> Vector4 {
     a + t * (b - a)
 }
 
+/// Returns the relative luminance of a linear colour with Rec. 709 primaries.
+pub fn luminance(color: Vector4) -> f32 {
+    0.2126 * color.x() + 0.7152 * color.y() + 0.0722 * color.z()
+}
+
 /// Perform gamma compression on a linear colour component.
 pub fn linear_to_gamma(l: f32, encoding_gamma: f32) -> f32 {
     l.powf(encoding_gamma)
-}
\ No newline at end of file
+}
+
+/// Perform gamma expansion on a gamma-compressed colour component.
+pub fn gamma_to_linear(c: f32, decoding_gamma: f32) -> f32 {
+    c.powf(decoding_gamma)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_image(color_depth: usize) -> Image {
+        let mut image = Image::new(2, 1, color_depth, 1.0);
+        image.set_pixel(Vector4::new(1.0, 0.5, 0.0, 0.0), 0, 0);
+        image.set_pixel(Vector4::new(2.0, -1.0, 0.25, 0.0), 0, 1);
+        image
+    }
+
+    fn decode_png(bytes: &[u8]) -> (png::BitDepth, Vec<u8>) {
+        let mut reader = png::Decoder::new(bytes).read_info().unwrap();
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).unwrap();
+        assert_eq!((info.width, info.height, info.color_type), (2, 1, png::ColorType::Rgb));
+        buffer.truncate(info.buffer_size());
+        (info.bit_depth, buffer)
+    }
+
+    #[test]
+    fn test_write_ppm() {
+        let mut p3 = Vec::new();
+        test_image(255).write(&mut p3, ImageFormat::P3).unwrap();
+        assert_eq!(String::from_utf8(p3).unwrap(), "P3\n2 1\n255\n255 127 0\n255 0 63\n");
+
+        let mut p6 = Vec::new();
+        test_image(255).write(&mut p6, ImageFormat::P6).unwrap();
+        assert_eq!(p6, b"P6\n2 1\n255\n\xff\x7f\x00\xff\x00\x3f");
+
+        let mut p6 = Vec::new();
+        test_image(65535).write(&mut p6, ImageFormat::P6).unwrap();
+        assert_eq!(p6, b"P6\n2 1\n65535\n\xff\xff\x7f\xff\x00\x00\xff\xff\x00\x00\x3f\xff");
+    }
+
+    #[test]
+    fn test_write_png() {
+        let mut png = Vec::new();
+        test_image(255).write(&mut png, ImageFormat::Png).unwrap();
+        assert_eq!(decode_png(&png), (png::BitDepth::Eight, vec![255, 127, 0, 255, 0, 63]));
+
+        let mut png = Vec::new();
+        test_image(255).write(&mut png, ImageFormat::Png16).unwrap();
+        assert_eq!(decode_png(&png), (png::BitDepth::Sixteen, vec![255, 255, 127, 255, 0, 0, 255, 255, 0, 0, 63, 255]));
+    }
+
+    #[test]
+    fn test_tone_map_operators() {
+        let assert_close = |a: f32, b: f32| assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+        assert_eq!(ToneMapOperator::Clamp.apply(0.25), 0.25);
+        assert_eq!(ToneMapOperator::Clamp.apply(2.0), 1.0);
+        assert_eq!(ToneMapOperator::Clamp.apply(-1.0), 0.0);
+
+        assert_eq!(ToneMapOperator::Reinhard.apply(1.0), 0.5);
+        assert_eq!(ToneMapOperator::Reinhard.apply(3.0), 0.75);
+
+        let extended = ToneMapOperator::ReinhardExtended { white: 4.0 };
+        assert_eq!(extended.apply(1.0), 0.53125);
+        assert_eq!(extended.apply(4.0), 1.0);
+        assert_eq!(extended.apply(8.0), 1.0);
+
+        assert_close(ToneMapOperator::Hable.apply(0.0), 0.0);
+        assert_close(ToneMapOperator::Hable.apply(1.0), 0.3043006);
+        assert_close(ToneMapOperator::Hable.apply(4.0), 0.713238);
+        assert_close(ToneMapOperator::Hable.apply(11.2), 1.0);
+
+        assert_eq!(ToneMapOperator::Aces.apply(0.0), 0.0);
+        assert_close(ToneMapOperator::Aces.apply(1.0), 0.8037975);
+        assert_eq!(ToneMapOperator::Aces.apply(100.0), 1.0);
+
+        // All operators are monotonic.
+        for operator in [ToneMapOperator::Reinhard, extended, ToneMapOperator::Hable, ToneMapOperator::Aces] {
+            let values: Vec<f32> = (0..100).map(|i| operator.apply(i as f32 * 0.1)).collect();
+            assert!(values.windows(2).all(|w| w[0] <= w[1]), "{:?}", operator);
+        }
+    }
+
+    #[test]
+    fn test_transfer_functions() {
+        let assert_close = |a: f32, b: f32| assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+        assert_close(TransferFunction::Srgb.encode(0.5), 0.735357);
+        assert_close(TransferFunction::Srgb.encode(0.18), 0.4613561);
+        assert_close(TransferFunction::Srgb.encode(0.002), 0.02584);
+        assert_eq!(TransferFunction::Srgb.encode(0.0), 0.0);
+        assert_close(TransferFunction::Srgb.encode(1.0), 1.0);
+        assert_eq!(TransferFunction::Gamma(0.5).encode(0.25), 0.5);
+        for transfer in [TransferFunction::Srgb, TransferFunction::Gamma(2.2_f32.recip())] {
+            for l in [0.0, 0.001, 0.0031308, 0.2, 0.7, 1.0] {
+                assert_close(transfer.decode(transfer.encode(l)), l);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tone_mapping() {
+        // One stop of exposure doubles the colour before the operator is applied.
+        let tone_mapping = ToneMapping::new(1.0, ToneMapOperator::Reinhard, TransferFunction::Gamma(1.0));
+        assert_eq!(tone_mapping.apply(Vector4::new(0.5, 1.5, 0.0, 0.0)), [0.5, 0.75, 0.0]);
+
+        // Each output may use its own tone mapping.
+        let mut image = test_image(255);
+        let mut p3 = Vec::new();
+        image.write_tone_mapped(&mut p3, ImageFormat::P3, &tone_mapping).unwrap();
+        assert_eq!(String::from_utf8(p3).unwrap(), "P3\n2 1\n255\n170 127 0\n204 0 85\n");
+        // The tone mapping of the image is unchanged.
+        let mut p3 = Vec::new();
+        image.write(&mut p3, ImageFormat::P3).unwrap();
+        assert_eq!(String::from_utf8(p3).unwrap(), "P3\n2 1\n255\n255 127 0\n255 0 63\n");
+        image.set_tone_mapping(tone_mapping);
+        let mut p3 = Vec::new();
+        image.write(&mut p3, ImageFormat::P3).unwrap();
+        assert_eq!(String::from_utf8(p3).unwrap(), "P3\n2 1\n255\n170 127 0\n204 0 85\n");
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(ImageFormat::from_path("render.ppm", 255), Some(ImageFormat::P6));
+        assert_eq!(ImageFormat::from_path("render.PNG", 255), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::from_path("out/render.png", 65535), Some(ImageFormat::Png16));
+        assert_eq!(ImageFormat::from_path("render.pfm", 255), Some(ImageFormat::Pfm));
+        assert_eq!(ImageFormat::from_path("render.hdr", 255), Some(ImageFormat::Hdr));
+        assert_eq!(ImageFormat::from_path("render.exr", 255), Some(ImageFormat::ExrZip));
+        assert_eq!(ImageFormat::from_path("render.jpg", 255), None);
+        assert_eq!(ImageFormat::from_path("render", 255), None);
+        assert_eq!("exr-zip".parse(), Ok(ImageFormat::ExrZip));
+        assert_eq!("ppm".parse(), Ok(ImageFormat::P6));
+        assert!("jpg".parse::<ImageFormat>().is_err());
+    }
+
+    #[test]
+    fn test_save() {
+        let path = std::env::temp_dir().join(format!("color_test_save_{}.png", std::process::id()));
+        test_image(255).save(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(decode_png(&bytes).1, vec![255, 127, 0, 255, 0, 63]);
+
+        let error = test_image(255).save("render.txt").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        // High dynamic range formats keep the unclamped linear colours, up to the precision of RGBE.
+        for (extension, tolerance) in [("pfm", 0.0), ("exr", 0.0), ("hdr", 0.01), ("png", 0.0)] {
+            let path = std::env::temp_dir().join(format!("color_test_load_{}.{}", std::process::id(), extension));
+            let image = test_image(255);
+            image.save(&path).unwrap();
+            let loaded = Image::load(&path, 255, 1.0).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!((loaded.width(), loaded.height()), (2, 1));
+            let expected = if extension == "png" {
+                // 8-bit PNG clamps and quantizes the colours.
+                [Vector4::new(1.0, 127.0 / 255.0, 0.0, 0.0), Vector4::new(1.0, 0.0, 63.0 / 255.0, 0.0)]
+            } else {
+                // Negative components are not representable in RGBE.
+                let clamp = |c: Vector4| if extension == "hdr" { Vector4::new(c.x().max(0.0), c.y().max(0.0), c.z().max(0.0), 0.0) } else { c };
+                [clamp(image.pixel(0, 0)), clamp(image.pixel(0, 1))]
+            };
+            for (j, expected) in expected.into_iter().enumerate() {
+                assert!((loaded.pixel(0, j) - expected).norm() <= tolerance * 2.0 + 1e-6, "{}: {:?} != {:?}", extension, loaded.pixel(0, j), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_errors_are_returned() {
+        struct BrokenPipe;
+        impl Write for BrokenPipe {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::Error::from(io::ErrorKind::BrokenPipe))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        for format in [
+            ImageFormat::P3, ImageFormat::P6, ImageFormat::Png, ImageFormat::Png16,
+            ImageFormat::Pfm, ImageFormat::Hdr, ImageFormat::Exr, ImageFormat::ExrZip
+        ] {
+            assert!(test_image(255).write(BrokenPipe, format).is_err());
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{
+    aabb::{Aabb, Bounded},
+    intersectable::{HitRecord, Intersectable},
+    materials::{Material, Tangible},
+    random::stream_seed,
+    ray::Ray
+};
+use rand::Rng;
+use std::sync::Arc;
+
+// Distance along a ray past a point where it crosses the boundary from which the next crossing is searched.
+const BOUNDARY_EPSILON: f32 = 1e-4;
+
+/// Homogeneous participating medium, e.g. fog or smoke, filling the inside of the closed surface `boundary`.
+///
+/// Rays passing through the medium are scattered after a free-flight distance drawn from an exponential distribution
+/// with rate `density`, so that a fraction `exp(-density * d)` of them travels a distance `d` through it unscattered.
+/// Scattered rays continue in a direction sampled from the phase function `phase`, e.g. `Isotropic`, which is returned
+/// as the material of the hits in the medium.
+///
+/// Rays may start inside the medium, e.g. those scattered in it or those of a camera placed in fog, and the boundary may
+/// be concave, in which case the distance is only counted while the ray is inside it. The boundary itself is never hit,
+/// so it may be given any material, and a medium may fill a surface that is also part of the scene, e.g. the inside of
+/// a glass sphere.
+#[derive(Clone)]
+pub struct ConstantMedium<T, R: Rng + ?Sized> {
+    pub boundary: T,
+    pub density: f32,
+    phase: Arc<dyn Material<R> + Send + Sync>
+}
+
+impl<T, R: Rng + ?Sized> ConstantMedium<T, R> {
+    /// Panics if `density` is not positive.
+    pub fn new(
+        boundary: T,
+        density: f32,
+        phase: Arc<dyn Material<R> + Send + Sync>
+    ) -> Self {
+        assert!(density > 0.0, "the density of a medium must be positive");
+        Self { boundary, density, phase }
+    }
+}
+
+impl<R: Rng + ?Sized, T: Intersectable<R> + Bounded + Send + Sync> Intersectable<R> for ConstantMedium<T, R> {
+    /// Finds the point at which `r` is scattered by the medium, if it is scattered within `[t_min, t_max]`. The normal
+    /// of the hit points against the ray, and its surface coordinates are zero.
+    ///
+    /// Intersection tests have no random number generator, so the free-flight distance is instead drawn from a hash of
+    /// the ray and of the point where it first crosses the boundary. The hash is uniform over the rays of a render, and
+    /// keeps the test deterministic like those of surfaces, so that renders remain reproducible.
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        let first = self.boundary.intersect(r, f32::NEG_INFINITY, f32::INFINITY)?.t;
+        let length = r.direction.norm();
+        let mut distance = -f32::ln(1.0 - ray_hash(r, first)) / self.density;
+
+        // Walk along the ray through the stretches inside the boundary, each between two consecutive crossings.
+        let mut entry = first;
+        loop {
+            let exit = self.boundary.intersect(r, entry + BOUNDARY_EPSILON, f32::INFINITY)?.t;
+            let (start, end) = (f32::max(entry, t_min), f32::min(exit, t_max));
+            if start < end {
+                let stretch = (end - start) * length;
+                if distance < stretch {
+                    let t = start + distance / length;
+                    return Some(HitRecord::new(r, t, -r.direction / length, (0.0, 0.0), self));
+                }
+                distance -= stretch;
+            }
+            if exit >= t_max {
+                return None;
+            }
+            entry = self.boundary.intersect(r, exit + BOUNDARY_EPSILON, f32::INFINITY)?.t;
+        }
+    }
+}
+
+impl<R: Rng + ?Sized, T: Bounded> Bounded for ConstantMedium<T, R> {
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+}
+
+impl<R: Rng + ?Sized, T: Intersectable<R> + Bounded + Send + Sync> Tangible<R> for ConstantMedium<T, R> {
+    fn material(&self) -> &Arc<dyn Material<R> + Send + Sync> {
+        &self.phase
+    }
+}
+
+/// Returns a number in `[0, 1)` derived from the bits of `r` and `t`, by scrambling them one after another like the
+/// seeds of random number streams.
+fn ray_hash(r: Ray, t: f32) -> f32 {
+    let words = [
+        r.origin.x(), r.origin.y(), r.origin.z(),
+        r.direction.x(), r.direction.y(), r.direction.z(),
+        r.time, t
+    ];
+    let hash = words.iter().fold(0, |hash, word| stream_seed(hash, word.to_bits() as u64));
+    // The 24 high bits fill the mantissa of an f32 exactly.
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        materials::{self, isotropic::Isotropic, lambertian::Lambertian},
+        random::sample_unit_sphere_uniform,
+        renderable_list::RenderableList,
+        surfaces::{sphere::Sphere, torus::Torus},
+        vector4::Vector4
+    };
+    use rand_pcg::Pcg64Mcg;
+    use std::f32::consts::PI;
+
+    fn fog(boundary: Sphere<Pcg64Mcg>, density: f32) -> ConstantMedium<Sphere<Pcg64Mcg>, Pcg64Mcg> {
+        ConstantMedium::new(boundary, density, Arc::new(Isotropic::new(Vector4::new(0.5, 0.5, 0.5, 0.0))))
+    }
+
+    fn boundary(radius: f32) -> Sphere<Pcg64Mcg> {
+        Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), radius, Arc::new(materials::None))
+    }
+
+    /// Returns the fraction of rays in random directions through the centre of `medium` that are not scattered before
+    /// travelling `max_distance`, where each ray starts `distance` before the centre.
+    fn transmittance<T: Intersectable<Pcg64Mcg>>(medium: &T, rng: &mut Pcg64Mcg, distance: f32, max_distance: f32) -> f32 {
+        const SAMPLE_COUNT: usize = 100000;
+        let unscattered = (0..SAMPLE_COUNT)
+            .filter(|_| {
+                let direction = sample_unit_sphere_uniform(rng);
+                let r = Ray::new(-distance * direction, rng.random_range(0.5..2.0) * direction);
+                medium.intersect(r, 0.0, max_distance / r.direction.norm()).is_none()
+            })
+            .count();
+        unscattered as f32 / SAMPLE_COUNT as f32
+    }
+
+    #[test]
+    fn test_transmittance() {
+        // Rays through the centre of a sphere of radius 2 travel 4 through it from outside, and less when they start
+        // inside it or stop at the centre.
+        let medium = fog(boundary(2.0), 0.3);
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        for (distance, max_distance, travelled) in [(10.0, f32::INFINITY, 4.0), (1.0, f32::INFINITY, 3.0), (0.0, f32::INFINITY, 2.0), (10.0, 10.0, 2.0)] {
+            let fraction = transmittance(&medium, &mut rng, distance, max_distance);
+            let expected = f32::exp(-0.3 * travelled);
+            assert!((fraction - expected).abs() < 0.01, "{} != {} from {}", fraction, expected, distance);
+        }
+    }
+
+    #[test]
+    fn test_concave_boundary() {
+        // Rays through the centre of a torus cross its tube twice, travelling 2 through the medium.
+        let material = Arc::new(materials::None);
+        let torus = Torus::<Pcg64Mcg>::new(Vector4::new(0.0, 0.0, 0.0, 0.0), Vector4::new(0.0, 0.0, 1.0, 0.0), 2.0, 0.5, material);
+        let medium = ConstantMedium::new(torus, 0.5, Arc::new(Isotropic::new(Vector4::new(0.5, 0.5, 0.5, 0.0))));
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        let mut unscattered = 0;
+        for _ in 0..100000 {
+            let angle = rng.random_range(0.0..2.0 * PI);
+            let direction = Vector4::new(angle.cos(), angle.sin(), 0.0, 0.0);
+            let r = Ray::new(-10.0 * direction, direction).with_time(rng.random());
+            match medium.intersect(r, 0.001, f32::INFINITY) {
+                Some(hit) => {
+                    let radius = Vector4::new(hit.p.x(), hit.p.y(), 0.0, 0.0).norm();
+                    assert!((radius - 2.0).abs() <= 0.5 + 1e-4, "{:?} is outside the medium", hit.p);
+                    assert!(hit.front_face);
+                },
+                None => unscattered += 1
+            }
+        }
+        let expected = f32::exp(-0.5 * 2.0);
+        assert!((unscattered as f32 / 100000.0 - expected).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_closest_hit() {
+        // A solid sphere inside the fog is hit unless the ray is scattered before reaching it, and the scene returns
+        // whichever comes first. Intersections with the same ray are repeatable.
+        let mut scene = RenderableList::<Pcg64Mcg>::new();
+        scene.push(Box::new(fog(boundary(3.0), 1.0)));
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        scene.push(Box::new(Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 1.0, material)));
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        let mut solid_hits = 0;
+        for _ in 0..10000 {
+            let r = Ray::new(Vector4::new(-10.0, 0.0, 0.0, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0)).with_time(rng.random());
+            let hit = scene.intersect(r, 0.001, f32::INFINITY).unwrap();
+            assert!(hit.t >= 7.0 && hit.t <= 9.0);
+            assert_eq!(scene.intersect(r, 0.001, f32::INFINITY).unwrap().t, hit.t);
+            solid_hits += (hit.t == 9.0) as usize;
+        }
+        let expected = f32::exp(-2.0);
+        assert!((solid_hits as f32 / 10000.0 - expected).abs() < 0.02);
+    }
+}
@@ -0,0 +1,400 @@
+use crate::{
+    color::{Image, lerp, luminance},
+    tiles::Tile,
+    vector4::Vector4
+};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path
+};
+
+// Luminance added to the mean of a pixel when estimating its relative error, so that dark pixels do not dominate the noise.
+const NOISE_LUMINANCE_OFFSET: f32 = 0.01;
+
+/// First bytes of a checkpoint file, ending in the version of the format.
+pub const CHECKPOINT_MAGIC: &[u8; 8] = b"RTCHKPT1";
+const CHECKPOINT_HEADER_SIZE: usize = CHECKPOINT_MAGIC.len() + 5 * 8;
+const CHECKPOINT_PIXEL_SIZE: usize = 5 * 4;
+
+/// Running sums of the samples taken of each pixel of an image, from which the image and an estimate of its noise are
+/// computed. Samples are added in passes, so that a render can be stopped and looked at after any pass.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Film {
+    width: usize,
+    height: usize,
+    sums: Vec<Vector4>,             // Sum of the samples of each pixel.
+    luminance_squares: Vec<f32>,    // Sum of the squared luminances of the samples of each pixel.
+    sample_counts: Vec<u32>,
+    passes: usize                   // Number of passes added so far.
+}
+
+/// Samples taken of a pixel in one pass.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PixelSamples {
+    pub sum: Vector4,
+    pub luminance_squares: f32,
+    pub count: u32
+}
+
+impl PixelSamples {
+    pub fn new() -> Self {
+        Self { sum: Vector4::new(0.0, 0.0, 0.0, 0.0), luminance_squares: 0.0, count: 0 }
+    }
+
+    pub fn add(&mut self, sample: Vector4) {
+        self.sum += sample;
+        self.luminance_squares += luminance(sample).powi(2);
+        self.count += 1;
+    }
+}
+
+impl Default for PixelSamples {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Running mean and variance of a sequence of values, updated one value at a time with Welford's algorithm, which unlike
+/// sums of the values and their squares does not lose precision when the variance is small compared to the mean.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RunningStatistics {
+    count: u32,
+    mean: f32,
+    m2: f32     // Sum of the squared differences of the values from the mean.
+}
+
+impl RunningStatistics {
+    pub fn new() -> Self {
+        Self { count: 0, mean: 0.0, m2: 0.0 }
+    }
+
+    pub fn add(&mut self, value: f32) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn mean(&self) -> f32 {
+        self.mean
+    }
+
+    /// Returns the sample variance of the values, or zero if there are fewer than two.
+    pub fn variance(&self) -> f32 {
+        if self.count < 2 { 0.0 } else { self.m2 / (self.count - 1) as f32 }
+    }
+
+    /// Estimates the standard error of the mean divided by the mean, like `Film::relative_error`. Returns infinity if there
+    /// are fewer than two values.
+    pub fn relative_error(&self) -> f32 {
+        if self.count < 2 {
+            return f32::INFINITY;
+        }
+        (self.variance() / self.count as f32).sqrt() / (self.mean.abs() + NOISE_LUMINANCE_OFFSET)
+    }
+}
+
+/// Saved state of an unfinished progressive render, from which it can be resumed. The random number generators of each pass
+/// are seeded from the seed and the number of passes before it, so a resumed render continued with the same number of
+/// samples per pass gives exactly the same film as an uninterrupted one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checkpoint {
+    pub seed: u64,
+    pub samples_per_pass: usize,
+    pub film: Film
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            sums: vec![Vector4::new(0.0, 0.0, 0.0, 0.0); width * height],
+            luminance_squares: vec![0.0; width * height],
+            sample_counts: vec![0; width * height],
+            passes: 0
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the number of passes completed so far, see `finish_pass`.
+    pub fn passes(&self) -> usize {
+        self.passes
+    }
+
+    /// Returns the number of samples taken of the pixel in row `i` and column `j`.
+    pub fn sample_count(&self, i: usize, j: usize) -> u32 {
+        self.sample_counts[i * self.width + j]
+    }
+
+    /// Returns the mean of the samples of the pixel in row `i` and column `j`, or black if it has no samples.
+    pub fn pixel(&self, i: usize, j: usize) -> Vector4 {
+        let k = i * self.width + j;
+        match self.sample_counts[k] {
+            0 => Vector4::new(0.0, 0.0, 0.0, 0.0),
+            n => self.sums[k] / n as f32
+        }
+    }
+
+    /// Adds the samples of the pixels of `tile`, given in row-major order.
+    pub fn add_tile(&mut self, samples: &[PixelSamples], tile: Tile) {
+        for ((i, j), s) in tile.pixels().zip(samples) {
+            let k = i * self.width + j;
+            self.sums[k] += s.sum;
+            self.luminance_squares[k] += s.luminance_squares;
+            self.sample_counts[k] += s.count;
+        }
+    }
+
+    /// Marks the end of a pass, after the samples of all of its tiles have been added.
+    pub fn finish_pass(&mut self) {
+        self.passes += 1;
+    }
+
+    /// Returns the mean number of samples per pixel.
+    pub fn mean_sample_count(&self) -> f32 {
+        self.sample_counts.iter().map(|&n| n as f64).sum::<f64>() as f32 / self.sample_counts.len().max(1) as f32
+    }
+
+    /// Estimates the relative standard error of the luminance of the pixel in row `i` and column `j`, i.e. the standard
+    /// deviation of its mean divided by the mean. Returns infinity for pixels with fewer than two samples.
+    pub fn relative_error(&self, i: usize, j: usize) -> f32 {
+        let k = i * self.width + j;
+        let n = self.sample_counts[k] as f32;
+        if n < 2.0 {
+            return f32::INFINITY;
+        }
+        let mean = luminance(self.sums[k]) / n;
+        let variance = f32::max((self.luminance_squares[k] - n * mean * mean) / (n - 1.0), 0.0);
+        (variance / n).sqrt() / (mean.abs() + NOISE_LUMINANCE_OFFSET)
+    }
+
+    /// Estimates the noise of the image as the mean relative error of its pixels, see `relative_error`.
+    pub fn noise(&self) -> f32 {
+        let sum: f64 = (0..self.height)
+            .flat_map(|i| (0..self.width).map(move |j| (i, j)))
+            .map(|(i, j)| self.relative_error(i, j) as f64)
+            .sum();
+        (sum / (self.width * self.height).max(1) as f64) as f32
+    }
+
+    /// Returns an image of the number of samples of each pixel, shading pixels from black through red and yellow to white
+    /// as their counts go from zero to the largest count in the film.
+    pub fn sample_count_heatmap(&self, color_depth: usize) -> Image {
+        let stops = [
+            Vector4::new(0.0, 0.0, 0.0, 0.0),
+            Vector4::new(1.0, 0.0, 0.0, 0.0),
+            Vector4::new(1.0, 1.0, 0.0, 0.0),
+            Vector4::new(1.0, 1.0, 1.0, 0.0)
+        ];
+        let max_count = self.sample_counts.iter().copied().max().unwrap_or(0).max(1);
+        let mut image = Image::new(self.width, self.height, color_depth, 1.0);
+        for i in 0..self.height {
+            for j in 0..self.width {
+                let t = self.sample_count(i, j) as f32 / max_count as f32 * (stops.len() - 1) as f32;
+                let k = usize::min(t as usize, stops.len() - 2);
+                image.set_pixel(lerp(stops[k], stops[k + 1], t - k as f32), i, j);
+            }
+        }
+        image
+    }
+
+    /// Returns the image of the means of the pixels.
+    pub fn to_image(&self, color_depth: usize, encoding_gamma: f32) -> Image {
+        let mut image = Image::new(self.width, self.height, color_depth, encoding_gamma);
+        for i in 0..self.height {
+            for j in 0..self.width {
+                image.set_pixel(self.pixel(i, j), i, j);
+            }
+        }
+        image
+    }
+}
+
+impl Checkpoint {
+    pub fn new(seed: u64, samples_per_pass: usize, film: Film) -> Self {
+        Self { seed, samples_per_pass, film }
+    }
+
+    /// Writes the checkpoint in little-endian binary: the magic bytes, the seed, samples per pass, width, height and number
+    /// of passes as 64-bit integers, then the red, green and blue sums, the sum of squared luminances and the sample count
+    /// of each pixel in row-major order. Floats are stored exactly, so that resuming loses no precision.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let film = &self.film;
+        let mut data = Vec::with_capacity(CHECKPOINT_HEADER_SIZE + film.sums.len() * CHECKPOINT_PIXEL_SIZE);
+        data.extend_from_slice(CHECKPOINT_MAGIC);
+        for value in [self.seed, self.samples_per_pass as u64, film.width as u64, film.height as u64, film.passes as u64] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        for k in 0..film.sums.len() {
+            let sum = film.sums[k];
+            for value in [sum.x(), sum.y(), sum.z(), film.luminance_squares[k]] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            data.extend_from_slice(&film.sample_counts[k].to_le_bytes());
+        }
+        writer.write_all(&data)
+    }
+
+    /// Reads a checkpoint written by `write`.
+    pub fn read(bytes: &[u8]) -> io::Result<Self> {
+        let invalid_data = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        if bytes.len() < CHECKPOINT_HEADER_SIZE || &bytes[..CHECKPOINT_MAGIC.len()] != CHECKPOINT_MAGIC {
+            return Err(invalid_data("not a checkpoint file"));
+        }
+        let header: Vec<u64> = bytes[CHECKPOINT_MAGIC.len()..CHECKPOINT_HEADER_SIZE]
+            .chunks_exact(8)
+            .map(|v| u64::from_le_bytes(v.try_into().unwrap()))
+            .collect();
+        let samples_per_pass = header[1] as usize;
+        if samples_per_pass == 0 {
+            return Err(invalid_data("checkpoint passes take no samples"));
+        }
+        let (width, height) = (header[2] as usize, header[3] as usize);
+        let pixel_count = width.checked_mul(height).ok_or_else(|| invalid_data("checkpoint dimensions are too large"))?;
+        if pixel_count.checked_mul(CHECKPOINT_PIXEL_SIZE) != Some(bytes.len() - CHECKPOINT_HEADER_SIZE) {
+            return Err(invalid_data("checkpoint size does not match its dimensions"));
+        }
+
+        let mut film = Film::new(width, height);
+        film.passes = header[4] as usize;
+        for (k, pixel) in bytes[CHECKPOINT_HEADER_SIZE..].chunks_exact(CHECKPOINT_PIXEL_SIZE).enumerate() {
+            let value = |i: usize| f32::from_le_bytes(pixel[4 * i..4 * i + 4].try_into().unwrap());
+            film.sums[k] = Vector4::new(value(0), value(1), value(2), 0.0);
+            film.luminance_squares[k] = value(3);
+            film.sample_counts[k] = u32::from_le_bytes(pixel[16..20].try_into().unwrap());
+        }
+        Ok(Self::new(header[0], samples_per_pass, film))
+    }
+
+    /// Saves the checkpoint to `path`. It is written to a temporary file next to `path` first and then renamed, so that a
+    /// render interrupted while saving still leaves the previous checkpoint intact.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temporary_path = path.as_os_str().to_owned();
+        temporary_path.push(".tmp");
+        let mut writer = BufWriter::new(File::create(&temporary_path)?);
+        self.write(&mut writer)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&temporary_path, path)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read(&fs::read(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accumulate() {
+        let mut film = Film::new(3, 2);
+        let tile = Tile { row: 0, column: 1, height: 2, width: 2 };
+        let mut samples = [PixelSamples::new(); 4];
+        samples[0].add(Vector4::new(1.0, 1.0, 1.0, 0.0));
+        samples[0].add(Vector4::new(3.0, 3.0, 3.0, 0.0));
+        samples[3].add(Vector4::new(0.5, 0.25, 0.0, 0.0));
+        film.add_tile(&samples, tile);
+        film.add_tile(&samples, tile);
+        film.finish_pass();
+
+        assert_eq!(film.passes(), 1);
+        assert_eq!(film.sample_count(0, 1), 4);
+        assert_eq!(film.pixel(0, 1), Vector4::new(2.0, 2.0, 2.0, 0.0));
+        assert_eq!(film.pixel(1, 2), Vector4::new(0.5, 0.25, 0.0, 0.0));
+        // Pixels without samples are black.
+        assert_eq!(film.pixel(0, 0), Vector4::new(0.0, 0.0, 0.0, 0.0));
+        assert_eq!(film.to_image(255, 1.0).pixel(0, 1), film.pixel(0, 1));
+        assert_eq!(film.mean_sample_count(), 1.0);
+    }
+
+    #[test]
+    fn test_relative_error() {
+        // Samples 1, 3, 1, 3 have mean 2 and sample variance 4 / 3, so the standard error is sqrt(1 / 3).
+        let mut film = Film::new(1, 1);
+        let mut samples = PixelSamples::new();
+        for l in [1.0, 3.0, 1.0, 3.0] {
+            samples.add(Vector4::new(l, l, l, 0.0));
+        }
+        film.add_tile(&[samples], Tile { row: 0, column: 0, height: 1, width: 1 });
+        let expected = (1.0_f32 / 3.0).sqrt() / (2.0 + NOISE_LUMINANCE_OFFSET);
+        assert!((film.relative_error(0, 0) - expected).abs() < 1e-5);
+        assert_eq!(film.noise(), film.relative_error(0, 0));
+
+        // Constant samples have no noise.
+        let mut film = Film::new(1, 1);
+        let mut samples = PixelSamples::new();
+        (0..10).for_each(|_| samples.add(Vector4::new(0.3, 0.6, 0.9, 0.0)));
+        film.add_tile(&[samples], Tile { row: 0, column: 0, height: 1, width: 1 });
+        assert!(film.noise() < 1e-3);
+    }
+
+    #[test]
+    fn test_running_statistics() {
+        // Welford's algorithm agrees with the two-pass formulas, even for values with a large common offset.
+        let values = [1e4 + 1.0, 1e4 + 3.0, 1e4 + 1.0, 1e4 + 3.0];
+        let mut statistics = RunningStatistics::new();
+        assert_eq!(statistics.relative_error(), f32::INFINITY);
+        values.iter().for_each(|&v| statistics.add(v));
+        assert_eq!(statistics.count(), 4);
+        assert_eq!(statistics.mean(), 1e4 + 2.0);
+        assert!((statistics.variance() - 4.0 / 3.0).abs() < 1e-5);
+        assert!((statistics.relative_error() - (1.0_f32 / 3.0).sqrt() / (1e4 + 2.0 + NOISE_LUMINANCE_OFFSET)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sample_count_heatmap() {
+        let mut film = Film::new(3, 1);
+        let mut samples = [PixelSamples::new(); 3];
+        samples[1].add(Vector4::new(0.5, 0.5, 0.5, 0.0));
+        (0..2).for_each(|_| samples[2].add(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        film.add_tile(&samples, Tile { row: 0, column: 0, height: 1, width: 3 });
+        let heatmap = film.sample_count_heatmap(255);
+        assert_eq!(heatmap.pixel(0, 0), Vector4::new(0.0, 0.0, 0.0, 0.0));
+        assert_eq!(heatmap.pixel(0, 1), lerp(Vector4::new(1.0, 0.0, 0.0, 0.0), Vector4::new(1.0, 1.0, 0.0, 0.0), 0.5));
+        assert_eq!(heatmap.pixel(0, 2), Vector4::new(1.0, 1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let mut film = Film::new(3, 2);
+        let mut samples = [PixelSamples::new(); 6];
+        samples[1].add(Vector4::new(0.1, 1e-30, 7.5e20, 0.0));
+        samples[4].add(Vector4::new(1.0 / 3.0, 2.0, 3.0, 0.0));
+        samples[4].add(Vector4::new(4.0, 5.0, 6.0, 0.0));
+        film.add_tile(&samples, Tile { row: 0, column: 0, height: 2, width: 3 });
+        film.finish_pass();
+        let checkpoint = Checkpoint::new(0xcafef00dd15ea5e5, 4, film);
+
+        let mut data = Vec::new();
+        checkpoint.write(&mut data).unwrap();
+        assert_eq!(&data[..8], CHECKPOINT_MAGIC);
+        assert_eq!(data.len(), CHECKPOINT_HEADER_SIZE + 6 * CHECKPOINT_PIXEL_SIZE);
+        assert_eq!(Checkpoint::read(&data).unwrap(), checkpoint);
+
+        let path = std::env::temp_dir().join(format!("ray-tracing-checkpoint-{}.bin", std::process::id()));
+        checkpoint.save(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), checkpoint);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(Checkpoint::read(&data[..data.len() - 1]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(Checkpoint::read(b"RTCHKPT0").unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let mut no_samples = data.clone();
+        no_samples[16..24].fill(0);
+        assert_eq!(Checkpoint::read(&no_samples).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
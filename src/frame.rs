@@ -0,0 +1,73 @@
+use crate::{ray::Ray, vector4::Vector4};
+use std::f32::consts::PI;
+
+/// Right-handed orthonormal frame with its origin at `origin` and its `z` axis along `w`, in which surfaces of revolution
+/// are described in their simplest form.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
+    pub origin: Vector4,
+    pub u: Vector4,
+    pub v: Vector4,
+    pub w: Vector4
+}
+
+impl Frame {
+    /// Constructs the frame at `origin` whose `z` axis points along `axis`, which need not be of unit length.
+    pub fn new(origin: Vector4, axis: Vector4) -> Self {
+        let w = axis.normalize();
+        let (u, v) = w.orthonormal_basis();
+        Self { origin, u, v, w }
+    }
+
+    /// Returns the coordinates of the point `p` in the frame.
+    pub fn to_local(&self, p: Vector4) -> Vector4 {
+        self.vector_to_local(p - self.origin)
+    }
+
+    /// Returns the coordinates of the vector `d` in the frame.
+    pub fn vector_to_local(&self, d: Vector4) -> Vector4 {
+        Vector4::new(self.u.dot(d), self.v.dot(d), self.w.dot(d), 0.0)
+    }
+
+    /// Returns the point with the coordinates `p` in the frame.
+    pub fn to_world(&self, p: Vector4) -> Vector4 {
+        self.origin + self.vector_to_world(p)
+    }
+
+    /// Returns the vector with the coordinates `d` in the frame.
+    pub fn vector_to_world(&self, d: Vector4) -> Vector4 {
+        d.x() * self.u + d.y() * self.v + d.z() * self.w
+    }
+
+    /// Returns `r` in the coordinates of the frame. As the frame is orthonormal, the ray parameter `t` of every point is
+    /// unchanged.
+    pub fn ray_to_local(&self, r: Ray) -> Ray {
+        Ray { origin: self.to_local(r.origin), direction: self.vector_to_local(r.direction), ..r }
+    }
+}
+
+/// Returns the azimuth of the point with the local coordinates `p` about the `z` axis, measured counterclockwise from the
+/// `x` axis and scaled to `[0, 1]`.
+pub fn azimuth(p: Vector4) -> f32 {
+    let phi = f32::atan2(p.y(), p.x());
+    let phi = if phi < 0.0 { phi + 2.0 * PI } else { phi };
+    phi / (2.0 * PI)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let frame = Frame::new(Vector4::new(1.0, 2.0, 3.0, 0.0), Vector4::new(0.0, 3.0, 4.0, 0.0));
+        assert!((frame.u.cross(frame.v) - frame.w).norm() < 1e-6);
+        assert_eq!(frame.to_local(frame.origin + 2.0 * frame.w), Vector4::new(0.0, 0.0, 2.0, 0.0));
+
+        let p = Vector4::new(-4.0, 0.5, 7.0, 0.0);
+        assert!((frame.to_world(frame.to_local(p)) - p).norm() < 1e-5);
+        let r = frame.ray_to_local(Ray::new(p, Vector4::new(1.0, -1.0, 2.0, 0.0)));
+        assert!((frame.to_world(r.at(1.5)) - (p + 1.5 * Vector4::new(1.0, -1.0, 2.0, 0.0))).norm() < 1e-5);
+        assert_eq!(azimuth(Vector4::new(0.0, -1.0, 5.0, 0.0)), 0.75);
+    }
+}
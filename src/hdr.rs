@@ -0,0 +1,693 @@
+use crate::vector4::Vector4;
+use std::io::{self, Write};
+
+/// Decoded high dynamic range image: its width, height and linear colours in row-major order, starting with the top row.
+pub type HdrPixels = (usize, usize, Vec<Vector4>);
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Reads the next whitespace-separated token of a text header starting at `*position`.
+fn next_token<'a>(bytes: &'a [u8], position: &mut usize) -> io::Result<&'a str> {
+    while *position < bytes.len() && bytes[*position].is_ascii_whitespace() {
+        *position += 1;
+    }
+    let start = *position;
+    while *position < bytes.len() && !bytes[*position].is_ascii_whitespace() {
+        *position += 1;
+    }
+    std::str::from_utf8(&bytes[start..*position])
+        .ok()
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| invalid_data("unexpected end of header"))
+}
+
+fn parse<T: std::str::FromStr>(token: &str) -> io::Result<T> {
+    token.parse().map_err(|_| invalid_data(&format!("invalid number in header: {}", token)))
+}
+
+/// Writes a colour Portable Float Map: a text header followed by little-endian `f32` samples, with rows running from
+/// the bottom of the image to its top.
+pub fn write_pfm<W: Write>(mut writer: W, width: usize, height: usize, pixels: &[Vector4]) -> io::Result<()> {
+    write!(writer, "PF\n{} {}\n-1.0\n", width, height)?;
+    let mut data = Vec::with_capacity(pixels.len() * 12);
+    for row in pixels.chunks_exact(width).rev() {
+        for color in row {
+            for sample in [color.x(), color.y(), color.z()] {
+                data.extend_from_slice(&sample.to_le_bytes());
+            }
+        }
+    }
+    writer.write_all(&data)?;
+    writer.flush()
+}
+
+/// Reads a colour (`PF`) or greyscale (`Pf`) Portable Float Map of either byte order.
+pub fn read_pfm(bytes: &[u8]) -> io::Result<HdrPixels> {
+    let mut position = 0;
+    let channels = match next_token(bytes, &mut position)? {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid_data("not a PFM image"))
+    };
+    let width: usize = parse(next_token(bytes, &mut position)?)?;
+    let height: usize = parse(next_token(bytes, &mut position)?)?;
+    // The sign of the scale gives the byte order, negative for little-endian.
+    let little_endian = parse::<f32>(next_token(bytes, &mut position)?)? < 0.0;
+    // A single whitespace character separates the header from the samples.
+    let start = position + 1;
+    if width == 0 || height == 0 {
+        return Err(invalid_data("PFM image is empty"));
+    }
+    let end = width
+        .checked_mul(height)
+        .and_then(|n| n.checked_mul(4 * channels))
+        .and_then(|n| n.checked_add(start))
+        .ok_or_else(|| invalid_data("PFM image is too large"))?;
+    let data = bytes.get(start..end).ok_or_else(|| invalid_data("unexpected end of PFM data"))?;
+    let samples: Vec<f32> = data
+        .chunks_exact(4)
+        .map(|s| {
+            let s = [s[0], s[1], s[2], s[3]];
+            if little_endian { f32::from_le_bytes(s) } else { f32::from_be_bytes(s) }
+        })
+        .collect();
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in samples.chunks_exact(width * channels).rev() {
+        pixels.extend(row.chunks_exact(channels).map(|s| match channels {
+            3 => Vector4::new(s[0], s[1], s[2], 0.0),
+            _ => Vector4::new(s[0], s[0], s[0], 0.0)
+        }));
+    }
+    Ok((width, height, pixels))
+}
+
+/// Encodes a colour as RGBE, i.e. three 8-bit mantissas sharing an 8-bit exponent. Negative and non-finite components are
+/// stored as zero.
+fn to_rgbe(color: Vector4) -> [u8; 4] {
+    let rgb = [color.x(), color.y(), color.z()].map(|c| if c.is_finite() { f32::max(c, 0.0) } else { 0.0 });
+    let max = rgb[0].max(rgb[1]).max(rgb[2]);
+    if max < 1e-32 {
+        return [0; 4];
+    }
+    // max = m * 2^e with m in [0.5, 1).
+    let e = max.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2.0_f32.powi(e);
+    let [r, g, b] = rgb.map(|c| f32::min(c * scale, 255.0) as u8);
+    [r, g, b, (e + 128).clamp(0, 255) as u8]
+}
+
+fn from_rgbe(rgbe: [u8; 4]) -> Vector4 {
+    if rgbe[3] == 0 {
+        return Vector4::new(0.0, 0.0, 0.0, 0.0);
+    }
+    let f = 2.0_f32.powi(rgbe[3] as i32 - (128 + 8));
+    Vector4::new((rgbe[0] as f32 + 0.5) * f, (rgbe[1] as f32 + 0.5) * f, (rgbe[2] as f32 + 0.5) * f, 0.0)
+}
+
+/// Appends the run-length encoding of one component of a scanline of RGBE pixels to `out`. Runs of at least four equal
+/// bytes are stored as a count above 128 and the byte, everything else as a count of up to 128 followed by literal bytes.
+fn encode_rle_component(component: &[u8], out: &mut Vec<u8>) {
+    const MIN_RUN: usize = 4;
+    let mut i = 0;
+    while i < component.len() {
+        // Find the next run, if any.
+        let mut run_start = i;
+        let mut run_length = 0;
+        while run_start < component.len() {
+            run_length = component[run_start..].iter().take(127).take_while(|&&b| b == component[run_start]).count();
+            if run_length >= MIN_RUN {
+                break;
+            }
+            run_start += 1;
+        }
+        // Literal bytes preceding the run.
+        while i < run_start {
+            let count = usize::min(128, run_start - i);
+            out.push(count as u8);
+            out.extend_from_slice(&component[i..i + count]);
+            i += count;
+        }
+        if run_start < component.len() {
+            out.push(128 + run_length as u8);
+            out.push(component[run_start]);
+            i = run_start + run_length;
+        }
+    }
+}
+
+/// Writes a Radiance RGBE image, run-length encoding the scanlines when their width allows it.
+pub fn write_hdr<W: Write>(mut writer: W, width: usize, height: usize, pixels: &[Vector4]) -> io::Result<()> {
+    write!(writer, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", height, width)?;
+    let mut data = Vec::with_capacity(pixels.len() * 4);
+    for row in pixels.chunks_exact(width) {
+        let rgbe: Vec<[u8; 4]> = row.iter().map(|&c| to_rgbe(c)).collect();
+        if (8..0x8000).contains(&width) {
+            data.extend_from_slice(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8]);
+            for c in 0..4 {
+                let component: Vec<u8> = rgbe.iter().map(|p| p[c]).collect();
+                encode_rle_component(&component, &mut data);
+            }
+        } else {
+            data.extend(rgbe.iter().flatten());
+        }
+    }
+    writer.write_all(&data)?;
+    writer.flush()
+}
+
+/// Reads a Radiance RGBE image with the standard `-Y height +X width` orientation, with either flat or run-length
+/// encoded scanlines.
+pub fn read_hdr(bytes: &[u8]) -> io::Result<HdrPixels> {
+    if !bytes.starts_with(b"#?") {
+        return Err(invalid_data("not a Radiance image"));
+    }
+    // Header lines run until an empty line, followed by the resolution line.
+    let mut position = 0;
+    loop {
+        let end = bytes[position..].iter().position(|&b| b == b'\n').ok_or_else(|| invalid_data("unexpected end of header"))?;
+        let line = &bytes[position..position + end];
+        position += end + 1;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with(b"FORMAT=") && line != b"FORMAT=32-bit_rle_rgbe" {
+            return Err(invalid_data("unsupported Radiance pixel format"));
+        }
+    }
+    if next_token(bytes, &mut position)? != "-Y" {
+        return Err(invalid_data("unsupported Radiance image orientation"));
+    }
+    let height: usize = parse(next_token(bytes, &mut position)?)?;
+    if next_token(bytes, &mut position)? != "+X" {
+        return Err(invalid_data("unsupported Radiance image orientation"));
+    }
+    let width: usize = parse(next_token(bytes, &mut position)?)?;
+    position += 1;
+    if width == 0 || height == 0 {
+        return Err(invalid_data("Radiance image is empty"));
+    }
+    let pixel_count = width.checked_mul(height).ok_or_else(|| invalid_data("Radiance image is too large"))?;
+
+    let unexpected_end = || invalid_data("unexpected end of Radiance data");
+    // Runs may encode many pixels in a few bytes, so the file size only bounds the space reserved up front.
+    let mut pixels = Vec::with_capacity(usize::min(pixel_count, bytes.len()));
+    for _ in 0..height {
+        let marker = bytes.get(position..position + 4).ok_or_else(unexpected_end)?;
+        if (8..0x8000).contains(&width) && marker[0] == 2 && marker[1] == 2 && marker[2] & 0x80 == 0 {
+            if ((marker[2] as usize) << 8 | marker[3] as usize) != width {
+                return Err(invalid_data("Radiance scanline width mismatch"));
+            }
+            position += 4;
+            let mut components = vec![[0_u8; 4]; width];
+            for c in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let count = *bytes.get(position).ok_or_else(unexpected_end)? as usize;
+                    position += 1;
+                    if count > 128 {
+                        let count = count - 128;
+                        let value = *bytes.get(position).ok_or_else(unexpected_end)?;
+                        position += 1;
+                        if count > width - x {
+                            return Err(invalid_data("Radiance run exceeds the scanline"));
+                        }
+                        components[x..x + count].iter_mut().for_each(|p| p[c] = value);
+                        x += count;
+                    } else {
+                        if count == 0 || count > width - x {
+                            return Err(invalid_data("invalid Radiance run length"));
+                        }
+                        let values = bytes.get(position..position + count).ok_or_else(unexpected_end)?;
+                        position += count;
+                        components[x..x + count].iter_mut().zip(values).for_each(|(p, &v)| p[c] = v);
+                        x += count;
+                    }
+                }
+            }
+            pixels.extend(components.into_iter().map(from_rgbe));
+        } else {
+            let data = width.checked_mul(4).and_then(|n| bytes.get(position..position.checked_add(n)?)).ok_or_else(unexpected_end)?;
+            position += 4 * width;
+            pixels.extend(data.chunks_exact(4).map(|p| from_rgbe([p[0], p[1], p[2], p[3]])));
+        }
+    }
+    Ok((width, height, pixels))
+}
+
+/// Compression methods supported for OpenEXR images.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExrCompression {
+    None,
+    /// Deflate applied to blocks of 16 scanlines after reordering and delta-encoding their bytes.
+    Zip
+}
+
+/// Magic number at the start of every OpenEXR file.
+pub const EXR_MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+const EXR_NO_COMPRESSION: u8 = 0;
+const EXR_ZIPS_COMPRESSION: u8 = 2;
+const EXR_ZIP_COMPRESSION: u8 = 3;
+const EXR_HALF: i32 = 1;
+const EXR_FLOAT: i32 = 2;
+// Upper bound on the ratio between the sizes of data inflated by zlib and its compressed form.
+const MAX_DEFLATE_RATIO: usize = 1032;
+
+fn write_exr_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// Reorders the bytes of `data` so that the even-indexed bytes precede the odd-indexed ones, then replaces each byte
+/// by its difference to the previous one, which makes floating point data compress better.
+fn exr_zip_predict(data: &[u8]) -> Vec<u8> {
+    let mut out: Vec<u8> = data.iter().step_by(2).chain(data.iter().skip(1).step_by(2)).copied().collect();
+    for i in (1..out.len()).rev() {
+        out[i] = out[i].wrapping_sub(out[i - 1]).wrapping_add(128);
+    }
+    out
+}
+
+fn exr_zip_unpredict(data: &mut [u8]) -> Vec<u8> {
+    for i in 1..data.len() {
+        data[i] = data[i].wrapping_add(data[i - 1]).wrapping_sub(128);
+    }
+    let (even, odd) = data.split_at(data.len().div_ceil(2));
+    let mut out = Vec::with_capacity(data.len());
+    for i in 0..even.len() {
+        out.push(even[i]);
+        if i < odd.len() {
+            out.push(odd[i]);
+        }
+    }
+    out
+}
+
+/// Writes a single-part scanline OpenEXR image with 32-bit float `R`, `G` and `B` channels.
+pub fn write_exr<W: Write>(mut writer: W, width: usize, height: usize, pixels: &[Vector4], compression: ExrCompression) -> io::Result<()> {
+    let mut header = Vec::new();
+    header.extend_from_slice(&EXR_MAGIC);
+    header.extend_from_slice(&2_u32.to_le_bytes());
+
+    let mut channels = Vec::new();
+    // Channels are stored in alphabetical order.
+    for name in ["B", "G", "R"] {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        channels.extend_from_slice(&EXR_FLOAT.to_le_bytes());
+        channels.extend_from_slice(&[0, 0, 0, 0]);              // pLinear and reserved bytes.
+        channels.extend_from_slice(&1_i32.to_le_bytes());       // x sampling.
+        channels.extend_from_slice(&1_i32.to_le_bytes());       // y sampling.
+    }
+    channels.push(0);
+    write_exr_attribute(&mut header, "channels", "chlist", &channels);
+    let (compression_id, lines_per_block) = match compression {
+        ExrCompression::None => (EXR_NO_COMPRESSION, 1),
+        ExrCompression::Zip => (EXR_ZIP_COMPRESSION, 16)
+    };
+    write_exr_attribute(&mut header, "compression", "compression", &[compression_id]);
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1].iter().flat_map(|v| v.to_le_bytes()).collect();
+    write_exr_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_exr_attribute(&mut header, "displayWindow", "box2i", &window);
+    write_exr_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    write_exr_attribute(&mut header, "pixelAspectRatio", "float", &1.0_f32.to_le_bytes());
+    write_exr_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    write_exr_attribute(&mut header, "screenWindowWidth", "float", &1.0_f32.to_le_bytes());
+    header.push(0);
+
+    // Each block holds, for each of its scanlines, all samples of the B channel, then G, then R.
+    let blocks: Vec<Vec<u8>> = (0..height)
+        .step_by(lines_per_block)
+        .map(|y_start| {
+            let mut raw = Vec::new();
+            for row in pixels[y_start * width..usize::min(y_start + lines_per_block, height) * width].chunks_exact(width) {
+                for channel in [2, 1, 0] {
+                    raw.extend(row.iter().flat_map(|c| c[channel].to_le_bytes()));
+                }
+            }
+            match compression {
+                ExrCompression::None => raw,
+                ExrCompression::Zip => {
+                    let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&exr_zip_predict(&raw), 6);
+                    // Blocks that do not shrink are stored uncompressed.
+                    if compressed.len() < raw.len() { compressed } else { raw }
+                }
+            }
+        })
+        .collect();
+
+    let mut offset = (header.len() + 8 * blocks.len()) as u64;
+    for block in &blocks {
+        header.extend_from_slice(&offset.to_le_bytes());
+        offset += 8 + block.len() as u64;
+    }
+    writer.write_all(&header)?;
+    for (i, block) in blocks.iter().enumerate() {
+        writer.write_all(&((i * lines_per_block) as i32).to_le_bytes())?;
+        writer.write_all(&(block.len() as i32).to_le_bytes())?;
+        writer.write_all(block)?;
+    }
+    writer.flush()
+}
+
+/// Converts an IEEE 754 half precision float to single precision.
+fn half_to_f32(h: u16) -> f32 {
+    let sign = if h & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = (h >> 10) & 0x1f;
+    let mantissa = (h & 0x3ff) as u32;
+    match exponent {
+        0 => sign * mantissa as f32 * 2.0_f32.powi(-24),
+        0x1f if mantissa == 0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => f32::from_bits(((h as u32 & 0x8000) << 16) | ((exponent as u32 + 112) << 23) | (mantissa << 13))
+    }
+}
+
+/// Reads a single-part scanline OpenEXR image with `HALF` or `FLOAT` channels, compressed with `NONE`, `ZIPS` or `ZIP`.
+/// The `R`, `G` and `B` channels give the colour, or the `Y` channel for greyscale images. Other channels are ignored.
+pub fn read_exr(bytes: &[u8]) -> io::Result<HdrPixels> {
+    let unexpected_end = || invalid_data("unexpected end of OpenEXR data");
+    if !bytes.starts_with(&EXR_MAGIC) {
+        return Err(invalid_data("not an OpenEXR image"));
+    }
+    let version = u32::from_le_bytes(bytes.get(4..8).ok_or_else(unexpected_end)?.try_into().unwrap());
+    if version & 0xff != 2 || version & !0x4ff != 0 {
+        return Err(invalid_data("only single-part scanline OpenEXR images are supported"));
+    }
+    // Long attribute names (flag 0x400) need no special handling.
+
+    let read_string = |position: &mut usize| -> io::Result<String> {
+        let end = bytes[*position..].iter().position(|&b| b == 0).ok_or_else(unexpected_end)?;
+        let s = String::from_utf8_lossy(&bytes[*position..*position + end]).into_owned();
+        *position += end + 1;
+        Ok(s)
+    };
+    let read_i32 = |position: usize| -> io::Result<i32> {
+        let end = position.checked_add(4).ok_or_else(unexpected_end)?;
+        Ok(i32::from_le_bytes(bytes.get(position..end).ok_or_else(unexpected_end)?.try_into().unwrap()))
+    };
+    let read_size = |position: usize| -> io::Result<usize> {
+        usize::try_from(read_i32(position)?).map_err(|_| invalid_data("negative size in OpenEXR data"))
+    };
+
+    let mut position = 8;
+    let mut channels: Vec<(String, i32)> = Vec::new();
+    let mut compression = None;
+    let mut data_window = None;
+    loop {
+        let name = read_string(&mut position)?;
+        if name.is_empty() {
+            break;
+        }
+        let _kind = read_string(&mut position)?;
+        let size = read_size(position)?;
+        position += 4;
+        let value = bytes.get(position..).and_then(|rest| rest.get(..size)).ok_or_else(unexpected_end)?;
+        match name.as_str() {
+            "channels" => {
+                let mut p = 0;
+                while p < value.len() && value[p] != 0 {
+                    let end = value[p..].iter().position(|&b| b == 0).ok_or_else(unexpected_end)?;
+                    let channel = String::from_utf8_lossy(&value[p..p + end]).into_owned();
+                    p += end + 1;
+                    let pixel_type = i32::from_le_bytes(value.get(p..p + 4).ok_or_else(unexpected_end)?.try_into().unwrap());
+                    let x_sampling = i32::from_le_bytes(value.get(p + 8..p + 12).ok_or_else(unexpected_end)?.try_into().unwrap());
+                    let y_sampling = i32::from_le_bytes(value.get(p + 12..p + 16).ok_or_else(unexpected_end)?.try_into().unwrap());
+                    if x_sampling != 1 || y_sampling != 1 {
+                        return Err(invalid_data("subsampled OpenEXR channels are not supported"));
+                    }
+                    p += 16;
+                    channels.push((channel, pixel_type));
+                }
+            },
+            "compression" => compression = value.first().copied(),
+            "dataWindow" => {
+                let v: Vec<i32> = value.chunks_exact(4).map(|v| i32::from_le_bytes(v.try_into().unwrap())).collect();
+                if v.len() == 4 {
+                    data_window = Some((v[0], v[1], v[2], v[3]));
+                }
+            },
+            _ => {}
+        }
+        position += size;
+    }
+
+    let (x_min, y_min, x_max, y_max) = data_window.ok_or_else(|| invalid_data("OpenEXR image has no data window"))?;
+    if x_max < x_min || y_max < y_min {
+        return Err(invalid_data("OpenEXR image is empty"));
+    }
+    // The window may span more than the range of i32.
+    let width = (x_max as i64 - x_min as i64 + 1) as usize;
+    let height = (y_max as i64 - y_min as i64 + 1) as usize;
+    let lines_per_block = match compression {
+        Some(EXR_NO_COMPRESSION) | Some(EXR_ZIPS_COMPRESSION) => 1,
+        Some(EXR_ZIP_COMPRESSION) => 16,
+        _ => return Err(invalid_data("unsupported OpenEXR compression"))
+    };
+    if channels.iter().any(|(_, t)| *t != EXR_HALF && *t != EXR_FLOAT) {
+        return Err(invalid_data("only HALF and FLOAT OpenEXR channels are supported"));
+    }
+    let find = |name: &str| channels.iter().position(|(n, _)| n == name);
+    let rgb = match (find("R"), find("G"), find("B"), find("Y")) {
+        (Some(r), Some(g), Some(b), _) => [r, g, b],
+        (_, _, _, Some(y)) => [y, y, y],
+        _ => return Err(invalid_data("OpenEXR image has no RGB or Y channels"))
+    };
+    let sample_size = |t: i32| if t == EXR_HALF { 2 } else { 4 };
+    let too_large = || invalid_data("OpenEXR image is too large");
+    let bytes_per_line = channels.iter().map(|(_, t)| sample_size(*t)).sum::<usize>().checked_mul(width).ok_or_else(too_large)?;
+
+    // Check that the header values are consistent with the size of the file before allocating the image: the offset table
+    // holds one entry per block, and the blocks can not hold more samples than they would inflate to.
+    let block_count = height.div_ceil(lines_per_block);
+    let table = block_count.checked_mul(8).and_then(|n| bytes.get(position..position.checked_add(n)?)).ok_or_else(unexpected_end)?;
+    let pixel_count = width.checked_mul(height).ok_or_else(too_large)?;
+    if bytes_per_line.checked_mul(height).is_none_or(|n| n / MAX_DEFLATE_RATIO > bytes.len()) {
+        return Err(too_large());
+    }
+    let mut pixels = vec![Vector4::new(0.0, 0.0, 0.0, 0.0); pixel_count];
+    for entry in table.chunks_exact(8) {
+        let offset = usize::try_from(u64::from_le_bytes(entry.try_into().unwrap())).map_err(|_| unexpected_end())?;
+        let y = read_i32(offset)?;
+        let size = read_size(offset.checked_add(4).ok_or_else(unexpected_end)?)?;
+        let data = offset
+            .checked_add(8)
+            .and_then(|start| bytes.get(start..start.checked_add(size)?))
+            .ok_or_else(unexpected_end)?;
+        let first_line = usize::try_from(y as i64 - y_min as i64)
+            .ok()
+            .filter(|&line| line < height)
+            .ok_or_else(|| invalid_data("OpenEXR block lies outside of the data window"))?;
+        let line_count = usize::min(lines_per_block, height - first_line);
+        let raw_size = line_count * bytes_per_line;
+        let raw = if size < raw_size {
+            let mut inflated = miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, raw_size)
+                .map_err(|_| invalid_data("corrupt OpenEXR block"))?;
+            if inflated.len() != raw_size {
+                return Err(invalid_data("corrupt OpenEXR block"));
+            }
+            exr_zip_unpredict(&mut inflated)
+        } else {
+            data.to_vec()
+        };
+
+        let mut p = 0;
+        for line in first_line..first_line + line_count {
+            let row = &mut pixels[line * width..(line + 1) * width];
+            for (c, (_, pixel_type)) in channels.iter().enumerate() {
+                let size = sample_size(*pixel_type);
+                for (x, sample) in raw[p..p + size * width].chunks_exact(size).enumerate() {
+                    let value = if size == 2 {
+                        half_to_f32(u16::from_le_bytes([sample[0], sample[1]]))
+                    } else {
+                        f32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]])
+                    };
+                    let pixel = &mut row[x];
+                    let [r, g, b] = rgb.map(|i| if i == c { Some(value) } else { None });
+                    *pixel = Vector4::new(
+                        r.unwrap_or(pixel.x()),
+                        g.unwrap_or(pixel.y()),
+                        b.unwrap_or(pixel.z()),
+                        0.0
+                    );
+                }
+                p += size * width;
+            }
+        }
+    }
+    Ok((width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Image with values spanning several orders of magnitude, including some outside of `[0, 1]`.
+    fn test_pixels(width: usize, height: usize) -> Vec<Vector4> {
+        (0..width * height)
+            .map(|i| {
+                let x = i as f32;
+                Vector4::new(0.001 * x, 1000.0 / (x + 1.0), (x * 0.37).sin() * 20.0 + 20.0, 0.0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pfm_round_trip() {
+        let pixels = test_pixels(5, 3);
+        let mut data = Vec::new();
+        write_pfm(&mut data, 5, 3, &pixels).unwrap();
+        assert!(data.starts_with(b"PF\n5 3\n-1.0\n"));
+        // The first samples stored belong to the bottom row.
+        assert_eq!(&data[12..16], &pixels[10].x().to_le_bytes());
+        assert_eq!(read_pfm(&data).unwrap(), (5, 3, pixels));
+
+        // Big-endian greyscale.
+        let mut data = b"Pf\n2 1\n1.0\n".to_vec();
+        data.extend_from_slice(&0.5_f32.to_be_bytes());
+        data.extend_from_slice(&8.0_f32.to_be_bytes());
+        assert_eq!(read_pfm(&data).unwrap(), (2, 1, vec![Vector4::new(0.5, 0.5, 0.5, 0.0), Vector4::new(8.0, 8.0, 8.0, 0.0)]));
+    }
+
+    #[test]
+    fn test_hdr_round_trip() {
+        // Widths with flat and run-length encoded scanlines.
+        for (width, height) in [(5, 3), (40, 3)] {
+            let mut pixels = test_pixels(width, height);
+            // Long runs of equal pixels.
+            pixels[width..2 * width].fill(Vector4::new(0.25, 0.5, 4.0, 0.0));
+            pixels[0] = Vector4::new(0.0, 0.0, 0.0, 0.0);
+            let mut data = Vec::new();
+            write_hdr(&mut data, width, height, &pixels).unwrap();
+            let (w, h, decoded) = read_hdr(&data).unwrap();
+            assert_eq!((w, h), (width, height));
+            assert_eq!(decoded[0], Vector4::new(0.0, 0.0, 0.0, 0.0));
+            for (expected, actual) in pixels.iter().zip(&decoded) {
+                // RGBE keeps 8 bits of precision relative to the largest component.
+                let max = expected.x().max(expected.y()).max(expected.z());
+                assert!((*expected - *actual).norm() <= 0.01 * max, "{:?} != {:?}", expected, actual);
+            }
+        }
+        assert_eq!(to_rgbe(Vector4::new(1.0, 0.5, 0.0, 0.0)), [128, 64, 0, 129]);
+        assert_eq!(from_rgbe([128, 64, 0, 129]), Vector4::new(257.0 / 256.0, 129.0 / 256.0, 1.0 / 256.0, 0.0));
+    }
+
+    #[test]
+    fn test_hdr_rle() {
+        let mut out = Vec::new();
+        encode_rle_component(&[1, 2, 3, 7, 7, 7, 7, 7, 4], &mut out);
+        assert_eq!(out, vec![3, 1, 2, 3, 128 + 5, 7, 1, 4]);
+        let mut out = Vec::new();
+        encode_rle_component(&[9; 300], &mut out);
+        assert_eq!(out, vec![128 + 127, 9, 128 + 127, 9, 128 + 46, 9]);
+    }
+
+    #[test]
+    fn test_exr_round_trip() {
+        // A height that is not a multiple of the 16 scanlines per ZIP block.
+        let pixels = test_pixels(7, 37);
+        for compression in [ExrCompression::None, ExrCompression::Zip] {
+            let mut data = Vec::new();
+            write_exr(&mut data, 7, 37, &pixels, compression).unwrap();
+            assert_eq!(read_exr(&data).unwrap(), (7, 37, pixels.clone()));
+        }
+
+        // ZIP compression pays off for smooth images.
+        let smooth = vec![Vector4::new(0.5, 0.25, 0.125, 0.0); 64 * 64];
+        let mut uncompressed = Vec::new();
+        let mut compressed = Vec::new();
+        write_exr(&mut uncompressed, 64, 64, &smooth, ExrCompression::None).unwrap();
+        write_exr(&mut compressed, 64, 64, &smooth, ExrCompression::Zip).unwrap();
+        assert!(compressed.len() * 10 < uncompressed.len());
+        assert_eq!(read_exr(&compressed).unwrap().2, smooth);
+    }
+
+    /// Returns the position of the value of the attribute `name` in an OpenEXR header.
+    fn exr_attribute(data: &[u8], name: &str) -> usize {
+        let name = [name.as_bytes(), &[0]].concat();
+        let start = data.windows(name.len()).position(|w| w == name).unwrap() + name.len();
+        // Skip the type name and the size.
+        start + data[start..].iter().position(|&b| b == 0).unwrap() + 1 + 4
+    }
+
+    #[test]
+    fn test_exr_malformed_header() {
+        let mut image = Vec::new();
+        write_exr(&mut image, 2, 2, &test_pixels(2, 2), ExrCompression::None).unwrap();
+        let window = exr_attribute(&image, "dataWindow");
+        let table = exr_attribute(&image, "screenWindowWidth") + 4 + 1;
+        let first_block = u64::from_le_bytes(image[table..table + 8].try_into().unwrap()) as usize;
+        let patched = |position: usize, values: &[i32]| {
+            let mut data = image.clone();
+            for (i, v) in values.iter().enumerate() {
+                data[position + 4 * i..position + 4 * i + 4].copy_from_slice(&v.to_le_bytes());
+            }
+            data
+        };
+
+        let malformed = [
+            // Windows far larger than the file, or spanning more than the range of i32.
+            patched(window, &[0, 0, 200000, 200000]),
+            patched(window, &[i32::MIN, i32::MIN, i32::MAX, i32::MAX]),
+            patched(window, &[0, 0, 1, 100000]),
+            // Negative attribute and block sizes.
+            patched(window - 4, &[-1]),
+            patched(first_block + 4, &[-1]),
+            // Blocks above the data window or at offsets near the end of the address space.
+            patched(first_block, &[i32::MIN]),
+            patched(table, &[-8, -1])
+        ];
+        for data in malformed {
+            assert_eq!(read_exr(&data).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+        assert_eq!(read_exr(&image).unwrap().2, test_pixels(2, 2));
+    }
+
+    #[test]
+    fn test_pfm_and_hdr_malformed_header() {
+        let malformed: [&[u8]; 3] = [b"PF\n4611686018427387904 4\n-1.0\n\0\0\0\0", b"PF\n0 4\n-1.0\n", b"Pf\n1 3074457345618258603\n-1.0\n\0"];
+        for data in malformed {
+            assert_eq!(read_pfm(data).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+        let malformed: [&[u8]; 3] = [
+            b"#?RADIANCE\n\n-Y 18446744073709551615 +X 0\n\0\0\0\0",
+            b"#?RADIANCE\n\n-Y 1000000000 +X 1000000000\n\0\0\0\0",
+            b"#?RADIANCE\n\n-Y 1 +X 4611686018427387904\n\0\0\0\0"
+        ];
+        for data in malformed {
+            assert_eq!(read_hdr(data).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_exr_zip_predictor() {
+        let data: Vec<u8> = (0..=255).chain(0..17).collect();
+        assert_eq!(exr_zip_unpredict(&mut exr_zip_predict(&data)), data);
+    }
+
+    #[test]
+    fn test_half_to_f32() {
+        assert_eq!(half_to_f32(0x0000), 0.0);
+        assert_eq!(half_to_f32(0x3c00), 1.0);
+        assert_eq!(half_to_f32(0xc000), -2.0);
+        assert_eq!(half_to_f32(0x3555), 0.33325195);
+        assert_eq!(half_to_f32(0x7bff), 65504.0);
+        assert_eq!(half_to_f32(0x0001), 2.0_f32.powi(-24));
+        assert_eq!(half_to_f32(0x7c00), f32::INFINITY);
+        assert!(half_to_f32(0x7e00).is_nan());
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        assert_eq!(read_pfm(b"P6\n1 1\n255\n\0\0\0").unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(read_pfm(b"PF\n2 2\n-1.0\n\0\0\0\0").unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(read_hdr(b"#?RADIANCE\n\n+Y 1 +X 1\n\0\0\0\0").unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(read_exr(b"v/1\x01\x02\0\0\0").unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -1,9 +1,76 @@
-use crate::ray::Ray;
+use crate::{
+    materials::{Material, Tangible},
+    ray::Ray,
+    vector4::Vector4
+};
+use rand::Rng;
+use std::sync::Arc;
+
+/// Record of an intersection between a ray and a tangible object, holding everything that materials need
+/// to attenuate and scatter the ray so that it only has to be computed once per intersection.
+pub struct HitRecord<'a, R: Rng + ?Sized> {
+    pub t: f32,
+    pub p: Vector4,
+    pub normal: Vector4,    // Outward-facing geometric surface normal of unit length.
+    pub shading_normal: Vector4,    // Unit normal used for shading, e.g. interpolated vertex normals. Equals normal by default.
+    pub front_face: bool,   // true if the ray hit the surface from the outside, i.e. r.direction * normal < 0.
+    pub u: f32,             // Surface coordinates of p, both in [0, 1].
+    pub v: f32,
+    pub object: &'a (dyn Tangible<R> + Send + Sync),
+    pub material: &'a (dyn Material<R> + Send + Sync)
+}
+
+impl<'a, R: Rng + ?Sized> HitRecord<'a, R> {
+    /// Constructs a hit record for an intersection between `r` and `object` at `t`, where `normal` is
+    /// the outward-facing surface normal of `object` at `r.at(t)`.
+    pub fn new(
+        r: Ray,
+        t: f32,
+        normal: Vector4,
+        (u, v): (f32, f32),
+        object: &'a (dyn Tangible<R> + Send + Sync)
+    ) -> Self {
+        Self {
+            t,
+            p: r.at(t),
+            normal,
+            shading_normal: normal,
+            front_face: r.direction.dot(normal) < 0.0,
+            u,
+            v,
+            object,
+            material: &**object.material()
+        }
+    }
+
+    /// Returns the shading normal flipped, if the ray hit the back of the surface, to point to the side it came from.
+    ///
+    /// Materials that scatter light on the side of the surface it arrives from use this normal, so that open surfaces,
+    /// e.g. quads and triangles, reflect light on both sides.
+    pub fn facing_normal(&self) -> Vector4 {
+        if self.front_face { self.shading_normal } else { -self.shading_normal }
+    }
+}
+
+impl<R: Rng + ?Sized> Clone for HitRecord<'_, R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<R: Rng + ?Sized> Copy for HitRecord<'_, R> {}
 
 /// Trait for objects that may be intersected by a ray.
-pub trait Intersectable {
+pub trait Intersectable<R: Rng + ?Sized> {
     /// Finds the smallest value of `t` such that `r` intersects the object and `t` lies in `[t_min, t_max]`.
     /// 
-    /// Returns `t` if such a `t` is found, `f32::INFINITY` otherwise.
-    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> f32;
-}
\ No newline at end of file
+    /// Returns a record of the intersection at `t` if such a `t` is found, `None` otherwise.
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>>;
+}
+
+
+impl<R: Rng + ?Sized, T: Intersectable<R> + ?Sized> Intersectable<R> for Arc<T> {
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        (**self).intersect(r, t_min, t_max)
+    }
+}
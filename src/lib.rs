@@ -1,12 +1,44 @@
+/// Axis-aligned bounding boxes and the trait for objects that admit them.
+pub mod aabb;
+
+/// Bounding volume hierarchy for accelerating ray intersections with scenes containing many objects.
+pub mod bvh;
+
 /// Abstractions for working with the camera and scene-rendering.
 pub mod camera;
 
 /// Functions for working with RGB colours as real vectors with components in the range `[0, 1]`.
 pub mod color;
 
+/// Homogeneous participating media, such as fog and smoke, filling closed boundary surfaces.
+pub mod constant_medium;
+
+/// Accumulation of the samples taken of each pixel over the passes of a progressive render, and checkpoints for resuming it.
+pub mod film;
+
+/// Local orthonormal frames, in which surfaces of revolution are intersected.
+pub mod frame;
+
+/// Readers and writers for high dynamic range image formats, which store linear colours without clamping them.
+pub mod hdr;
+
 /// Abstractions for working with materials and various instances of materials.
 pub mod materials;
 
+/// Collection of the lights in a scene, used for sampling direct lighting.
+pub mod light_list;
+
+/// 4x4 matrices for affine transforms of points, vectors and normals.
+/// 
+/// Like the functions in `vector4`, matrix products use intrinsics for Intel's SSE ISA extensions where available.
+pub mod matrix4;
+
+/// Gradient noise and the fractal sums built from it, for procedural textures.
+pub mod noise;
+
+/// Loader for Wavefront OBJ meshes and their MTL material libraries.
+pub mod obj;
+
 /// Abstractions for working with orientable objects, i.e. objects that admit an assignment of
 /// surface normals to each point of their surface.
 pub mod orientable;
@@ -20,12 +52,30 @@ pub mod random;
 /// Abstractions for working with rays.
 pub mod ray;
 
+/// Abstractions for sampling points on the surfaces of objects, and the trait for objects that may be used as lights.
+pub mod sampleable;
+
 /// Naive collection for ray tracing of multi-object scenes.
 pub mod renderable_list;
 
+/// Real roots of polynomials of degree up to four, for intersecting rays with analytic surfaces.
+pub mod roots;
+
+/// Loader for scene description files, which describe the camera, materials and objects of a scene in TOML.
+pub mod scene;
+
 /// Intersectable surfaces.
 pub mod surfaces;
 
+/// Abstractions for working with textures and various instances of textures.
+pub mod textures;
+
+/// Instancing of objects placed in a scene by affine transforms, which may be animated by keyframes.
+pub mod transformed;
+
+/// Division of images into tiles and the queue handing them out to rendering threads.
+pub mod tiles;
+
 /// Linear algebra functions for vectors in 4-dimensional Euclidean space (i.e. `R^4`), including some functions 
 /// for vectors in 3-dimensional space which may be applied to 4-vectors with `w = 0`.
 /// 
@@ -0,0 +1,82 @@
+use crate::{
+    materials::Tangible,
+    sampleable::Light,
+    vector4::Vector4
+};
+use rand::Rng;
+use std::{
+    collections::HashMap,
+    sync::Arc
+};
+
+/// Collection of the objects in a scene that are sampled explicitly when estimating direct lighting.
+/// 
+/// Lights are shared with the scene, so every light should also be added to the scene that is rendered.
+pub struct LightList<R: Rng + ?Sized> {
+    lights: Vec<Arc<dyn Light<R> + Send + Sync>>,
+    indices: HashMap<usize, usize>      // Index of each light by the address of the object, see `address`.
+}
+
+impl<R: Rng + ?Sized> LightList<R> {
+    pub fn new() -> Self {
+        Self { lights: Vec::new(), indices: HashMap::new() }
+    }
+
+    pub fn get(&self, index: usize) -> &(dyn Light<R> + Send + Sync) {
+        &*self.lights[index]
+    }
+
+    pub fn push(&mut self, light: Arc<dyn Light<R> + Send + Sync>) {
+        self.indices.entry(address(&*light)).or_insert(self.lights.len());
+        self.lights.push(light);
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// Returns `true` if `object` is one of the lights in the list.
+    pub fn contains(&self, object: &(dyn Tangible<R> + Send + Sync)) -> bool {
+        self.find(object).is_some()
+    }
+
+    fn find(&self, object: &(dyn Tangible<R> + Send + Sync)) -> Option<&(dyn Light<R> + Send + Sync)> {
+        self.indices.get(&address(object)).map(|&i| &*self.lights[i])
+    }
+
+    /// Returns the probability density, with respect to solid angle, of `choose` followed by `Sampleable::sample`
+    /// choosing the point where the ray from `origin` in the unit vector `direction` first meets `object`,
+    /// or `0` if `object` is not in the list.
+    pub fn pdf(&self, object: &(dyn Tangible<R> + Send + Sync), origin: Vector4, direction: Vector4) -> f32 {
+        match self.find(object) {
+            Some(light) => light.pdf(origin, direction) / self.lights.len() as f32,
+            None => 0.0
+        }
+    }
+
+    /// Chooses a light uniformly at random. Returns the light and the probability of choosing it,
+    /// or `None` if the list is empty.
+    pub fn choose(&self, rng: &mut R) -> Option<(&(dyn Light<R> + Send + Sync), f32)> {
+        if self.lights.is_empty() {
+            return None;
+        }
+        let i = rng.random_range(0..self.lights.len());
+        Some((&*self.lights[i], 1.0 / self.lights.len() as f32))
+    }
+}
+
+/// Returns the address of the data of `object`, which identifies it whichever trait object it is viewed through, e.g.
+/// as a light when it is added to the list and as a tangible object when a ray hits it.
+fn address<T: ?Sized>(object: &T) -> usize {
+    object as *const T as *const () as usize
+}
+
+impl<R: Rng + ?Sized> Default for LightList<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,19 +1,35 @@
 use rand::Rng;
 use ray_tracing_in_one_weekend::{
+    bvh::Bvh,
     camera::{
+        AdaptiveSampling,
         Camera,
+        ProgressiveSettings,
+        StopReason,
         vfov_to_hfov
     },
+    color::ImageFormat,
+    film::{Checkpoint, Film},
+    light_list::LightList,
     materials::{
         dielectric::Dielectric,
         fuzzy_specular::FuzzySpecular,
         lambertian::Lambertian,
     },
     renderable_list::RenderableList,
-    surfaces::sphere::Sphere, 
+    scene::Scene,
+    surfaces::{moving_sphere::MovingSphere, plane::Plane, sphere::Sphere},
     vector4::Vector4
 };
-use std::sync::Arc;
+use std::{
+    env,
+    error::Error,
+    io::{self, BufWriter},
+    path::PathBuf,
+    process::ExitCode,
+    sync::Arc,
+    time::{Duration, Instant}
+};
 use rand_pcg::Pcg64Mcg;
 
 // Set RNG parameters.
@@ -36,7 +52,314 @@ const FOCUS_DISTANCE: f32 = 10.0;
 const DEFOCUS_ANGLE_DEG: f32 = 0.6;
 const MAX_FUZZING_ITERATIONS: usize = 4;
 
-fn main() {
+const PROGRESS_BAR_WIDTH: usize = 40;
+
+const USAGE: &str = "\
+Usage: ray-tracing-in-one-weekend [OPTIONS] [SCENE]
+
+Renders the TOML scene file SCENE, or the final scene of \"Ray Tracing in One Weekend\" if no file is given.
+
+Options:
+  -o, --output PATH          Write the image to PATH instead of standard output
+  -f, --format FORMAT        Image format: p3, p6, png, png16, pfm, hdr, exr or exr-zip
+                             [default: from the extension of PATH, p3 for standard output]
+  -r, --resolution WxH       Image size in pixels, or only the width to keep the aspect ratio of the scene
+  -s, --spp N                Samples per pixel
+  -d, --depth N              Maximum number of bounces per path
+  -t, --threads N            Number of rendering threads, 0 for one per core [default: 0]
+      --seed N               Seed of the random number generators
+      --adaptive X           Sample each pixel adaptively, up to --spp samples, until the 95% confidence interval of
+                             its mean luminance is narrower than X relative to the mean
+      --min-spp N            Samples per pixel before adaptive sampling may stop [default: 8]
+      --heatmap PATH         Also write an image of the number of samples of each pixel to PATH
+      --bouncing             Without SCENE, let the small diffuse spheres bounce up while the shutter is open, like the
+                             motion blur scene of \"Ray Tracing: The Next Week\"
+
+Progressive rendering, enabled by any of these options, renders in passes until --spp samples per pixel are reached or
+another stop condition is met, writing snapshots of the image to the output path:
+      --pass-spp N           Samples per pixel of each pass [default: 1]
+      --time-limit SECONDS   Stop after the pass during which the time limit is reached
+      --noise-threshold X    Stop once the mean relative error of the pixels falls below X
+      --snapshot-interval SECONDS
+                             Minimum time between snapshots [default: a snapshot after every pass]
+      --checkpoint PATH      Save the state of the render to PATH with every snapshot and when it stops
+      --resume PATH          Continue the render saved in the checkpoint PATH, with its seed and samples per pass, and
+                             keep saving checkpoints to PATH unless --checkpoint is given
+  -h, --help                 Print this help
+";
+
+/// Options given on the command line, `None` where the scene decides.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Options {
+    scene: Option<PathBuf>,
+    output: Option<PathBuf>,
+    format: Option<ImageFormat>,
+    resolution: Option<(usize, Option<usize>)>,    // Width and optionally height.
+    samples_per_pixel: Option<usize>,
+    max_depth: Option<usize>,
+    thread_count: usize,
+    seed: Option<u64>,
+    adaptive_threshold: Option<f32>,
+    min_samples: Option<usize>,
+    heatmap: Option<PathBuf>,
+    bouncing: bool,
+    samples_per_pass: Option<usize>,
+    time_limit: Option<Duration>,
+    noise_threshold: Option<f32>,
+    snapshot_interval: Option<Duration>,
+    checkpoint: Option<PathBuf>,
+    resume: Option<PathBuf>,
+    help: bool
+}
+
+impl Options {
+    fn progressive(&self) -> bool {
+        self.samples_per_pass.is_some()
+            || self.time_limit.is_some()
+            || self.noise_threshold.is_some()
+            || self.snapshot_interval.is_some()
+            || self.checkpoint.is_some()
+            || self.resume.is_some()
+    }
+}
+
+/// Parses the command-line arguments following the program name.
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+    fn number<T: std::str::FromStr>(option: &str, value: &str) -> Result<T, String> {
+        value.parse().map_err(|_| format!("invalid value '{}' for {}", value, option))
+    }
+    fn positive(option: &str, value: &str) -> Result<usize, String> {
+        match number(option, value)? {
+            0 => Err(format!("{} must be positive", option)),
+            n => Ok(n)
+        }
+    }
+    fn non_negative(option: &str, value: &str) -> Result<f32, String> {
+        match number(option, value)? {
+            x if x >= 0.0 && f32::is_finite(x) => Ok(x),
+            _ => Err(format!("{} must be a non-negative number", option))
+        }
+    }
+    fn seconds(option: &str, value: &str) -> Result<Duration, String> {
+        Duration::try_from_secs_f32(non_negative(option, value)?).map_err(|_| format!("{} is too long", option))
+    }
+
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // Accept both "--option value" and "--option=value".
+        let (option, inline_value) = match arg.split_once('=') {
+            Some((option, value)) if option.starts_with("--") => (option.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None)
+        };
+        let mut value = || inline_value.clone().or_else(|| args.next()).ok_or_else(|| format!("missing value for {}", option));
+        match option.as_str() {
+            "-h" | "--help" => options.help = true,
+            "-o" | "--output" => options.output = Some(PathBuf::from(value()?)),
+            "-f" | "--format" => options.format = Some(value()?.parse()?),
+            "-r" | "--resolution" => {
+                let value = value()?;
+                options.resolution = Some(match value.split_once('x') {
+                    Some((width, height)) => (positive(&option, width)?, Some(positive(&option, height)?)),
+                    None => (positive(&option, &value)?, None)
+                });
+            },
+            "-s" | "--spp" => options.samples_per_pixel = Some(positive(&option, &value()?)?),
+            "-d" | "--depth" => options.max_depth = Some(number(&option, &value()?)?),
+            "-t" | "--threads" => options.thread_count = number(&option, &value()?)?,
+            "--seed" => options.seed = Some(number(&option, &value()?)?),
+            "--adaptive" => options.adaptive_threshold = Some(non_negative(&option, &value()?)?),
+            "--min-spp" => options.min_samples = match number(&option, &value()?)? {
+                n if n < 2 => return Err(format!("{} must be at least 2", option)),
+                n => Some(n)
+            },
+            "--heatmap" => options.heatmap = Some(PathBuf::from(value()?)),
+            "--bouncing" => options.bouncing = true,
+            "--pass-spp" => options.samples_per_pass = Some(positive(&option, &value()?)?),
+            "--time-limit" => options.time_limit = Some(seconds(&option, &value()?)?),
+            "--noise-threshold" => options.noise_threshold = Some(non_negative(&option, &value()?)?),
+            "--snapshot-interval" => options.snapshot_interval = Some(seconds(&option, &value()?)?),
+            "--checkpoint" => options.checkpoint = Some(PathBuf::from(value()?)),
+            "--resume" => options.resume = Some(PathBuf::from(value()?)),
+            _ if option.starts_with('-') && option.len() > 1 => return Err(format!("unknown option {}", option)),
+            _ if options.scene.is_none() => options.scene = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument {}", arg))
+        }
+    }
+    Ok(options)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    if options.help {
+        print!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    match run(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(options: Options) -> Result<(), Box<dyn Error>> {
+    // Work out the output format before rendering, so that a bad output path fails immediately.
+    let format = match (&options.output, options.format) {
+        (_, Some(format)) => format,
+        (Some(path), None) => ImageFormat::from_path(path, COLOR_DEPTH)
+            .ok_or_else(|| format!("can not tell the image format from {}, use --format", path.display()))?,
+        (None, None) => ImageFormat::P3
+    };
+    let heatmap_format = match &options.heatmap {
+        Some(path) => Some(ImageFormat::from_path(path, COLOR_DEPTH).ok_or_else(|| format!("can not tell the image format from {}", path.display()))?),
+        None => None
+    };
+    if options.adaptive_threshold.is_some() && options.progressive() {
+        return Err("--adaptive can not be combined with progressive rendering, use --noise-threshold instead".into());
+    }
+    if options.bouncing && options.scene.is_some() {
+        return Err("--bouncing only applies to the scene rendered without SCENE".into());
+    }
+
+    let (mut camera, objects, lights) = match &options.scene {
+        Some(path) => {
+            let scene = Scene::<Pcg64Mcg>::load(path)?;
+            (scene.camera, scene.objects, scene.lights)
+        },
+        None => final_scene(options.bouncing)
+    };
+    if let Some((width, height)) = options.resolution {
+        let aspect_ratio = camera.image_width() as f32 / camera.image_height() as f32;
+        let height = height.unwrap_or_else(|| usize::max(1, (width as f32 / aspect_ratio).round() as usize));
+        camera = camera.with_resolution(width, height);
+    }
+    if let Some(samples_per_pixel) = options.samples_per_pixel {
+        camera = camera.with_samples_per_pixel(samples_per_pixel);
+    }
+    if let Some(max_depth) = options.max_depth {
+        camera = camera.with_max_depth(max_depth);
+    }
+    if let Some(threshold) = options.adaptive_threshold {
+        camera = camera.with_adaptive_sampling(AdaptiveSampling::new(options.min_samples.unwrap_or(8), threshold));
+    }
+    let mut seed = options.seed.unwrap_or(RNG_SEED as u64);
+    let mut samples_per_pass = options.samples_per_pass.unwrap_or(1);
+    let mut film = Film::new(camera.image_width(), camera.image_height());
+    if let Some(path) = &options.resume {
+        let checkpoint = Checkpoint::load(path).map_err(|e| format!("can not read checkpoint {}: {}", path.display(), e))?;
+        if options.seed.is_some_and(|s| s != checkpoint.seed) {
+            return Err(format!("--seed differs from the seed {} of the checkpoint", checkpoint.seed).into());
+        }
+        if options.samples_per_pass.is_some_and(|n| n != checkpoint.samples_per_pass) {
+            return Err(format!("--pass-spp differs from the {} samples per pass of the checkpoint", checkpoint.samples_per_pass).into());
+        }
+        if (checkpoint.film.width(), checkpoint.film.height()) != (camera.image_width(), camera.image_height()) {
+            return Err(format!(
+                "the checkpoint is of a {}x{} image but the scene is rendered at {}x{}",
+                checkpoint.film.width(),
+                checkpoint.film.height(),
+                camera.image_width(),
+                camera.image_height()
+            ).into());
+        }
+        eprintln!("Resuming after pass {} at {:.0} samples per pixel.", checkpoint.film.passes(), checkpoint.film.mean_sample_count());
+        (seed, samples_per_pass, film) = (checkpoint.seed, checkpoint.samples_per_pass, checkpoint.film);
+    }
+    let checkpoint_path = options.checkpoint.as_ref().or(options.resume.as_ref());
+
+    let scene = Bvh::from(objects);
+    let start = Instant::now();
+    if options.progressive() {
+        let mut settings = ProgressiveSettings::new(samples_per_pass, camera.samples_per_pixel());
+        if let Some(time_limit) = options.time_limit {
+            settings = settings.with_time_budget(time_limit);
+        }
+        if let Some(threshold) = options.noise_threshold {
+            settings = settings.with_noise_threshold(threshold);
+        }
+        if let Some(interval) = options.snapshot_interval {
+            settings = settings.with_snapshot_interval(interval);
+        }
+        let save_checkpoint = |film: &Film| match checkpoint_path {
+            Some(path) => Checkpoint::new(seed, samples_per_pass, film.clone()).save(path),
+            None => Ok(())
+        };
+        let mut snapshot_error = None;
+        let reason = camera.render_progressive(seed, &scene, &lights, options.thread_count, &settings, &mut film, &mut |film| {
+            eprintln!(
+                "Pass {}: {:.0} samples per pixel, noise {:.4}, {:.1} s",
+                film.passes(),
+                film.mean_sample_count(),
+                film.noise(),
+                start.elapsed().as_secs_f32()
+            );
+            if let Some(path) = &options.output
+                && let Err(e) = camera.image(film).save_as(path, format)
+            {
+                snapshot_error.get_or_insert(e);
+            }
+            if let Err(e) = save_checkpoint(film) {
+                snapshot_error.get_or_insert(e);
+            }
+        });
+        if let Some(e) = snapshot_error {
+            return Err(e.into());
+        }
+        save_checkpoint(&film)?;
+        eprintln!("Stopped after {} passes: {}.", film.passes(), match reason {
+            StopReason::SampleCount => "sample count reached",
+            StopReason::TimeBudget => "time limit reached",
+            StopReason::NoiseThreshold => "noise threshold reached"
+        });
+    } else {
+        film = camera.render_film(seed, &scene, &lights, options.thread_count, &|finished, total| {
+            let filled = PROGRESS_BAR_WIDTH * finished / total;
+            eprint!(
+                "\r[{}{}] {:3}% {}/{} tiles {:.1} s",
+                "#".repeat(filled),
+                "-".repeat(PROGRESS_BAR_WIDTH - filled),
+                100 * finished / total,
+                finished,
+                total,
+                start.elapsed().as_secs_f32()
+            );
+        });
+        eprintln!();
+    }
+    let elapsed = start.elapsed().as_secs_f32();
+    let sample_count = (camera.image_width() * camera.image_height()) as f32 * film.mean_sample_count();
+    eprintln!(
+        "Rendered {}x{} pixels at {:.1} samples per pixel in {:.2} s ({:.2} M samples/s).",
+        camera.image_width(),
+        camera.image_height(),
+        film.mean_sample_count(),
+        elapsed,
+        sample_count / elapsed / 1e6
+    );
+
+    if let (Some(path), Some(heatmap_format)) = (&options.heatmap, heatmap_format) {
+        film.sample_count_heatmap(COLOR_DEPTH).save_as(path, heatmap_format)?;
+    }
+    let image = camera.image(&film);
+    match &options.output {
+        Some(path) => image.save_as(path, format)?,
+        None => image.write(BufWriter::new(io::stdout().lock()), format)?
+    }
+    Ok(())
+}
+
+/// Builds the final scene of "Ray Tracing in One Weekend", with small spheres of random materials scattered around
+/// three large ones. If `bouncing`, the small diffuse spheres move up by a random height while the shutter is open, as in
+/// the first scene of "Ray Tracing: The Next Week".
+fn final_scene(bouncing: bool) -> (Camera, RenderableList<Pcg64Mcg>, LightList<Pcg64Mcg>) {
     // RNG.
     let mut rng = Pcg64Mcg::new(RNG_SEED);
 
@@ -58,17 +381,18 @@ fn main() {
         MAX_DEPTH,
         T_MIN,
         T_MAX
-    );
+    )
+    .with_shutter(0.0, if bouncing { 1.0 } else { 0.0 });
 
     // Materials.
     let material_ground = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
     let material_glass = Arc::new(Dielectric::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 1.5));
     let material_metal = Arc::new(FuzzySpecular::new(Vector4::new(0.7, 0.6, 0.5, 0.0), 0.3, MAX_FUZZING_ITERATIONS));
     let material_diffuse_brown = Arc::new(Lambertian::new(Vector4::new(0.4, 0.2, 0.1, 0.0)));
-    
+
     // Scene.
     let mut scene = RenderableList::<Pcg64Mcg>::new();
-    scene.push(Box::new(Sphere::new(Vector4::new(0.0, 0.0, -1000.0, 0.0), 1000.0, material_ground.clone())));
+    scene.push(Box::new(Plane::new(Vector4::new(0.0, 0.0, 0.0, 0.0), Vector4::new(0.0, 0.0, 1.0, 0.0), material_ground.clone())));
     scene.push(Box::new(Sphere::new(Vector4::new(0.0, 0.0, 1.0, 0.0), 1.0, material_glass.clone())));
     scene.push(Box::new(Sphere::new(Vector4::new(-4.0, 0.0, 1.0, 0.0), 1.0, material_diffuse_brown.clone())));
     scene.push(Box::new(Sphere::new(Vector4::new(4.0, 0.0, 1.0, 0.0), 1.0, material_metal.clone())));
@@ -85,7 +409,12 @@ fn main() {
                 if material_selector < 0.8 {
                     let (r, g, b): (f32, f32, f32) = rng.random();
                     let material = Arc::new(Lambertian::new(Vector4::new(r, g, b, 0.0)));
-                    scene.push(Box::new(Sphere::new(center, 0.2, material)));
+                    if bouncing {
+                        let center1 = center + Vector4::new(0.0, 0.0, rng.random_range(0.0..0.5), 0.0);
+                        scene.push(Box::new(MovingSphere::new(center, center1, 0.0, 1.0, 0.2, material)));
+                    } else {
+                        scene.push(Box::new(Sphere::new(center, 0.2, material)));
+                    }
                 }
                 // (Fuzzy) Specular.
                 else if material_selector < 0.95 {
@@ -106,10 +435,58 @@ fn main() {
         i += 1.0;
     }
 
-    let scene = Arc::new(scene);
+    (camera, scene, LightList::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
 
-    // let image = camera.render(&mut rng, &scene);
-    // image.write_p3_image_stdout();
-    let image = camera.render_concurrent(scene.clone(), 4);
-    image.write_p3_image_stdout();
-}
\ No newline at end of file
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse(&[]), Ok(Options::default()));
+        assert_eq!(
+            parse(&["scenes/spheres.toml", "-o", "out.exr", "--resolution=640x360", "--spp", "64", "-d", "8", "-t", "3", "--seed", "7"]),
+            Ok(Options {
+                scene: Some(PathBuf::from("scenes/spheres.toml")),
+                output: Some(PathBuf::from("out.exr")),
+                resolution: Some((640, Some(360))),
+                samples_per_pixel: Some(64),
+                max_depth: Some(8),
+                thread_count: 3,
+                seed: Some(7),
+                ..Options::default()
+            })
+        );
+        assert_eq!(parse(&["-r", "800", "--format", "png16"]).map(|o| (o.resolution, o.format)), Ok((Some((800, None)), Some(ImageFormat::Png16))));
+        assert!(parse(&["-h"]).unwrap().help);
+        assert!(!parse(&["--spp", "8"]).unwrap().progressive());
+        let options = parse(&["--time-limit", "2.5", "--snapshot-interval=10"]).unwrap();
+        assert!(options.progressive());
+        assert_eq!((options.time_limit, options.snapshot_interval), (Some(Duration::from_millis(2500)), Some(Duration::from_secs(10))));
+        assert!(parse(&["--resume", "render.ckpt"]).unwrap().progressive());
+        assert!(parse(&["--bouncing"]).unwrap().bouncing);
+        let options = parse(&["--adaptive", "0.05", "--min-spp", "4", "--heatmap", "samples.png"]).unwrap();
+        assert!(!options.progressive());
+        assert_eq!((options.adaptive_threshold, options.min_samples), (Some(0.05), Some(4)));
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert_eq!(parse(&["--spp"]), Err("missing value for --spp".to_string()));
+        assert_eq!(parse(&["--spp", "0"]), Err("--spp must be positive".to_string()));
+        assert_eq!(parse(&["-r", "640x"]), Err("invalid value '' for -r".to_string()));
+        assert_eq!(parse(&["--seed", "-1"]), Err("invalid value '-1' for --seed".to_string()));
+        assert_eq!(parse(&["--format", "jpg"]), Err("unknown image format 'jpg'".to_string()));
+        assert_eq!(parse(&["--noise-threshold", "-0.1"]), Err("--noise-threshold must be a non-negative number".to_string()));
+        assert_eq!(parse(&["--time-limit", "1e30"]), Err("--time-limit is too long".to_string()));
+        assert_eq!(parse(&["--snapshot-interval", "nan"]), Err("--snapshot-interval must be a non-negative number".to_string()));
+        assert_eq!(parse(&["--min-spp", "1"]), Err("--min-spp must be at least 2".to_string()));
+        assert_eq!(parse(&["--verbose"]), Err("unknown option --verbose".to_string()));
+        assert_eq!(parse(&["a.toml", "b.toml"]), Err("unexpected argument b.toml".to_string()));
+    }
+}
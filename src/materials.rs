@@ -1,6 +1,6 @@
 use crate::{
-    intersectable::Intersectable,
-    orientable::Orientable,
+    aabb::Bounded,
+    intersectable::{HitRecord, Intersectable},
     ray::Ray,
     vector4::Vector4
 };
@@ -12,38 +12,87 @@ use std::sync::Arc;
 pub struct None;
 
 impl<R: Rng + ?Sized> Material<R> for None {
-    fn attenuation(&self, _rng: &mut R, _r: Ray, _t: f32, _n: Vector4, _is_inside: bool) -> Vector4 {
-        // Do not attenuate incoming rays.
-        Vector4::new(1.0, 1.0, 1.0, 0.0)
-    }
-
-    fn scatter(&self, _rng: &mut R, r: Ray, t: f32, _n: Vector4, _is_inside: bool) -> Option<Ray> {
-        // Do not scatter incoming rays.
-        Some(Ray::new(r.at(t), r.direction))
+    fn scatter(&self, _rng: &mut R, r: Ray, _hit: &HitRecord<R>) -> Option<ScatterRecord> {
+        // Neither attenuate nor scatter incoming rays.
+        Some(ScatterRecord::specular(r.direction.normalize(), Vector4::new(1.0, 1.0, 1.0, 0.0), 1.0))
     }
 }
 
 /// Trait for tangible objects, i.e. objects consisting of a material.
-/// 
-/// Note that the `t` value provided to the functions implemented by this trait must yield an intersection between `r` and
-/// the implementer for correct behaviour.
-pub trait Tangible<R: Rng + ?Sized>: Intersectable + Orientable {
+pub trait Tangible<R: Rng + ?Sized>: Intersectable<R> + Bounded {
     fn material(&self) -> &Arc<dyn Material<R> + Send + Sync>;
+}
+
+/// Shared objects are tangible, so that an object may be added both to a scene and to its `LightList`.
+impl<R: Rng + ?Sized, T: Tangible<R> + ?Sized> Tangible<R> for Arc<T> {
+    fn material(&self) -> &Arc<dyn Material<R> + Send + Sync> {
+        (**self).material()
+    }
+}
+
+/// Kind of the part of a material's scattering function from which a direction was sampled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lobe {
+    /// Scatters rays into all directions of a hemisphere.
+    Diffuse,
+    /// Scatters rays into a spread of directions about a preferred one, e.g. rough metals.
+    Glossy,
+    /// Scatters rays into a single direction, e.g. mirrors and glass. Specular lobes are described by a delta
+    /// distribution, so they are not accounted for by `Material::eval` and `Material::pdf`.
+    Specular
+}
+
+/// Direction sampled by a material, together with everything needed to continue a path in that direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScatterRecord {
+    pub direction: Vector4, // Unit vector.
+    pub weight: Vector4,    // Throughput weight of the path continued in direction, i.e. eval / pdf for non-specular lobes.
+    pub pdf: f32,           // Density with respect to solid angle, or the probability of choosing the lobe for specular lobes.
+    pub lobe: Lobe
+}
 
-    fn attenuation(&self, rng: &mut R, r: Ray, t: f32) -> Vector4 {
-        self.material().attenuation(rng, r, t, self.normal(r.at(t)), self.is_inside(r, t))
+impl ScatterRecord {
+    pub fn new(direction: Vector4, weight: Vector4, pdf: f32, lobe: Lobe) -> Self {
+        Self { direction, weight, pdf, lobe }
     }
 
-    fn scatter(&self, rng: &mut R, r: Ray, t: f32) -> Option<Ray> {
-        self.material().scatter(rng, r, t, self.normal(r.at(t)), self.is_inside(r, t))
+    /// Constructs a record for a direction sampled from a specular lobe chosen with probability `probability`.
+    pub fn specular(direction: Vector4, weight: Vector4, probability: f32) -> Self {
+        Self { direction, weight, pdf: probability, lobe: Lobe::Specular }
     }
 }
 
-/// Trait defining a common interface for materials.
+/// Trait defining a common interface for materials, i.e. bidirectional scattering distribution functions (BSDFs).
+/// 
+/// Directions passed to and returned from materials are unit vectors pointing away from the surface, and the direction
+/// the light scatters into is always the one opposite that of `r`.
+/// Note that `hit` must be a record of an intersection between `r` and an object made of the implementer for correct behaviour.
 pub trait Material<R: Rng + ?Sized> {
-    fn attenuation(&self, rng: &mut R, r: Ray, t: f32, n: Vector4, is_inside: bool) -> Vector4;
+    /// Samples a direction to continue the path along `r` in. Returns `None` if the ray is absorbed.
+    fn scatter(&self, rng: &mut R, r: Ray, hit: &HitRecord<R>) -> Option<ScatterRecord>;
+
+    /// Returns the fraction of the radiance arriving at `hit.p` from `direction` that is scattered in the direction opposite
+    /// that of `r`, including the cosine of the angle between `direction` and the surface normal.
+    /// 
+    /// Only the non-specular lobes of the material contribute, so materials consisting solely of specular lobes need not
+    /// implement this.
+    fn eval(&self, _r: Ray, _hit: &HitRecord<R>, _direction: Vector4) -> Vector4 {
+        Vector4::new(0.0, 0.0, 0.0, 0.0)
+    }
 
-    fn scatter(&self, rng: &mut R, r: Ray, t: f32, n: Vector4, is_inside: bool) -> Option<Ray>;
+    /// Returns the probability density, with respect to solid angle, of `scatter` sampling `direction` from a non-specular lobe.
+    /// 
+    /// The density integrates to the probability that `scatter` samples a non-specular lobe, so materials consisting solely
+    /// of specular lobes need not implement this.
+    fn pdf(&self, _r: Ray, _hit: &HitRecord<R>, _direction: Vector4) -> f32 {
+        0.0
+    }
+
+    /// Returns the radiance emitted from `hit.p` in the direction opposite that of `r`. Materials that do not emit light
+    /// need not implement this.
+    fn emitted(&self, _r: Ray, _hit: &HitRecord<R>) -> Vector4 {
+        Vector4::new(0.0, 0.0, 0.0, 0.0)
+    }
 }
 
 /// Dielectric material that attenuates rays in accordance with Beer's law.
@@ -52,11 +101,208 @@ pub mod dielectric;
 /// Non-Lambertian diffuse material that randomly reflects incoming rays.
 pub mod diffuse;
 
+/// Emissive material that emits light uniformly in all directions and absorbs incoming rays.
+pub mod diffuse_light;
+
 /// Specular material with reflected ray fuzzing.
 pub mod fuzzy_specular;
 
+/// Henyey-Greenstein phase function for participating media that scatter light mostly forwards or backwards.
+pub mod henyey_greenstein;
+
+/// Isotropic phase function for participating media.
+pub mod isotropic;
+
 /// Lambertian diffuse material.
 pub mod lambertian;
 
 /// Specular material, may be used for metals or mirrors.
-pub mod specular;
\ No newline at end of file
+pub mod specular;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        materials::{
+            dielectric::Dielectric,
+            diffuse::Diffuse,
+            fuzzy_specular::FuzzySpecular,
+            henyey_greenstein::HenyeyGreenstein,
+            isotropic::Isotropic,
+            lambertian::Lambertian,
+            specular::Specular
+        },
+        random::sample_unit_sphere_uniform,
+        surfaces::{quad::Quad, sphere::Sphere},
+        textures::checker::Checker
+    };
+    use rand_pcg::Pcg64Mcg;
+    use std::f32::consts::PI;
+
+    const SAMPLE_COUNT: usize = 200000;
+
+    fn test_sphere(material: Arc<dyn Material<Pcg64Mcg> + Send + Sync>) -> Sphere<Pcg64Mcg> {
+        Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 1.0, material)
+    }
+
+    /// Ray hitting the unit sphere at (0, 0, 1) at an angle of 60 degrees to the normal.
+    fn test_ray() -> Ray {
+        let direction = Vector4::new(f32::sqrt(3.0) / 2.0, 0.0, -0.5, 0.0);
+        Ray::new(Vector4::new(0.0, 0.0, 1.0, 0.0) - 2.0 * direction, direction)
+    }
+
+    fn non_specular_materials() -> Vec<Arc<dyn Material<Pcg64Mcg> + Send + Sync>> {
+        let attenuation = Vector4::new(0.8, 0.6, 0.4, 0.0);
+        vec![
+            Arc::new(Lambertian::new(attenuation)),
+            Arc::new(Diffuse::new(attenuation)),
+            Arc::new(FuzzySpecular::new(attenuation, 0.5, 4)),
+            Arc::new(FuzzySpecular::new(attenuation, 2.0, 2)),
+            Arc::new(Isotropic::new(attenuation)),
+            Arc::new(HenyeyGreenstein::new(attenuation, 0.7)),
+            Arc::new(HenyeyGreenstein::new(attenuation, -0.3))
+        ]
+    }
+
+    #[test]
+    fn test_pdf_integrates_to_scatter_probability() {
+        // The diffuse materials and phase functions always scatter, so their densities integrate to one. FuzzySpecular
+        // absorbs rays for which no direction above the surface is found.
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        for material in non_specular_materials() {
+            let sphere = test_sphere(material.clone());
+            let r = test_ray();
+            let hit = sphere.intersect(r, 0.001, f32::INFINITY).unwrap();
+
+            // Integrate the density over the sphere of directions by uniform sampling.
+            let integral = (0..SAMPLE_COUNT)
+                .map(|_| 4.0 * PI * material.pdf(r, &hit, sample_unit_sphere_uniform(&mut rng)))
+                .sum::<f32>() / SAMPLE_COUNT as f32;
+            let scatter_probability = (0..SAMPLE_COUNT)
+                .filter(|_| material.scatter(&mut rng, r, &hit).is_some())
+                .count() as f32 / SAMPLE_COUNT as f32;
+            assert!((integral - scatter_probability).abs() < 0.02, "{} != {}", integral, scatter_probability);
+        }
+    }
+
+    #[test]
+    fn test_sample_records_match_eval_and_pdf() {
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        for material in non_specular_materials() {
+            let sphere = test_sphere(material.clone());
+            let r = test_ray();
+            let hit = sphere.intersect(r, 0.001, f32::INFINITY).unwrap();
+            for _ in 0..1000 {
+                let Some(scattered) = material.scatter(&mut rng, r, &hit) else {
+                    continue;
+                };
+                assert_ne!(scattered.lobe, Lobe::Specular);
+                assert!((scattered.direction.norm() - 1.0).abs() < 1e-5);
+                let pdf = material.pdf(r, &hit, scattered.direction);
+                if scattered.pdf == 0.0 && pdf == 0.0 {
+                    // Directions at the edge of the fuzzed cone may be rounded to lie just outside of it, where the density is zero.
+                    continue;
+                }
+                assert!((scattered.pdf / pdf - 1.0).abs() < 1e-3, "{} != {}", scattered.pdf, pdf);
+                let weight = material.eval(r, &hit, scattered.direction) / pdf;
+                assert!((scattered.weight - weight).norm() < 1e-3, "{:?} != {:?}", scattered.weight, weight);
+            }
+        }
+    }
+
+    #[test]
+    fn test_back_faces() {
+        // A quad facing up, lit from below, reflects the light back down from its back face like from its front face.
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        let up = Vector4::new(0.0, 0.0, 1.0, 0.0);
+        let towards_light = Vector4::new(-0.6, 0.0, -0.8, 0.0);
+        let attenuation = Vector4::new(0.8, 0.6, 0.4, 0.0);
+        let materials: [Arc<dyn Material<Pcg64Mcg> + Send + Sync>; 3] = [
+            Arc::new(Lambertian::new(attenuation)),
+            Arc::new(Diffuse::new(attenuation)),
+            Arc::new(FuzzySpecular::new(attenuation, 0.5, 4))
+        ];
+        for material in materials {
+            let quad = Quad::new(Vector4::new(-1.0, -1.0, 0.0, 0.0), Vector4::new(2.0, 0.0, 0.0, 0.0), Vector4::new(0.0, 2.0, 0.0, 0.0), material.clone());
+            let r = Ray::new(Vector4::new(1.5, 0.0, -2.0, 0.0), Vector4::new(-0.6, 0.0, 0.8, 0.0));
+            let hit = quad.intersect(r, 0.001, f32::INFINITY).unwrap();
+            assert!(!hit.front_face);
+            assert_eq!(hit.facing_normal(), -up);
+            for _ in 0..1000 {
+                let scattered = material.scatter(&mut rng, r, &hit).expect("rays should not be absorbed");
+                assert!(scattered.direction.dot(up) < 0.0, "{:?} passes through the quad", scattered.direction);
+            }
+            assert!(material.eval(r, &hit, towards_light).x() > 0.0);
+            assert!(material.pdf(r, &hit, towards_light) > 0.0);
+            assert_eq!(material.pdf(r, &hit, -towards_light), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_henyey_greenstein_asymmetry() {
+        // The mean cosine of the angle between the incoming and scattered directions is the asymmetry parameter.
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        for g in [-0.8, -0.3, 0.0, 0.5, 0.9] {
+            let sphere = test_sphere(Arc::new(HenyeyGreenstein::new(Vector4::new(1.0, 1.0, 1.0, 0.0), g)));
+            let r = test_ray();
+            let hit = sphere.intersect(r, 0.001, f32::INFINITY).unwrap();
+            let mean_cosine = (0..SAMPLE_COUNT)
+                .map(|_| hit.material.scatter(&mut rng, r, &hit).unwrap().direction.dot(r.direction))
+                .sum::<f32>() / SAMPLE_COUNT as f32;
+            assert!((mean_cosine - g).abs() < 0.01, "{} != {}", mean_cosine, g);
+        }
+    }
+
+    #[test]
+    fn test_specular_lobe_probabilities() {
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        let r = test_ray();
+
+        let sphere = test_sphere(Arc::new(Specular::new(Vector4::new(0.9, 0.9, 0.9, 0.0))));
+        let hit = sphere.intersect(r, 0.001, f32::INFINITY).unwrap();
+        let scattered = hit.material.scatter(&mut rng, r, &hit).unwrap();
+        assert_eq!(scattered.lobe, Lobe::Specular);
+        assert_eq!(scattered.pdf, 1.0);
+        assert!((scattered.direction - Vector4::new(f32::sqrt(3.0) / 2.0, 0.0, 0.5, 0.0)).norm() < 1e-6);
+        assert_eq!(hit.material.pdf(r, &hit, scattered.direction), 0.0);
+
+        // The probabilities of the reflected and refracted lobes sum to one and match how often they are chosen.
+        let sphere = test_sphere(Arc::new(Dielectric::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 1.5)));
+        let hit = sphere.intersect(r, 0.001, f32::INFINITY).unwrap();
+        let mut reflected_count = 0;
+        let mut probabilities = (0.0, 0.0);
+        for _ in 0..SAMPLE_COUNT {
+            let scattered = hit.material.scatter(&mut rng, r, &hit).unwrap();
+            assert_eq!(scattered.lobe, Lobe::Specular);
+            if scattered.direction.z() > 0.0 {
+                reflected_count += 1;
+                probabilities.0 = scattered.pdf;
+            } else {
+                probabilities.1 = scattered.pdf;
+            }
+        }
+        assert!((probabilities.0 + probabilities.1 - 1.0).abs() < 1e-6);
+        assert!((reflected_count as f32 / SAMPLE_COUNT as f32 - probabilities.0).abs() < 0.005);
+    }
+
+    #[test]
+    fn test_textured_attenuation() {
+        // The test ray hits the sphere at (0, 0, 1), which lies in an odd cell of the checker pattern.
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        let odd = Vector4::new(0.2, 0.4, 0.6, 0.0);
+        let checker = Arc::new(Checker::from_colors(0.3, Vector4::new(1.0, 1.0, 1.0, 0.0), odd));
+        let materials: [Arc<dyn Material<Pcg64Mcg> + Send + Sync>; 4] = [
+            Arc::new(Lambertian::from_texture(checker.clone())),
+            Arc::new(Diffuse::from_texture(checker.clone())),
+            Arc::new(Specular::from_texture(checker.clone())),
+            Arc::new(FuzzySpecular::from_texture(checker, 0.1, 4))
+        ];
+        for material in materials {
+            let sphere = test_sphere(material.clone());
+            let r = test_ray();
+            let hit = sphere.intersect(r, 0.001, f32::INFINITY).unwrap();
+            let scattered = material.scatter(&mut rng, r, &hit).unwrap();
+            assert_eq!(scattered.weight, odd);
+        }
+    }
+}
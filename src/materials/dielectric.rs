@@ -1,5 +1,6 @@
 use crate::{
-    materials::Material,
+    intersectable::HitRecord,
+    materials::{Material, ScatterRecord},
     ray::Ray,
     vector4::Vector4
 };
@@ -24,42 +25,47 @@ impl Dielectric {
 }
 
 impl<R: Rng + ?Sized> Material<R> for Dielectric {
-    fn attenuation(&self, _rng: &mut R, r: Ray, t: f32, _n: Vector4, is_inside: bool) -> Vector4 {
-        if is_inside && self.absorbance != Vector4::new(0.0, 0.0, 0.0, 0.0) {
-            return Vector4::new(
-                f32::exp(-self.absorbance.x() * r.length(t)),
-                f32::exp(-self.absorbance.y() * r.length(t)),
-                f32::exp(-self.absorbance.z() * r.length(t)),
+    fn scatter(&self, rng: &mut R, r: Ray, hit: &HitRecord<R>) -> Option<ScatterRecord> {
+        // Rays leaving the object are attenuated according to the distance they travelled inside it.
+        let attenuation = if !hit.front_face && self.absorbance != Vector4::new(0.0, 0.0, 0.0, 0.0) {
+            Vector4::new(
+                f32::exp(-self.absorbance.x() * r.length(hit.t)),
+                f32::exp(-self.absorbance.y() * r.length(hit.t)),
+                f32::exp(-self.absorbance.z() * r.length(hit.t)),
                 0.0
-            );
-        }
-        Vector4::new(1.0, 1.0, 1.0, 0.0)
-    }
+            )
+        } else {
+            Vector4::new(1.0, 1.0, 1.0, 0.0)
+        };
 
-    fn scatter(&self, rng: &mut R, r: Ray, t: f32, n: Vector4, is_inside: bool) -> Option<Ray> {
         // The relative refractive index must be inverted if the intersection occurred with
         // the ray going into the object.
-        let (relative_refractive_index, normal_adjustment) = match is_inside {
+        let (relative_refractive_index, normal_adjustment) = match !hit.front_face {
             true => (self.relative_refractive_index, 1.0),
             _ => (self.relative_refractive_index.recip(), -1.0)
         };
         let direction_in = r.direction.normalize();
-        let local_normal = -normal_adjustment * n;
-        let cos_theta_in = normal_adjustment * direction_in.dot(n);
+        let local_normal = -normal_adjustment * hit.shading_normal;
+        let cos_theta_in = normal_adjustment * direction_in.dot(hit.shading_normal);
 
         // Schlick's approximation of dielectric reflectance.
         let r_0 = (1.0 - relative_refractive_index) / (1.0 + relative_refractive_index);
         let r_0 = r_0 * r_0;
         let reflectance = r_0 + (1.0 - r_0) * (1.0 - cos_theta_in) * (1.0 - cos_theta_in) * (1.0 - cos_theta_in) * (1.0 - cos_theta_in) * (1.0 - cos_theta_in);
 
-        // Scatter.
+        // Scatter. Reflection and refraction are chosen with the probabilities with which they occur, so their weights
+        // need not be scaled by the reflectance.
         let sin_theta_in = f32::sqrt(1.0 - cos_theta_in * cos_theta_in);
-        if relative_refractive_index * sin_theta_in > 1.0 || rng.random_bool(reflectance as f64) {
-            Some(Ray::new(r.at(t), r.direction - 2.0 * r.direction.dot(local_normal) * local_normal))
+        let reflected_direction = direction_in - 2.0 * direction_in.dot(local_normal) * local_normal;
+        if relative_refractive_index * sin_theta_in > 1.0 {
+            Some(ScatterRecord::specular(reflected_direction, attenuation, 1.0))
+        } else if rng.random_bool(reflectance as f64) {
+            Some(ScatterRecord::specular(reflected_direction, attenuation, reflectance))
         } else {
             let r_out_direction_perp = relative_refractive_index * (direction_in + cos_theta_in * local_normal);
-            let r_out_direction_parallel = -local_normal * f32::sqrt(1.0 - r_out_direction_perp.norm2());
-            Some(Ray::new(r.at(t), r_out_direction_perp + r_out_direction_parallel))
+            let r_out_direction_parallel = -local_normal * f32::sqrt(f32::max(0.0, 1.0 - r_out_direction_perp.norm2()));
+            let refracted_direction = (r_out_direction_perp + r_out_direction_parallel).normalize();
+            Some(ScatterRecord::specular(refracted_direction, attenuation, 1.0 - reflectance))
         }
     }
 }
\ No newline at end of file
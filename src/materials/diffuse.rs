@@ -1,30 +1,54 @@
 use crate::{
-    materials::Material,
+    intersectable::HitRecord,
+    materials::{Lobe, Material, ScatterRecord},
     random::sample_unit_hemisphere_uniform,
     ray::Ray,
+    textures::{SolidColor, Texture},
     vector4::Vector4,
 };
 use rand::Rng;
+use std::{
+    f32::consts::PI,
+    sync::Arc
+};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone)]
 pub struct Diffuse {
-    attenuation: Vector4,
+    attenuation: Arc<dyn Texture + Send + Sync>,
 }
 
 impl Diffuse {
     pub fn new(
         attenuation: Vector4,
+    ) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(attenuation)))
+    }
+
+    /// Constructs the material with an attenuation varying over the surface.
+    pub fn from_texture(
+        attenuation: Arc<dyn Texture + Send + Sync>,
     ) -> Self {
         Self { attenuation }
     }
 }
 
 impl<R: Rng + ?Sized> Material<R> for Diffuse {
-    fn attenuation(&self, _rng: &mut R, _r: Ray, _t: f32, _n: Vector4, _is_inside: bool) -> Vector4 {
-        self.attenuation
+    fn scatter(&self, rng: &mut R, _r: Ray, hit: &HitRecord<R>) -> Option<ScatterRecord> {
+        let direction = sample_unit_hemisphere_uniform(rng, hit.facing_normal());
+        Some(ScatterRecord::new(direction, self.attenuation.value(hit.u, hit.v, hit.p), 1.0 / (2.0 * PI), Lobe::Diffuse))
+    }
+
+    fn eval(&self, _r: Ray, hit: &HitRecord<R>, direction: Vector4) -> Vector4 {
+        // Rays are scattered uniformly over the hemisphere without being weighted by the cosine, so the scattering function
+        // including the cosine is constant.
+        if direction.dot(hit.facing_normal()) > 0.0 {
+            self.attenuation.value(hit.u, hit.v, hit.p) / (2.0 * PI)
+        } else {
+            Vector4::new(0.0, 0.0, 0.0, 0.0)
+        }
     }
 
-    fn scatter(&self, rng: &mut R, r: Ray, t: f32, n: Vector4, _is_inside: bool) -> Option<Ray> {
-        Some(Ray::new(r.at(t), sample_unit_hemisphere_uniform(rng, n)))
+    fn pdf(&self, _r: Ray, hit: &HitRecord<R>, direction: Vector4) -> f32 {
+        if direction.dot(hit.facing_normal()) > 0.0 { 1.0 / (2.0 * PI) } else { 0.0 }
     }
 }
\ No newline at end of file
@@ -0,0 +1,38 @@
+use crate::{
+    intersectable::HitRecord,
+    materials::{Material, ScatterRecord},
+    ray::Ray,
+    vector4::Vector4
+};
+use rand::Rng;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiffuseLight {
+    radiance: Vector4,
+    two_sided: bool,    // Whether the back faces of the surface emit light as well.
+}
+
+impl DiffuseLight {
+    pub fn new(radiance: Vector4) -> Self {
+        Self { radiance, two_sided: false }
+    }
+
+    pub fn two_sided(radiance: Vector4) -> Self {
+        Self { radiance, two_sided: true }
+    }
+}
+
+impl<R: Rng + ?Sized> Material<R> for DiffuseLight {
+    fn scatter(&self, _rng: &mut R, _r: Ray, _hit: &HitRecord<R>) -> Option<ScatterRecord> {
+        // Absorb all incoming rays.
+        None
+    }
+
+    fn emitted(&self, _r: Ray, hit: &HitRecord<R>) -> Vector4 {
+        if hit.front_face || self.two_sided {
+            self.radiance
+        } else {
+            Vector4::new(0.0, 0.0, 0.0, 0.0)
+        }
+    }
+}
\ No newline at end of file
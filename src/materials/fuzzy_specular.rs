@@ -1,14 +1,20 @@
 use crate::{
-    materials::Material,
+    intersectable::HitRecord,
+    materials::{Lobe, Material, ScatterRecord},
     random::sample_unit_sphere_uniform,
     ray::Ray,
+    textures::{SolidColor, Texture},
     vector4::Vector4,
 };
 use rand::Rng;
+use std::{
+    f32::consts::PI,
+    sync::Arc
+};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone)]
 pub struct FuzzySpecular {
-    attenuation: Vector4,
+    attenuation: Arc<dyn Texture + Send + Sync>,
     fuzzing_radius: f32,
     max_fuzzing_iterations: usize,  // The maximum number of attempts to find the fuzzed reflection direction.
 }
@@ -18,6 +24,15 @@ impl FuzzySpecular {
         attenuation: Vector4,
         fuzzing_radius: f32,
         max_fuzzing_iterations: usize,
+    ) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(attenuation)), fuzzing_radius, max_fuzzing_iterations)
+    }
+
+    /// Constructs the material with an attenuation varying over the surface.
+    pub fn from_texture(
+        attenuation: Arc<dyn Texture + Send + Sync>,
+        fuzzing_radius: f32,
+        max_fuzzing_iterations: usize,
     ) -> Self {
         Self {
             attenuation,
@@ -25,23 +40,73 @@ impl FuzzySpecular {
             max_fuzzing_iterations,
         }
     }
-}
 
-impl<R: Rng + ?Sized> Material<R> for FuzzySpecular {
-    fn attenuation(&self, _rng: &mut R, _r: Ray, _t: f32, _n: Vector4, _is_inside: bool) -> Vector4 {
-        self.attenuation
+    /// Returns the probability density, with respect to solid angle, of `scatter` choosing the unit vector `direction`,
+    /// where `specular` is the unit specular reflection direction and `normal` is the surface normal.
+    fn scatter_pdf(&self, specular: Vector4, normal: Vector4, direction: Vector4) -> f32 {
+        if direction.dot(normal) <= 0.0 {
+            return 0.0;
+        }
+        // Each attempt picks a point uniformly on the sphere of radius f about the specular direction. The ray t * direction
+        // meets that sphere where t^2 - 2bt + 1 - f^2 = 0, and the area density 1 / (4pi f^2) of a point at distance t
+        // converts to the solid angle density t^2 / (4pi f sqrt(D)), since the cosine at the point is sqrt(D) / f.
+        let f = self.fuzzing_radius;
+        let b = direction.dot(specular);
+        let d = b * b - 1.0 + f * f;
+        if d <= 0.0 {
+            return 0.0;
+        }
+        let sqrt_d = d.sqrt();
+        let density = [b - sqrt_d, b + sqrt_d].iter().filter(|&&t| t > 0.0).map(|t| t * t).sum::<f32>() / (4.0 * PI * f * sqrt_d);
+
+        // An attempt is accepted if the point lies above the surface. The cosine between a uniformly distributed unit vector
+        // and the normal is uniform in [-1, 1], so this happens with probability (1 + specular * normal / f) / 2.
+        let acceptance = f32::min(1.0, (1.0 + specular.dot(normal) / f) / 2.0);
+        if acceptance <= 0.0 {
+            return 0.0;
+        }
+        // Summing over the attempts, sum_k (1 - a)^k a = 1 - (1 - a)^n of the density of accepted points is kept.
+        density * (1.0 - (1.0 - acceptance).powi(self.max_fuzzing_iterations as i32)) / acceptance
     }
+}
 
-    fn scatter(&self, rng: &mut R, r: Ray, t: f32, n: Vector4, _is_inside: bool) -> Option<Ray> {
+impl<R: Rng + ?Sized> Material<R> for FuzzySpecular {
+    fn scatter(&self, rng: &mut R, r: Ray, hit: &HitRecord<R>) -> Option<ScatterRecord> {
         // Rejection sampling for the win!
-        let direction_specular_normalized = (r.direction - 2.0 * r.direction.dot(n) * n).normalize();
+        let normal = hit.facing_normal();
+        let direction_specular_normalized = reflect(r.direction, normal).normalize();
         let mut direction: Vector4;
         for _ in 0..self.max_fuzzing_iterations {
             direction = direction_specular_normalized + self.fuzzing_radius * sample_unit_sphere_uniform(rng);
-            if direction.dot(n) > 0.0 {
-                return Some(Ray::new(r.at(t), direction));
+            if direction.dot(normal) > 0.0 {
+                let direction = direction.normalize();
+                // Without fuzzing, rays are only scattered into the specular direction.
+                if self.fuzzing_radius <= 0.0 {
+                    return Some(ScatterRecord::specular(direction, self.attenuation.value(hit.u, hit.v, hit.p), 1.0));
+                }
+                let pdf = self.scatter_pdf(direction_specular_normalized, normal, direction);
+                return Some(ScatterRecord::new(direction, self.attenuation.value(hit.u, hit.v, hit.p), pdf, Lobe::Glossy));
             }
         }
         None
     }
+
+    fn eval(&self, r: Ray, hit: &HitRecord<R>, direction: Vector4) -> Vector4 {
+        // Every scattered ray is attenuated by the same amount, so the scattering function is proportional to the density.
+        self.attenuation.value(hit.u, hit.v, hit.p) * Material::<R>::pdf(self, r, hit, direction)
+    }
+
+    fn pdf(&self, r: Ray, hit: &HitRecord<R>, direction: Vector4) -> f32 {
+        if self.fuzzing_radius <= 0.0 {
+            return 0.0;
+        }
+        let normal = hit.facing_normal();
+        let specular = reflect(r.direction, normal).normalize();
+        self.scatter_pdf(specular, normal, direction)
+    }
+}
+
+
+fn reflect(direction: Vector4, normal: Vector4) -> Vector4 {
+    direction - 2.0 * direction.dot(normal) * normal
 }
\ No newline at end of file
@@ -0,0 +1,76 @@
+use crate::{
+    intersectable::HitRecord,
+    materials::{Lobe, Material, ScatterRecord},
+    ray::Ray,
+    textures::{SolidColor, Texture},
+    vector4::Vector4,
+};
+use rand::Rng;
+use std::{
+    f32::consts::PI,
+    sync::Arc
+};
+
+/// Henyey-Greenstein phase function, which scatters light forwards for positive asymmetry parameters `g` and backwards for
+/// negative ones, with `g` the mean cosine of the angle between the incoming and scattered directions. Like `Isotropic`,
+/// which it equals for `g = 0`, it is meant for participating media.
+#[derive(Clone)]
+pub struct HenyeyGreenstein {
+    albedo: Arc<dyn Texture + Send + Sync>,
+    g: f32
+}
+
+impl HenyeyGreenstein {
+    /// Panics if `g` does not lie strictly between -1 and 1.
+    pub fn new(
+        albedo: Vector4,
+        g: f32
+    ) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(albedo)), g)
+    }
+
+    /// Constructs the phase function with an albedo varying through space.
+    pub fn from_texture(
+        albedo: Arc<dyn Texture + Send + Sync>,
+        g: f32
+    ) -> Self {
+        assert!(-1.0 < g && g < 1.0, "the asymmetry parameter must lie strictly between -1 and 1");
+        Self { albedo, g }
+    }
+
+    /// Returns the density of scattering by an angle with cosine `cos_theta` from the direction of propagation.
+    fn phase(&self, cos_theta: f32) -> f32 {
+        let g = self.g;
+        let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+        (1.0 - g * g) / (4.0 * PI * denominator * denominator.sqrt())
+    }
+}
+
+impl<R: Rng + ?Sized> Material<R> for HenyeyGreenstein {
+    fn scatter(&self, rng: &mut R, r: Ray, hit: &HitRecord<R>) -> Option<ScatterRecord> {
+        // Invert the cumulative distribution of the cosine, which is uniform for (almost) isotropic scattering.
+        let (s, phi): (f32, f32) = rng.random();
+        let g = self.g;
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * s
+        } else {
+            let k = (1.0 - g * g) / (1.0 - g + 2.0 * g * s);
+            ((1.0 + g * g - k * k) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin_theta = f32::sqrt(f32::max(0.0, 1.0 - cos_theta * cos_theta));
+        let phi = 2.0 * PI * phi;
+        let w = r.direction.normalize();
+        let (u, v) = w.orthonormal_basis();
+        let direction = (sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + cos_theta * w).normalize();
+        let pdf = self.phase(direction.dot(w));
+        Some(ScatterRecord::new(direction, self.albedo.value(hit.u, hit.v, hit.p), pdf, Lobe::Glossy))
+    }
+
+    fn eval(&self, r: Ray, hit: &HitRecord<R>, direction: Vector4) -> Vector4 {
+        self.albedo.value(hit.u, hit.v, hit.p) * self.phase(direction.dot(r.direction.normalize()))
+    }
+
+    fn pdf(&self, r: Ray, _hit: &HitRecord<R>, direction: Vector4) -> f32 {
+        self.phase(direction.dot(r.direction.normalize()))
+    }
+}
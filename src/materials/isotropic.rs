@@ -0,0 +1,50 @@
+use crate::{
+    intersectable::HitRecord,
+    materials::{Lobe, Material, ScatterRecord},
+    random::sample_unit_sphere_uniform,
+    ray::Ray,
+    textures::{SolidColor, Texture},
+    vector4::Vector4,
+};
+use rand::Rng;
+use std::{
+    f32::consts::PI,
+    sync::Arc
+};
+
+/// Isotropic phase function, which scatters light into all directions equally. Meant for participating media such as
+/// `ConstantMedium`, whose scattering points have no surface, so unlike surface materials it has no cosine factor.
+#[derive(Clone)]
+pub struct Isotropic {
+    albedo: Arc<dyn Texture + Send + Sync>,
+}
+
+impl Isotropic {
+    pub fn new(
+        albedo: Vector4,
+    ) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    /// Constructs the phase function with an albedo varying through space.
+    pub fn from_texture(
+        albedo: Arc<dyn Texture + Send + Sync>,
+    ) -> Self {
+        Self { albedo }
+    }
+}
+
+impl<R: Rng + ?Sized> Material<R> for Isotropic {
+    fn scatter(&self, rng: &mut R, _r: Ray, hit: &HitRecord<R>) -> Option<ScatterRecord> {
+        let direction = sample_unit_sphere_uniform(rng);
+        Some(ScatterRecord::new(direction, self.albedo.value(hit.u, hit.v, hit.p), 1.0 / (4.0 * PI), Lobe::Diffuse))
+    }
+
+    fn eval(&self, _r: Ray, hit: &HitRecord<R>, _direction: Vector4) -> Vector4 {
+        self.albedo.value(hit.u, hit.v, hit.p) / (4.0 * PI)
+    }
+
+    fn pdf(&self, _r: Ray, _hit: &HitRecord<R>, _direction: Vector4) -> f32 {
+        1.0 / (4.0 * PI)
+    }
+}
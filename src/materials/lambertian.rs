@@ -1,30 +1,53 @@
 use crate::{
-    materials::Material,
+    intersectable::HitRecord,
+    materials::{Lobe, Material, ScatterRecord},
     random::sample_unit_sphere_uniform,
     ray::Ray,
+    textures::{SolidColor, Texture},
     vector4::Vector4,
 };
 use rand::Rng;
+use std::{
+    f32::consts::PI,
+    sync::Arc
+};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone)]
 pub struct Lambertian {
-    attenuation: Vector4,
+    attenuation: Arc<dyn Texture + Send + Sync>,
 }
 
 impl Lambertian {
     pub fn new(
         attenuation: Vector4,
+    ) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(attenuation)))
+    }
+
+    /// Constructs the material with an attenuation varying over the surface.
+    pub fn from_texture(
+        attenuation: Arc<dyn Texture + Send + Sync>,
     ) -> Self {
         Self { attenuation }
     }
 }
 
 impl<R: Rng + ?Sized> Material<R> for Lambertian {
-    fn attenuation(&self, _rng: &mut R, _r: Ray, _t: f32, _n: Vector4, _is_inside: bool) -> Vector4 {
-        self.attenuation
+    fn scatter(&self, rng: &mut R, r: Ray, hit: &HitRecord<R>) -> Option<ScatterRecord> {
+        // Offsetting the normal by a point on the unit sphere yields cosine-weighted directions.
+        let normal = hit.facing_normal();
+        let direction = sample_unit_sphere_uniform(rng) + normal;
+        // Guard against the sample landing (almost) opposite the normal.
+        let direction = if direction.norm2() < 1e-12 { normal } else { direction.normalize() };
+        let pdf = Material::<R>::pdf(self, r, hit, direction);
+        Some(ScatterRecord::new(direction, self.attenuation.value(hit.u, hit.v, hit.p), pdf, Lobe::Diffuse))
+    }
+
+    fn eval(&self, _r: Ray, hit: &HitRecord<R>, direction: Vector4) -> Vector4 {
+        self.attenuation.value(hit.u, hit.v, hit.p) * f32::max(direction.dot(hit.facing_normal()), 0.0) / PI
     }
 
-    fn scatter(&self, rng: &mut R, r: Ray, t: f32, n: Vector4, _is_inside: bool) -> Option<Ray> {
-        Some(Ray::new(r.at(t), sample_unit_sphere_uniform(rng) + n))
+    fn pdf(&self, _r: Ray, hit: &HitRecord<R>, direction: Vector4) -> f32 {
+        f32::max(direction.dot(hit.facing_normal()), 0.0) / PI
     }
 }
\ No newline at end of file
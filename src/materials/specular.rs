@@ -1,27 +1,32 @@
 use crate::{
-    materials::Material,
+    intersectable::HitRecord,
+    materials::{Material, ScatterRecord},
     ray::Ray,
+    textures::{SolidColor, Texture},
     vector4::Vector4
 };
 use rand::Rng;
+use std::sync::Arc;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone)]
 pub struct Specular {
-    attenuation: Vector4
+    attenuation: Arc<dyn Texture + Send + Sync>
 }
 
 impl Specular {
     pub fn new(attenuation: Vector4) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(attenuation)))
+    }
+
+    /// Constructs the material with an attenuation varying over the surface.
+    pub fn from_texture(attenuation: Arc<dyn Texture + Send + Sync>) -> Self {
         Self { attenuation }
     }
 }
 
 impl<R: Rng + ?Sized> Material<R> for Specular {
-    fn attenuation(&self, _rng: &mut R, _r: Ray, _t: f32, _n: Vector4, _is_inside: bool) -> Vector4 {
-        self.attenuation
-    }
-
-    fn scatter(&self, _rng: &mut R, r: Ray, t: f32, n: Vector4, _is_inside: bool) -> Option<Ray> {
-        Some(Ray::new(r.at(t), r.direction - 2.0 * r.direction.dot(n) * n))
+    fn scatter(&self, _rng: &mut R, r: Ray, hit: &HitRecord<R>) -> Option<ScatterRecord> {
+        let direction = (r.direction - 2.0 * r.direction.dot(hit.shading_normal) * hit.shading_normal).normalize();
+        Some(ScatterRecord::specular(direction, self.attenuation.value(hit.u, hit.v, hit.p), 1.0))
     }
 }
\ No newline at end of file
@@ -0,0 +1,327 @@
+use crate::vector4::Vector4;
+use std::{
+    arch::x86_64::*,
+    fmt,
+    ops
+};
+
+/// 4x4 matrix stored as four column vectors, so that products are computed with SIMD arithmetic on whole columns.
+///
+/// Matrices act on column vectors, so `a * b` is the transform applying `b` first and `a` second. As points are stored
+/// with `w = 0` throughout the crate, `transform_point` and `transform_vector` treat `w` as `1` and `0` respectively.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Matrix4 {
+    columns: [Vector4; 4]
+}
+
+impl Matrix4 {
+    /// Constructs the matrix with the rows `rows`, which reads like the matrix when written out.
+    pub fn from_rows(rows: [[f32; 4]; 4]) -> Self {
+        Self::from_columns([0, 1, 2, 3].map(|j| Vector4::new(rows[0][j], rows[1][j], rows[2][j], rows[3][j])))
+    }
+
+    pub fn from_columns(columns: [Vector4; 4]) -> Self {
+        Self { columns }
+    }
+
+    pub fn identity() -> Self {
+        Self::scaling(Vector4::new(1.0, 1.0, 1.0, 0.0))
+    }
+
+    /// Returns the transform moving points by `offset`.
+    pub fn translation(offset: Vector4) -> Self {
+        let mut m = Self::identity();
+        m.columns[3] = Vector4::new(offset.x(), offset.y(), offset.z(), 1.0);
+        m
+    }
+
+    /// Returns the transform scaling points by `factors.x()`, `factors.y()` and `factors.z()` along the respective axes.
+    pub fn scaling(factors: Vector4) -> Self {
+        Self::from_columns([
+            Vector4::new(factors.x(), 0.0, 0.0, 0.0),
+            Vector4::new(0.0, factors.y(), 0.0, 0.0),
+            Vector4::new(0.0, 0.0, factors.z(), 0.0),
+            Vector4::new(0.0, 0.0, 0.0, 1.0)
+        ])
+    }
+
+    /// Returns the transform rotating points counterclockwise by `angle` radians about `axis` through the origin, as seen
+    /// with `axis` pointing towards the viewer. `axis` need not be of unit length.
+    pub fn rotation(axis: Vector4, angle: f32) -> Self {
+        // Rodrigues' rotation formula.
+        let a = axis.normalize();
+        let (x, y, z) = (a.x(), a.y(), a.z());
+        let (s, c) = angle.sin_cos();
+        let t = 1.0 - c;
+        Self::from_rows([
+            [t * x * x + c, t * x * y - s * z, t * x * z + s * y, 0.0],
+            [t * x * y + s * z, t * y * y + c, t * y * z - s * x, 0.0],
+            [t * x * z - s * y, t * y * z + s * x, t * z * z + c, 0.0],
+            [0.0, 0.0, 0.0, 1.0]
+        ])
+    }
+
+    /// Returns the rigid transform moving the origin to `from` and turning the `z` axis towards `at`, with the `y` axis
+    /// turned as far towards `up` as possible. `up` must not be parallel to `at - from`.
+    pub fn look_at(from: Vector4, at: Vector4, up: Vector4) -> Self {
+        let w = (at - from).normalize();
+        let u = up.cross(w).normalize();
+        let v = w.cross(u);
+        Self::from_columns([u, v, w, Vector4::new(from.x(), from.y(), from.z(), 1.0)])
+    }
+
+    pub fn column(&self, j: usize) -> Vector4 {
+        self.columns[j]
+    }
+
+    pub fn row(&self, i: usize) -> Vector4 {
+        Vector4::new(self.columns[0][i], self.columns[1][i], self.columns[2][i], self.columns[3][i])
+    }
+
+    #[allow(unreachable_code)]
+    pub fn transpose(&self) -> Self {
+        #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse"))]
+        unsafe { return self.simd_transpose() }
+        Self::from_columns([0, 1, 2, 3].map(|i| self.row(i)))
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let (s, c) = self.sub_determinants();
+        (s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]) as f32
+    }
+
+    /// Returns the 2x2 determinants of the upper two rows and of the lower two rows, from which the determinant and the
+    /// cofactors are assembled (Laplace expansion along the first two rows). They are computed in double precision to
+    /// limit the cancellation in their differences.
+    fn sub_determinants(&self) -> ([f64; 6], [f64; 6]) {
+        let a = |i: usize, j: usize| self.columns[j][i] as f64;
+        let s = [
+            a(0, 0) * a(1, 1) - a(1, 0) * a(0, 1),
+            a(0, 0) * a(1, 2) - a(1, 0) * a(0, 2),
+            a(0, 0) * a(1, 3) - a(1, 0) * a(0, 3),
+            a(0, 1) * a(1, 2) - a(1, 1) * a(0, 2),
+            a(0, 1) * a(1, 3) - a(1, 1) * a(0, 3),
+            a(0, 2) * a(1, 3) - a(1, 2) * a(0, 3)
+        ];
+        let c = [
+            a(2, 0) * a(3, 1) - a(3, 0) * a(2, 1),
+            a(2, 0) * a(3, 2) - a(3, 0) * a(2, 2),
+            a(2, 0) * a(3, 3) - a(3, 0) * a(2, 3),
+            a(2, 1) * a(3, 2) - a(3, 1) * a(2, 2),
+            a(2, 1) * a(3, 3) - a(3, 1) * a(2, 3),
+            a(2, 2) * a(3, 3) - a(3, 2) * a(2, 3)
+        ];
+        (s, c)
+    }
+
+    /// Returns the inverse of the matrix, or `None` if it is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let a = |i: usize, j: usize| self.columns[j][i] as f64;
+        let (s, c) = self.sub_determinants();
+        let determinant = s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0];
+        if determinant == 0.0 || !determinant.is_finite() {
+            return None;
+        }
+        let rows = [
+            [
+                a(1, 1) * c[5] - a(1, 2) * c[4] + a(1, 3) * c[3],
+                -a(0, 1) * c[5] + a(0, 2) * c[4] - a(0, 3) * c[3],
+                a(3, 1) * s[5] - a(3, 2) * s[4] + a(3, 3) * s[3],
+                -a(2, 1) * s[5] + a(2, 2) * s[4] - a(2, 3) * s[3]
+            ],
+            [
+                -a(1, 0) * c[5] + a(1, 2) * c[2] - a(1, 3) * c[1],
+                a(0, 0) * c[5] - a(0, 2) * c[2] + a(0, 3) * c[1],
+                -a(3, 0) * s[5] + a(3, 2) * s[2] - a(3, 3) * s[1],
+                a(2, 0) * s[5] - a(2, 2) * s[2] + a(2, 3) * s[1]
+            ],
+            [
+                a(1, 0) * c[4] - a(1, 1) * c[2] + a(1, 3) * c[0],
+                -a(0, 0) * c[4] + a(0, 1) * c[2] - a(0, 3) * c[0],
+                a(3, 0) * s[4] - a(3, 1) * s[2] + a(3, 3) * s[0],
+                -a(2, 0) * s[4] + a(2, 1) * s[2] - a(2, 3) * s[0]
+            ],
+            [
+                -a(1, 0) * c[3] + a(1, 1) * c[1] - a(1, 2) * c[0],
+                a(0, 0) * c[3] - a(0, 1) * c[1] + a(0, 2) * c[0],
+                -a(3, 0) * s[3] + a(3, 1) * s[1] - a(3, 2) * s[0],
+                a(2, 0) * s[3] - a(2, 1) * s[1] + a(2, 2) * s[0]
+            ]
+        ];
+        Some(Self::from_rows(rows.map(|row| row.map(|x| (x / determinant) as f32))))
+    }
+
+    /// Returns the image of the point `p` under the affine transform, treating the `w` component of `p` as `1`. The
+    /// returned point has `w = 0`.
+    pub fn transform_point(&self, p: Vector4) -> Vector4 {
+        let q = *self * Vector4::new(p.x(), p.y(), p.z(), 1.0);
+        Vector4::new(q.x(), q.y(), q.z(), 0.0)
+    }
+
+    /// Returns the image of the vector `v` under the linear part of the transform, ignoring its translation. The `w`
+    /// component of `v` is treated as `0`, and is `0` in the returned vector.
+    pub fn transform_vector(&self, v: Vector4) -> Vector4 {
+        let q = *self * Vector4::new(v.x(), v.y(), v.z(), 0.0);
+        Vector4::new(q.x(), q.y(), q.z(), 0.0)
+    }
+}
+
+// Matrix arithmetic using x86/x86_64 SSE intrinsics.
+#[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse"))]
+impl Matrix4 {
+    #[target_feature(enable = "sse")]
+    fn simd_mul_vector(&self, v: Vector4) -> Vector4 {
+        // Sum of the columns weighted by the components of v, each broadcast to a whole register.
+        let v = v.simd();
+        let x = _mm_shuffle_ps::<0b00000000>(v, v);
+        let y = _mm_shuffle_ps::<0b01010101>(v, v);
+        let z = _mm_shuffle_ps::<0b10101010>(v, v);
+        let w = _mm_shuffle_ps::<0b11111111>(v, v);
+        let xy = _mm_add_ps(_mm_mul_ps(self.columns[0].simd(), x), _mm_mul_ps(self.columns[1].simd(), y));
+        let zw = _mm_add_ps(_mm_mul_ps(self.columns[2].simd(), z), _mm_mul_ps(self.columns[3].simd(), w));
+        Vector4::from_simd(_mm_add_ps(xy, zw))
+    }
+
+    #[target_feature(enable = "sse")]
+    fn simd_transpose(&self) -> Self {
+        let [c0, c1, c2, c3] = self.columns.map(|c| c.simd());
+        // Interleave the upper and lower halves of pairs of columns, then combine the halves into rows.
+        let (t0, t1) = (_mm_unpacklo_ps(c0, c1), _mm_unpackhi_ps(c0, c1));
+        let (t2, t3) = (_mm_unpacklo_ps(c2, c3), _mm_unpackhi_ps(c2, c3));
+        Self::from_columns([
+            Vector4::from_simd(_mm_movelh_ps(t0, t2)),
+            Vector4::from_simd(_mm_movehl_ps(t2, t0)),
+            Vector4::from_simd(_mm_movelh_ps(t1, t3)),
+            Vector4::from_simd(_mm_movehl_ps(t3, t1))
+        ])
+    }
+}
+
+impl fmt::Debug for Matrix4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries((0..4).map(|i| self.row(i))).finish()
+    }
+}
+
+impl ops::Index<(usize, usize)> for Matrix4 {
+    type Output = f32;
+
+    /// Returns the element in the row and column `(i, j)`.
+    fn index(&self, (i, j): (usize, usize)) -> &Self::Output {
+        &self.columns[j][i]
+    }
+}
+
+impl ops::Mul<Vector4> for Matrix4 {
+    type Output = Vector4;
+
+    #[allow(unreachable_code)]
+    fn mul(self, rhs: Vector4) -> Self::Output {
+        #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse"))]
+        unsafe { return self.simd_mul_vector(rhs) }
+        self.columns[0] * rhs.x() + self.columns[1] * rhs.y() + self.columns[2] * rhs.z() + self.columns[3] * rhs.w()
+    }
+}
+
+impl ops::Mul for Matrix4 {
+    type Output = Self;
+
+    /// Composes the transforms, so that `rhs` is applied first.
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::from_columns(rhs.columns.map(|c| self * c))
+    }
+}
+
+impl ops::MulAssign for Matrix4 {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn assert_close(a: Matrix4, b: Matrix4) {
+        for i in 0..4 {
+            assert!((a.row(i) - b.row(i)).norm() < 1e-5, "{:?} != {:?}", a, b);
+        }
+    }
+
+    fn matrix() -> Matrix4 {
+        Matrix4::from_rows([
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 10.0, 11.0, 12.0],
+            [13.0, 14.0, 15.0, 16.0]
+        ])
+    }
+
+    #[test]
+    fn test_rows_and_columns() {
+        let m = matrix();
+        assert_eq!(m.row(1), Vector4::new(5.0, 6.0, 7.0, 8.0));
+        assert_eq!(m.column(1), Vector4::new(2.0, 6.0, 10.0, 14.0));
+        assert_eq!(m[(2, 3)], 12.0);
+        assert_eq!(m.transpose().row(1), m.column(1));
+        assert_eq!(m.transpose().transpose(), m);
+    }
+
+    #[test]
+    fn test_mul() {
+        let m = matrix();
+        assert_eq!(m * Vector4::new(1.0, 0.0, -1.0, 2.0), Vector4::new(6.0, 14.0, 22.0, 30.0));
+        assert_eq!(Matrix4::identity() * m, m);
+        assert_eq!(m * Matrix4::identity(), m);
+        let product = m * m.transpose();
+        for (i, j) in (0..4).flat_map(|i| (0..4).map(move |j| (i, j))) {
+            assert_eq!(product[(i, j)], m.row(i).dot(m.row(j)));
+        }
+        let mut n = m;
+        n *= Matrix4::scaling(Vector4::new(2.0, 2.0, 2.0, 0.0));
+        assert_eq!(n.column(0), 2.0 * m.column(0));
+        assert_eq!(n.column(3), m.column(3));
+    }
+
+    #[test]
+    fn test_inverse() {
+        assert!(matrix().inverse().is_none());
+        assert!(Matrix4::scaling(Vector4::new(1.0, 0.0, 1.0, 0.0)).inverse().is_none());
+        let m = Matrix4::translation(Vector4::new(10.0, -20.0, 3.0, 0.0))
+            * Matrix4::rotation(Vector4::new(1.0, 2.0, 3.0, 0.0), 0.7)
+            * Matrix4::scaling(Vector4::new(0.5, 2.0, -3.0, 0.0));
+        let inverse = m.inverse().unwrap();
+        assert_close(m * inverse, Matrix4::identity());
+        assert_close(inverse * m, Matrix4::identity());
+        assert!((m.determinant() - 0.5 * 2.0 * -3.0).abs() < 1e-5);
+        assert!((matrix().determinant()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_transforms() {
+        let p = Vector4::new(1.0, 2.0, 3.0, 0.0);
+        let t = Matrix4::translation(Vector4::new(1.0, 1.0, 1.0, 0.0));
+        assert_eq!(t.transform_point(p), Vector4::new(2.0, 3.0, 4.0, 0.0));
+        assert_eq!(t.transform_vector(p), p);
+        let s = Matrix4::scaling(Vector4::new(2.0, -1.0, 0.5, 0.0));
+        assert_eq!(s.transform_point(p), Vector4::new(2.0, -2.0, 1.5, 0.0));
+
+        // Rotations are counterclockwise about their axis, and translations apply after rotations in t * r.
+        let r = Matrix4::rotation(Vector4::new(0.0, 0.0, 2.0, 0.0), PI / 2.0);
+        assert!((r.transform_point(Vector4::new(1.0, 0.0, 5.0, 0.0)) - Vector4::new(0.0, 1.0, 5.0, 0.0)).norm() < 1e-6);
+        assert!(((t * r).transform_point(Vector4::new(1.0, 0.0, 0.0, 0.0)) - Vector4::new(1.0, 2.0, 1.0, 0.0)).norm() < 1e-6);
+        assert!(((r * t).transform_point(Vector4::new(1.0, 0.0, 0.0, 0.0)) - Vector4::new(-1.0, 2.0, 1.0, 0.0)).norm() < 1e-6);
+        assert_close(r.transpose() * r, Matrix4::identity());
+    }
+
+    #[test]
+    fn test_look_at() {
+        let from = Vector4::new(1.0, 2.0, 3.0, 0.0);
+        let m = Matrix4::look_at(from, Vector4::new(1.0, 5.0, 3.0, 0.0), Vector4::new(0.0, 0.0, 1.0, 0.0));
+        assert_eq!(m.transform_point(Vector4::new(0.0, 0.0, 0.0, 0.0)), from);
+        assert!((m.transform_vector(Vector4::new(0.0, 0.0, 1.0, 0.0)) - Vector4::new(0.0, 1.0, 0.0, 0.0)).norm() < 1e-6);
+        assert!((m.transform_vector(Vector4::new(0.0, 1.0, 0.0, 0.0)) - Vector4::new(0.0, 0.0, 1.0, 0.0)).norm() < 1e-6);
+        assert!((m.determinant() - 1.0).abs() < 1e-6);
+    }
+}
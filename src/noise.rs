@@ -0,0 +1,142 @@
+use crate::{random::sample_unit_sphere_uniform, vector4::Vector4};
+use rand::{Rng, seq::SliceRandom};
+
+const POINT_COUNT: usize = 256;
+
+/// Gradient noise (Perlin, "Improving Noise", SIGGRAPH 2002) over `R^3`, with random unit gradients at the integer
+/// lattice points. The noise is fully determined by the random number generator used to construct it, so seeding the
+/// generator makes it reproducible.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Perlin {
+    gradients: Vec<Vector4>,
+    permutation_x: Vec<usize>,
+    permutation_y: Vec<usize>,
+    permutation_z: Vec<usize>
+}
+
+impl Perlin {
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let gradients = (0..POINT_COUNT).map(|_| sample_unit_sphere_uniform(rng)).collect();
+        let mut permutation = || {
+            let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+            p.shuffle(rng);
+            p
+        };
+        let permutation_x = permutation();
+        let permutation_y = permutation();
+        let permutation_z = permutation();
+        Self { gradients, permutation_x, permutation_y, permutation_z }
+    }
+
+    /// Returns the noise at `p`, which lies in `[-1, 1]` and vanishes at the integer lattice points.
+    pub fn noise(&self, p: Vector4) -> f32 {
+        let floor = [p.x().floor(), p.y().floor(), p.z().floor()];
+        let cell = floor.map(|f| f as i64);
+        let offset = [p.x() - floor[0], p.y() - floor[1], p.z() - floor[2]];
+        // Quintic fade curve, so that the noise has continuous second derivatives.
+        let fade = offset.map(|t| t * t * t * (t * (t * 6.0 - 15.0) + 10.0));
+
+        let mut acc = 0.0;
+        for corner in 0..8 {
+            let d = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let hash = self.permutation_x[(cell[0] + d[0] as i64).rem_euclid(POINT_COUNT as i64) as usize]
+                ^ self.permutation_y[(cell[1] + d[1] as i64).rem_euclid(POINT_COUNT as i64) as usize]
+                ^ self.permutation_z[(cell[2] + d[2] as i64).rem_euclid(POINT_COUNT as i64) as usize];
+            let to_p = Vector4::new(offset[0] - d[0] as f32, offset[1] - d[1] as f32, offset[2] - d[2] as f32, 0.0);
+            let weight = (0..3)
+                .map(|axis| if d[axis] == 1 { fade[axis] } else { 1.0 - fade[axis] })
+                .product::<f32>();
+            acc += weight * self.gradients[hash].dot(to_p);
+        }
+        acc.clamp(-1.0, 1.0)
+    }
+
+    /// Returns the fractional Brownian motion at `p`, i.e. the sum of `octaves` octaves of noise, where each octave
+    /// scales the frequency of the previous one by `lacunarity` and its amplitude by `gain`.
+    pub fn fbm(&self, p: Vector4, octaves: usize, lacunarity: f32, gain: f32) -> f32 {
+        let mut acc = 0.0;
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        for _ in 0..octaves {
+            acc += amplitude * self.noise(frequency * p);
+            frequency *= lacunarity;
+            amplitude *= gain;
+        }
+        acc
+    }
+
+    /// Returns the turbulence at `p`, i.e. the sum of the magnitudes of `octaves` octaves of noise, each with twice
+    /// the frequency and half the amplitude of the previous one.
+    pub fn turbulence(&self, p: Vector4, octaves: usize) -> f32 {
+        let mut acc = 0.0;
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        for _ in 0..octaves {
+            acc += amplitude * self.noise(frequency * p).abs();
+            frequency *= 2.0;
+            amplitude *= 0.5;
+        }
+        acc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64Mcg;
+
+    fn random_point<R: Rng + ?Sized>(rng: &mut R) -> Vector4 {
+        Vector4::new(rng.random_range(-50.0..50.0), rng.random_range(-50.0..50.0), rng.random_range(-50.0..50.0), 0.0)
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let a = Perlin::new(&mut Pcg64Mcg::seed_from_u64(42));
+        let b = Perlin::new(&mut Pcg64Mcg::seed_from_u64(42));
+        let c = Perlin::new(&mut Pcg64Mcg::seed_from_u64(43));
+        assert_eq!(a, b);
+
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        let mut differences = 0;
+        for _ in 0..1000 {
+            let p = random_point(&mut rng);
+            assert_eq!(a.noise(p), b.noise(p));
+            assert_eq!(a.fbm(p, 6, 2.0, 0.5), b.fbm(p, 6, 2.0, 0.5));
+            assert_eq!(a.turbulence(p, 7), b.turbulence(p, 7));
+            if a.noise(p) != c.noise(p) {
+                differences += 1;
+            }
+        }
+        assert!(differences > 900);
+    }
+
+    #[test]
+    fn test_noise() {
+        let perlin = Perlin::new(&mut Pcg64Mcg::new(0xcafef00dd15ea5e5));
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        let mut acc = 0.0;
+        const SAMPLE_COUNT: usize = 100000;
+        for _ in 0..SAMPLE_COUNT {
+            let p = random_point(&mut rng);
+            let n = perlin.noise(p);
+            assert!((-1.0..=1.0).contains(&n));
+            acc += n;
+
+            // Continuity.
+            let q = p + Vector4::new(1e-3, -1e-3, 1e-3, 0.0);
+            assert!((perlin.noise(q) - n).abs() < 1e-2);
+
+            // Turbulence is non-negative and bounded by the sum of the amplitudes.
+            let t = perlin.turbulence(p, 7);
+            assert!((0.0..2.0).contains(&t));
+        }
+        // The noise has zero mean.
+        assert!((acc / SAMPLE_COUNT as f32).abs() < 0.01);
+
+        // The noise vanishes at lattice points, including negative ones.
+        for p in [Vector4::new(0.0, 0.0, 0.0, 0.0), Vector4::new(3.0, -7.0, 300.0, 0.0), Vector4::new(-1.0, -1.0, -1.0, 0.0)] {
+            assert_eq!(perlin.noise(p), 0.0);
+        }
+    }
+}
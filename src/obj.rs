@@ -0,0 +1,577 @@
+use crate::{
+    materials::{
+        Material,
+        dielectric::Dielectric,
+        diffuse_light::DiffuseLight,
+        fuzzy_specular::FuzzySpecular,
+        lambertian::Lambertian
+    },
+    renderable_list::RenderableList,
+    surfaces::triangle_mesh::TriangleMesh,
+    vector4::Vector4
+};
+use rand::Rng;
+use std::{
+    collections::HashMap,
+    error,
+    fmt,
+    fs,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc
+};
+
+// The maximum number of attempts to find the fuzzed reflection direction for specular MTL materials.
+const MAX_FUZZING_ITERATIONS: usize = 4;
+
+/// Error produced when loading an OBJ or MTL file, pointing to the offending file and line.
+/// `line` is `0` for errors that do not concern a specific line, e.g. I/O errors.
+#[derive(Debug)]
+pub struct ObjError {
+    pub path: PathBuf,
+    pub line: usize,
+    pub kind: ObjErrorKind
+}
+
+#[derive(Debug)]
+pub enum ObjErrorKind {
+    Io(io::Error),
+    MissingValue(&'static str),
+    InvalidNumber(String),
+    InvalidIndex(String),
+    IndexOutOfRange(i64),
+    TooFewVertices(usize),
+    UndefinedMaterial(String),
+    NoCurrentMaterial
+}
+
+impl fmt::Display for ObjErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::MissingValue(what) => write!(f, "missing {}", what),
+            Self::InvalidNumber(s) => write!(f, "invalid number '{}'", s),
+            Self::InvalidIndex(s) => write!(f, "invalid vertex reference '{}'", s),
+            Self::IndexOutOfRange(i) => write!(f, "index {} is out of range", i),
+            Self::TooFewVertices(n) => write!(f, "face has {} vertices, at least 3 are required", n),
+            Self::UndefinedMaterial(name) => write!(f, "undefined material '{}'", name),
+            Self::NoCurrentMaterial => write!(f, "material statement before any 'newmtl'")
+        }
+    }
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.path.display(), self.kind)
+        } else {
+            write!(f, "{}:{}: {}", self.path.display(), self.line, self.kind)
+        }
+    }
+}
+
+impl error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match &self.kind {
+            ObjErrorKind::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+/// A mesh loaded from an OBJ file, containing the faces of one group that share a material.
+pub struct ObjMesh<R: Rng + ?Sized> {
+    pub group: String,
+    pub material: Option<String>,   // None if the faces were not preceded by a 'usemtl' statement.
+    pub mesh: TriangleMesh<R>
+}
+
+/// Meshes and materials loaded from a Wavefront OBJ file and its MTL material libraries.
+///
+/// Supported OBJ statements are `v`, `vt`, `vn`, `f` (with any number of vertices and negative indices), `g`, `o`,
+/// `usemtl` and `mtllib`, all others are ignored. MTL materials are mapped onto the existing materials using `Kd`,
+/// `Ks`, `Ke`, `Ns`, `Ni`, `d`/`Tr`, `Tf` and `illum`, see `MtlMaterial`.
+pub struct Obj<R: Rng + ?Sized> {
+    pub meshes: Vec<ObjMesh<R>>,
+    pub materials: HashMap<String, Arc<dyn Material<R> + Send + Sync>>
+}
+
+impl<R: Rng + ?Sized> Obj<R> {
+    /// Loads the OBJ file at `path`. Faces that are not preceded by a `usemtl` statement are given `default_material`.
+    pub fn load(path: impl AsRef<Path>, default_material: Arc<dyn Material<R> + Send + Sync>) -> Result<Self, ObjError> {
+        let path = path.as_ref();
+        let source = read_to_string(path)?;
+        let error = |line: usize, kind: ObjErrorKind| ObjError { path: path.to_path_buf(), line, kind };
+
+        let mut positions: Vec<Vector4> = Vec::new();
+        let mut texture_coordinates: Vec<(f32, f32)> = Vec::new();
+        let mut normals: Vec<Vector4> = Vec::new();
+        let mut mtl_materials: HashMap<String, MtlMaterial> = HashMap::new();
+        let mut builders: Vec<MeshBuilder> = Vec::new();
+        let mut builder_indices: HashMap<(String, Option<String>), usize> = HashMap::new();
+        let mut group = String::from("default");
+        let mut material: Option<String> = None;
+
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let mut tokens = strip_comment(line).split_whitespace();
+            let Some(keyword) = tokens.next() else {
+                continue;
+            };
+            let args: Vec<&str> = tokens.collect();
+            match keyword {
+                "v" => positions.push(parse_vector(&args, "vertex position").map_err(|kind| error(line_number, kind))?),
+                "vn" => normals.push(parse_vector(&args, "vertex normal").map_err(|kind| error(line_number, kind))?),
+                "vt" => {
+                    let u = parse_f32(args.first().copied(), "texture coordinate").map_err(|kind| error(line_number, kind))?;
+                    let v = args.get(1).map_or(Ok(0.0), |v| parse_f32(Some(v), "texture coordinate")).map_err(|kind| error(line_number, kind))?;
+                    texture_coordinates.push((u, v));
+                },
+                "f" => {
+                    if args.len() < 3 {
+                        return Err(error(line_number, ObjErrorKind::TooFewVertices(args.len())));
+                    }
+                    let vertices = args.iter()
+                    .map(|v| parse_face_vertex(v, positions.len(), texture_coordinates.len(), normals.len()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|kind| error(line_number, kind))?;
+
+                    let key = (group.clone(), material.clone());
+                    let index = *builder_indices.entry(key).or_insert_with(|| {
+                        builders.push(MeshBuilder::new(group.clone(), material.clone()));
+                        builders.len() - 1
+                    });
+                    let polygon: Vec<Vector4> = vertices.iter().map(|v| positions[v.0]).collect();
+                    let builder = &mut builders[index];
+                    let indices: Vec<usize> = vertices.iter()
+                    .map(|&v| builder.vertex(v, &positions, &texture_coordinates, &normals))
+                    .collect();
+                    builder.triangles.extend(triangulate(&polygon).into_iter().map(|t| t.map(|i| indices[i])));
+                },
+                "g" | "o" => group = if args.is_empty() { String::from("default") } else { args.join(" ") },
+                "usemtl" => {
+                    let name = args.join(" ");
+                    if name.is_empty() {
+                        return Err(error(line_number, ObjErrorKind::MissingValue("material name")));
+                    }
+                    if !mtl_materials.contains_key(&name) {
+                        return Err(error(line_number, ObjErrorKind::UndefinedMaterial(name)));
+                    }
+                    material = Some(name);
+                },
+                "mtllib" => {
+                    if args.is_empty() {
+                        return Err(error(line_number, ObjErrorKind::MissingValue("material library")));
+                    }
+                    let directory = path.parent().unwrap_or(Path::new(""));
+                    for library in &args {
+                        mtl_materials.extend(MtlMaterial::load(directory.join(library))?);
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        let materials: HashMap<String, Arc<dyn Material<R> + Send + Sync>> = mtl_materials.iter()
+        .map(|(name, m)| (name.clone(), m.to_material()))
+        .collect();
+        let meshes = builders.into_iter()
+        .filter(|b| !b.triangles.is_empty())
+        .map(|b| {
+            let mesh_material = b.material.as_ref().map_or(default_material.clone(), |name| materials[name].clone());
+            let normals = b.normals.into_iter().collect::<Option<Vec<_>>>();
+            let uvs = b.uvs.into_iter().collect::<Option<Vec<_>>>();
+            ObjMesh {
+                group: b.group,
+                material: b.material,
+                mesh: TriangleMesh::new(b.positions, normals, uvs, b.triangles, mesh_material)
+            }
+        })
+        .collect();
+
+        Ok(Self { meshes, materials })
+    }
+}
+
+impl<R: Rng + ?Sized + 'static> Obj<R> {
+    /// Adds all meshes to `scene`.
+    pub fn add_to(self, scene: &mut RenderableList<R>) {
+        for m in self.meshes {
+            scene.push(Box::new(m.mesh));
+        }
+    }
+}
+
+/// Accumulates the vertices and triangles of one mesh. Every distinct combination of position, texture coordinate
+/// and normal indices becomes one mesh vertex.
+struct MeshBuilder {
+    group: String,
+    material: Option<String>,
+    vertex_indices: HashMap<(usize, Option<usize>, Option<usize>), usize>,
+    positions: Vec<Vector4>,
+    uvs: Vec<Option<(f32, f32)>>,
+    normals: Vec<Option<Vector4>>,
+    triangles: Vec<[usize; 3]>
+}
+
+impl MeshBuilder {
+    fn new(group: String, material: Option<String>) -> Self {
+        Self {
+            group,
+            material,
+            vertex_indices: HashMap::new(),
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            triangles: Vec::new()
+        }
+    }
+
+    fn vertex(
+        &mut self,
+        v: (usize, Option<usize>, Option<usize>),
+        positions: &[Vector4],
+        texture_coordinates: &[(f32, f32)],
+        normals: &[Vector4]
+    ) -> usize {
+        *self.vertex_indices.entry(v).or_insert_with(|| {
+            self.positions.push(positions[v.0]);
+            self.uvs.push(v.1.map(|i| texture_coordinates[i]));
+            self.normals.push(v.2.map(|i| normals[i]));
+            self.positions.len() - 1
+        })
+    }
+}
+
+/// Material parameters read from an MTL file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MtlMaterial {
+    pub diffuse: Vector4,               // Kd.
+    pub specular: Vector4,              // Ks.
+    pub emission: Vector4,              // Ke.
+    pub specular_exponent: f32,         // Ns.
+    pub refractive_index: f32,          // Ni.
+    pub dissolve: f32,                  // d, or 1 - Tr.
+    pub transmission_filter: Vector4,   // Tf.
+    pub illumination_model: u32         // illum.
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            diffuse: Vector4::new(0.8, 0.8, 0.8, 0.0),
+            specular: Vector4::new(0.0, 0.0, 0.0, 0.0),
+            emission: Vector4::new(0.0, 0.0, 0.0, 0.0),
+            specular_exponent: 0.0,
+            refractive_index: 1.0,
+            dissolve: 1.0,
+            transmission_filter: Vector4::new(1.0, 1.0, 1.0, 0.0),
+            illumination_model: 2
+        }
+    }
+}
+
+/// The material that an MTL material is mapped onto.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MtlKind {
+    DiffuseLight,
+    Lambertian,
+    FuzzySpecular,
+    Dielectric
+}
+
+impl MtlMaterial {
+    /// Loads all materials defined in the MTL file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<HashMap<String, Self>, ObjError> {
+        let path = path.as_ref();
+        let source = read_to_string(path)?;
+        let error = |line: usize, kind: ObjErrorKind| ObjError { path: path.to_path_buf(), line, kind };
+
+        let mut materials = HashMap::new();
+        let mut current: Option<(String, Self)> = None;
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let mut tokens = strip_comment(line).split_whitespace();
+            let Some(keyword) = tokens.next() else {
+                continue;
+            };
+            let args: Vec<&str> = tokens.collect();
+            if keyword == "newmtl" {
+                if args.is_empty() {
+                    return Err(error(line_number, ObjErrorKind::MissingValue("material name")));
+                }
+                materials.extend(current.take());
+                current = Some((args.join(" "), Self::default()));
+                continue;
+            }
+
+            let is_known = matches!(keyword, "Kd" | "Ks" | "Ke" | "Ns" | "Ni" | "d" | "Tr" | "Tf" | "illum");
+            if !is_known {
+                continue;
+            }
+            let Some((_, m)) = current.as_mut() else {
+                return Err(error(line_number, ObjErrorKind::NoCurrentMaterial));
+            };
+            let mut parse = || -> Result<(), ObjErrorKind> {
+                match keyword {
+                    "Kd" => m.diffuse = parse_color(&args)?,
+                    "Ks" => m.specular = parse_color(&args)?,
+                    "Ke" => m.emission = parse_color(&args)?,
+                    "Tf" => m.transmission_filter = parse_color(&args)?,
+                    "Ns" => m.specular_exponent = parse_f32(args.first().copied(), "specular exponent")?,
+                    "Ni" => m.refractive_index = parse_f32(args.first().copied(), "refractive index")?,
+                    "d" => m.dissolve = parse_f32(args.last().copied(), "dissolve")?,
+                    "Tr" => m.dissolve = 1.0 - parse_f32(args.first().copied(), "transparency")?,
+                    _ => {
+                        let s = args.first().copied().ok_or(ObjErrorKind::MissingValue("illumination model"))?;
+                        m.illumination_model = s.parse().map_err(|_| ObjErrorKind::InvalidNumber(s.to_string()))?;
+                    }
+                }
+                Ok(())
+            };
+            parse().map_err(|kind| error(line_number, kind))?;
+        }
+        materials.extend(current);
+
+        Ok(materials)
+    }
+
+    /// Materials with an emission colour become diffuse lights, transparent materials (`d < 1`, or an illumination
+    /// model with refraction) become dielectrics, materials
+    /// with an illumination model with ray traced reflections, or with a specular but no diffuse colour, become
+    /// fuzzy specular, and all others become Lambertian.
+    pub fn kind(&self) -> MtlKind {
+        let is_black = |c: Vector4| c.x() <= 0.0 && c.y() <= 0.0 && c.z() <= 0.0;
+        if !is_black(self.emission) {
+            MtlKind::DiffuseLight
+        } else if self.dissolve < 1.0 || matches!(self.illumination_model, 4 | 6 | 7 | 9) {
+            MtlKind::Dielectric
+        } else if matches!(self.illumination_model, 3 | 5 | 8) || (is_black(self.diffuse) && !is_black(self.specular)) {
+            MtlKind::FuzzySpecular
+        } else {
+            MtlKind::Lambertian
+        }
+    }
+
+    pub fn to_material<R: Rng + ?Sized>(&self) -> Arc<dyn Material<R> + Send + Sync> {
+        match self.kind() {
+            MtlKind::DiffuseLight => Arc::new(DiffuseLight::new(self.emission)),
+            MtlKind::Dielectric => {
+                // Beer's law: Tf = exp(-absorbance) for a unit distance.
+                let absorbance = |t: f32| if t > 0.0 { -t.min(1.0).ln() } else { 0.0 };
+                let tf = self.transmission_filter;
+                let refractive_index = if self.refractive_index > 0.0 { self.refractive_index } else { 1.0 };
+                Arc::new(Dielectric::new(Vector4::new(absorbance(tf.x()), absorbance(tf.y()), absorbance(tf.z()), 0.0), refractive_index))
+            },
+            MtlKind::FuzzySpecular => {
+                // Map the Phong exponent onto a roughness, using the common alpha = sqrt(2 / (Ns + 2)).
+                let fuzzing_radius = f32::sqrt(2.0 / (self.specular_exponent.max(0.0) + 2.0));
+                Arc::new(FuzzySpecular::new(self.specular, fuzzing_radius, MAX_FUZZING_ITERATIONS))
+            },
+            MtlKind::Lambertian => Arc::new(Lambertian::new(self.diffuse))
+        }
+    }
+}
+
+fn read_to_string(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|e| ObjError { path: path.to_path_buf(), line: 0, kind: ObjErrorKind::Io(e) })
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split('#').next().unwrap_or("")
+}
+
+fn parse_f32(s: Option<&str>, what: &'static str) -> Result<f32, ObjErrorKind> {
+    let s = s.ok_or(ObjErrorKind::MissingValue(what))?;
+    s.parse().map_err(|_| ObjErrorKind::InvalidNumber(s.to_string()))
+}
+
+fn parse_vector(args: &[&str], what: &'static str) -> Result<Vector4, ObjErrorKind> {
+    Ok(Vector4::new(
+        parse_f32(args.first().copied(), what)?,
+        parse_f32(args.get(1).copied(), what)?,
+        parse_f32(args.get(2).copied(), what)?,
+        0.0
+    ))
+}
+
+/// Parses an MTL colour, where a single value is used for all three components.
+fn parse_color(args: &[&str]) -> Result<Vector4, ObjErrorKind> {
+    if args.len() == 1 {
+        let c = parse_f32(args.first().copied(), "colour")?;
+        return Ok(Vector4::new(c, c, c, 0.0));
+    }
+    parse_vector(args, "colour")
+}
+
+/// Resolves a 1-based, or negative and relative to the end, OBJ index into a list of length `len`.
+fn resolve_index(s: &str, token: &str, len: usize) -> Result<usize, ObjErrorKind> {
+    let i: i64 = s.parse().map_err(|_| ObjErrorKind::InvalidIndex(token.to_string()))?;
+    let resolved = if i > 0 { i - 1 } else { len as i64 + i };
+    if i == 0 || resolved < 0 || resolved >= len as i64 {
+        return Err(ObjErrorKind::IndexOutOfRange(i));
+    }
+    Ok(resolved as usize)
+}
+
+/// Parses a face vertex of the form `v`, `v/vt`, `v//vn` or `v/vt/vn` into 0-based indices.
+fn parse_face_vertex(
+    token: &str,
+    position_count: usize,
+    texture_coordinate_count: usize,
+    normal_count: usize
+) -> Result<(usize, Option<usize>, Option<usize>), ObjErrorKind> {
+    let mut parts = token.split('/');
+    let position = resolve_index(parts.next().unwrap_or(""), token, position_count)?;
+    let texture_coordinate = match parts.next() {
+        Some("") | None => None,
+        Some(s) => Some(resolve_index(s, token, texture_coordinate_count)?)
+    };
+    let normal = match parts.next() {
+        Some("") | None => None,
+        Some(s) => Some(resolve_index(s, token, normal_count)?)
+    };
+    if parts.next().is_some() {
+        return Err(ObjErrorKind::InvalidIndex(token.to_string()));
+    }
+    Ok((position, texture_coordinate, normal))
+}
+
+/// Triangulates a planar, possibly concave, simple polygon by ear clipping and returns triples of indices into `polygon`.
+/// Falls back to a triangle fan if the polygon is degenerate.
+fn triangulate(polygon: &[Vector4]) -> Vec<[usize; 3]> {
+    let n = polygon.len();
+    let fan = || (1..n - 1).map(|i| [0, i, i + 1]).collect();
+    if n == 3 {
+        return vec![[0, 1, 2]];
+    }
+
+    // Newell's method for the polygon normal, whose direction also gives the orientation of the polygon.
+    let normal = (0..n).fold(Vector4::new(0.0, 0.0, 0.0, 0.0), |acc, i| acc + polygon[i].cross(polygon[(i + 1) % n]));
+    let axis = (0..3).max_by(|&a, &b| normal[a].abs().total_cmp(&normal[b].abs())).unwrap();
+    if normal[axis] == 0.0 {
+        return fan();
+    }
+
+    // Project onto the plane orthogonal to the dominant axis such that the polygon is counterclockwise.
+    let (a, b) = if normal[axis] > 0.0 { ((axis + 1) % 3, (axis + 2) % 3) } else { ((axis + 2) % 3, (axis + 1) % 3) };
+    let points: Vec<(f32, f32)> = polygon.iter().map(|p| (p[a], p[b])).collect();
+    let cross = |o: (f32, f32), p: (f32, f32), q: (f32, f32)| (p.0 - o.0) * (q.1 - o.1) - (p.1 - o.1) * (q.0 - o.0);
+
+    let mut remaining: Vec<usize> = (0..n).collect();
+    let mut triangles = Vec::with_capacity(n - 2);
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let ear = (0..m).find(|&i| {
+            let (prev, cur, next) = (remaining[(i + m - 1) % m], remaining[i], remaining[(i + 1) % m]);
+            let (p, c, q) = (points[prev], points[cur], points[next]);
+            cross(p, c, q) > 0.0 && remaining.iter()
+            .filter(|&&j| j != prev && j != cur && j != next)
+            .all(|&j| {
+                let x = points[j];
+                cross(p, c, x) < 0.0 || cross(c, q, x) < 0.0 || cross(q, p, x) < 0.0
+            })
+        });
+        let Some(i) = ear else {
+            // Numerically degenerate polygon, triangulate the rest as a fan.
+            triangles.extend((1..m - 1).map(|k| [remaining[0], remaining[k], remaining[k + 1]]));
+            return triangles;
+        };
+        triangles.push([remaining[(i + m - 1) % m], remaining[i], remaining[(i + 1) % m]]);
+        remaining.remove(i);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        intersectable::Intersectable,
+        ray::Ray
+    };
+    use rand_pcg::Pcg64Mcg;
+
+    fn data_path(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/obj").join(name)
+    }
+
+    fn default_material() -> Arc<dyn Material<Pcg64Mcg> + Send + Sync> {
+        Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)))
+    }
+
+    #[test]
+    fn test_load_cube() {
+        let obj = Obj::<Pcg64Mcg>::load(data_path("cube.obj"), default_material()).unwrap();
+        assert_eq!(obj.materials.len(), 4);
+
+        // One mesh per group and material.
+        let summary: Vec<(&str, Option<&str>, usize)> = obj.meshes.iter()
+        .map(|m| (m.group.as_str(), m.material.as_deref(), m.mesh.triangle_count()))
+        .collect();
+        assert_eq!(summary, vec![
+            ("sides", Some("red"), 8),
+            ("caps", Some("metal"), 2),
+            ("caps", Some("glass"), 2)
+        ]);
+
+        // Every ray from the inside must hit one of the meshes.
+        let r = Ray::new(Vector4::new(0.0, 0.0, 0.0, 0.0), Vector4::new(0.3, 0.2, 1.0, 0.0));
+        let hit = obj.meshes.iter().find_map(|m| m.mesh.intersect(r, 0.0, f32::INFINITY)).unwrap();
+        assert!((hit.p.z() - 1.0).abs() < 1e-6);
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn test_load_mtl() {
+        let materials = MtlMaterial::load(data_path("cube.mtl")).unwrap();
+        assert_eq!(materials["red"].kind(), MtlKind::Lambertian);
+        assert_eq!(materials["red"].diffuse, Vector4::new(0.8, 0.1, 0.1, 0.0));
+        assert_eq!(materials["metal"].kind(), MtlKind::FuzzySpecular);
+        assert_eq!(materials["metal"].specular_exponent, 200.0);
+        assert_eq!(materials["glass"].kind(), MtlKind::Dielectric);
+        assert_eq!(materials["glass"].refractive_index, 1.5);
+        assert_eq!(materials["lamp"].kind(), MtlKind::DiffuseLight);
+        assert_eq!(materials["lamp"].emission, Vector4::new(4.0, 4.0, 4.0, 0.0));
+    }
+
+    #[test]
+    fn test_negative_indices_and_polygons() {
+        let obj = Obj::<Pcg64Mcg>::load(data_path("polygons.obj"), default_material()).unwrap();
+        assert_eq!(obj.meshes.len(), 1);
+        let mesh = &obj.meshes[0].mesh;
+        // A concave hexagon (an L shape) and a quad using negative indices.
+        assert_eq!(mesh.triangle_count(), 4 + 2);
+
+        // The notch of the L shape must not be covered by the triangulation.
+        let down = Vector4::new(0.0, 0.0, -1.0, 0.0);
+        assert!(mesh.intersect(Ray::new(Vector4::new(1.5, 1.5, 1.0, 0.0), down), 0.0, f32::INFINITY).is_none());
+        for (x, y) in [(0.5, 0.5), (1.5, 0.5), (0.5, 1.5)] {
+            assert!(mesh.intersect(Ray::new(Vector4::new(x, y, 1.0, 0.0), down), 0.0, f32::INFINITY).is_some());
+        }
+        // The quad, which lies in the plane z = -1.
+        let hit = mesh.intersect(Ray::new(Vector4::new(4.5, 0.5, 1.0, 0.0), down), 0.0, f32::INFINITY).unwrap();
+        assert_eq!(hit.t, 2.0);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = Obj::<Pcg64Mcg>::load(data_path("invalid_index.obj"), default_material()).err().unwrap();
+        assert_eq!(error.line, 5);
+        assert!(matches!(error.kind, ObjErrorKind::IndexOutOfRange(4)));
+        assert!(error.to_string().ends_with("invalid_index.obj:5: index 4 is out of range"));
+
+        let error = Obj::<Pcg64Mcg>::load(data_path("undefined_material.obj"), default_material()).err().unwrap();
+        assert_eq!(error.line, 3);
+        assert!(matches!(error.kind, ObjErrorKind::UndefinedMaterial(ref name) if name == "missing"));
+
+        let error = Obj::<Pcg64Mcg>::load(data_path("invalid_number.obj"), default_material()).err().unwrap();
+        assert_eq!(error.line, 2);
+        assert!(matches!(error.kind, ObjErrorKind::InvalidNumber(ref s) if s == "1.0.0"));
+
+        let error = Obj::<Pcg64Mcg>::load(data_path("does_not_exist.obj"), default_material()).err().unwrap();
+        assert_eq!(error.line, 0);
+        assert!(matches!(error.kind, ObjErrorKind::Io(_)));
+    }
+}
@@ -26,6 +26,17 @@ pub fn sample_unit_hemisphere_uniform<R: Rng + ?Sized>(rng: &mut R, n: Vector4)
     }
 }
 
+/// Derives the seed of an independent random number stream from a user `seed` and the index of the stream, e.g. that of
+/// a pixel. Nearby indices give unrelated seeds, since both are scrambled with the SplitMix64 finalizer.
+pub fn stream_seed(seed: u64, stream: u64) -> u64 {
+    fn mix(mut z: u64) -> u64 {
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+    mix(mix(seed.wrapping_add(0x9e3779b97f4a7c15)) ^ stream.wrapping_mul(0x9e3779b97f4a7c15))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -56,7 +67,8 @@ mod tests {
 
         // If the points lie on the unit sphere, their norm should be (approximately) 1.
         const SAMPLE_COUNT: u32 = 1000000;
-        const MAX_ERROR: f32 = 0.000000001;
+        // The spacing of f32s around 1 is ~1e-7, so the error can not be bounded much below a few ULPs.
+        const MAX_ERROR: f32 = 4.0 * f32::EPSILON;
         for _ in 0..SAMPLE_COUNT {
             assert!(f32::abs(1.0 - sample_unit_sphere_uniform(&mut rng).norm()) < MAX_ERROR);
         }
@@ -82,4 +94,12 @@ mod tests {
         assert!(f32::abs(1.0 - (SAMPLE_COUNT - samples_in_direction_n_count) as f32 / samples_in_direction_n_count as f32) < MAX_ERROR);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_stream_seeds_are_distinct() {
+        let mut seeds: Vec<u64> = (0..3).flat_map(|seed| (0..10000).map(move |stream| stream_seed(seed, stream))).collect();
+        assert_eq!(stream_seed(7, 42), stream_seed(7, 42));
+        seeds.sort();
+        seeds.dedup();
+        assert_eq!(seeds.len(), 30000);
+    }
+}
@@ -3,12 +3,19 @@ use crate::vector4::Vector4;
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Ray {
     pub origin: Vector4,
-    pub direction: Vector4
+    pub direction: Vector4,
+    pub time: f32           // Time at which the ray samples a scene with moving objects.
 }
 
 impl Ray {
+    /// Constructs a ray at time zero.
     pub fn new(origin: Vector4, direction: Vector4) -> Self {
-        Self { origin, direction }
+        Self { origin, direction, time: 0.0 }
+    }
+
+    pub fn with_time(mut self, time: f32) -> Self {
+        self.time = time;
+        self
     }
 
     pub fn at(&self, t: f32) -> Vector4 {
@@ -18,4 +25,4 @@ impl Ray {
     pub fn length(&self, t: f32) -> f32 {
         (self.at(t) - self.origin).norm()
     }
-}
\ No newline at end of file
+}
@@ -1,4 +1,5 @@
 use crate::{
+    intersectable::{HitRecord, Intersectable},
     materials::Tangible,
     ray::Ray
 };
@@ -21,12 +22,34 @@ impl<R: Rng + ?Sized> RenderableList<R> {
         self.elements.push(element);
     }
 
-    /// Finds the smallest value of `t` such that `r` intersects an element of the list and `t` lies in `[t_min, t_max]`, and the index `i`
-    /// 
-    /// of the list element that yields the minimal `t`. Returns `Some(Intersection { t, i })` if such a `t` is found, `None` otherwise.
-    pub fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> (f32, &(dyn Tangible<R> + Send + Sync)) {
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    pub fn into_elements(self) -> Vec<Box<dyn Tangible<R> + Send + Sync>> {
+        self.elements
+    }
+}
+
+impl<R: Rng + ?Sized> Default for RenderableList<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: Rng + ?Sized> Intersectable<R> for RenderableList<R> {
+    /// Finds the intersection with the smallest `t` among all elements of the list. If several elements
+    /// yield the smallest `t`, the intersection with the one that was added first is returned.
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
         self.elements.iter()
-        .map(|e| (e.intersect(r, t_min, t_max), &**e))
-        .fold((f32::INFINITY, &*self.elements[0]), |acc, e| if e.0 < acc.0 { e } else { acc })
+        .filter_map(|e| e.intersect(r, t_min, t_max))
+        .fold(None, |acc, hit| match acc {
+            Some(closest) if closest.t <= hit.t => acc,
+            _ => Some(hit)
+        })
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,259 @@
+use std::{
+    f64::consts::PI,
+    ops::Deref
+};
+
+// Maximum number of Newton steps taken to polish each root.
+const POLISH_ITERATIONS: usize = 4;
+
+/// Real roots of a polynomial of degree at most four in ascending order. Multiple roots may be listed once or repeatedly.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Roots {
+    values: [f64; 4],
+    count: usize
+}
+
+impl Roots {
+    fn push(&mut self, x: f64) {
+        self.values[self.count] = x;
+        self.count += 1;
+    }
+
+    fn sorted(mut self) -> Self {
+        self.values[..self.count].sort_by(f64::total_cmp);
+        self
+    }
+}
+
+impl Deref for Roots {
+    type Target = [f64];
+
+    fn deref(&self) -> &[f64] {
+        &self.values[..self.count]
+    }
+}
+
+/// Evaluates the polynomial with `coefficients` in descending order of degree and its derivative at `x`.
+fn evaluate(coefficients: &[f64], x: f64) -> (f64, f64) {
+    coefficients.iter().fold((0.0, 0.0), |(f, df), &c| (f * x + c, df * x + f))
+}
+
+/// Refines the root `x` of the polynomial with `coefficients` in descending order of degree with Newton's method,
+/// stopping as soon as a step does not reduce the residual.
+fn polish(coefficients: &[f64], x: f64) -> f64 {
+    let mut x = x;
+    let (mut f, mut df) = evaluate(coefficients, x);
+    for _ in 0..POLISH_ITERATIONS {
+        if f == 0.0 || df == 0.0 {
+            break;
+        }
+        let next = x - f / df;
+        let (next_f, next_df) = evaluate(coefficients, next);
+        if next_f.is_nan() || next_f.abs() >= f.abs() {
+            break;
+        }
+        (x, f, df) = (next, next_f, next_df);
+    }
+    x
+}
+
+/// Finds the real roots of `a x^2 + b x + c`, or of `b x + c` if `a` is zero. Uses the form of the quadratic formula that
+/// avoids cancellation between `-b` and the square root of the discriminant.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Roots {
+    let mut roots = Roots::default();
+    if a == 0.0 {
+        if b != 0.0 {
+            roots.push(-c / b);
+        }
+        return roots;
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return roots;
+    }
+    let q = -0.5 * (b + discriminant.sqrt().copysign(b));
+    if q == 0.0 {
+        // b and c are both zero.
+        roots.push(0.0);
+        roots.push(0.0);
+    } else {
+        roots.push(q / a);
+        roots.push(c / q);
+    }
+    roots.sorted()
+}
+
+/// Finds the real roots of `a x^3 + b x^2 + c x + d`, falling back to `solve_quadratic` if `a` is zero.
+///
+/// The cubic is reduced to the depressed cubic `t^3 + p t + q`, whose roots are found with Cardano's formula if it has one
+/// real root and with the trigonometric method if it has three. The roots are then polished with Newton's method.
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Roots {
+    if a == 0.0 {
+        return solve_quadratic(b, c, d);
+    }
+    let (b, c, d) = (b / a, c / a, d / a);
+    let p = c - b * b / 3.0;
+    let q = 2.0 * b * b * b / 27.0 - b * c / 3.0 + d;
+    let discriminant = q * q / 4.0 + p * p * p / 27.0;
+    let offset = -b / 3.0;
+
+    let mut roots = Roots::default();
+    if discriminant > 0.0 {
+        // Of the two cube roots of Cardano's formula, compute the one without cancellation and derive the other from it.
+        let u = (-q / 2.0 - discriminant.sqrt().copysign(q)).cbrt();
+        let t = if u == 0.0 { 0.0 } else { u - p / (3.0 * u) };
+        roots.push(t + offset);
+    } else if p == 0.0 {
+        roots.push(offset);
+    } else {
+        let r = (-p / 3.0).sqrt();
+        let phi = (3.0 * q / (2.0 * p) * (-3.0 / p).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        for k in 0..3 {
+            roots.push(2.0 * r * (phi - 2.0 * PI * k as f64 / 3.0).cos() + offset);
+        }
+    }
+    let coefficients = [1.0, b, c, d];
+    roots.values[..roots.count].iter_mut().for_each(|x| *x = polish(&coefficients, *x));
+    roots.sorted()
+}
+
+/// Finds the real roots of `a x^4 + b x^3 + c x^2 + d x + e`, falling back to `solve_cubic` if `a` is zero.
+///
+/// The quartic is reduced to the depressed quartic `y^4 + p y^2 + q y + r` and factored into two quadratics using the
+/// largest root of its resolvent cubic (Ferrari's method), or solved as a quadratic in `y^2` if `q` vanishes. As both
+/// reductions lose accuracy when roots are close together, the roots are polished with Newton's method on the original
+/// quartic.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Roots {
+    if a == 0.0 {
+        return solve_cubic(b, c, d, e);
+    }
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+    let b2 = b * b;
+    let p = c - 3.0 * b2 / 8.0;
+    let q = d - b * c / 2.0 + b2 * b / 8.0;
+    let r = e - b * d / 4.0 + b2 * c / 16.0 - 3.0 * b2 * b2 / 256.0;
+    let offset = -b / 4.0;
+
+    let mut roots = Roots::default();
+    // The resolvent cubic is negative at zero and so always has a non-negative root.
+    let m = solve_cubic(8.0, 8.0 * p, 2.0 * p * p - 8.0 * r, -q * q).last().copied().unwrap_or(0.0);
+    if m <= 0.0 {
+        // Biquadratic: q is zero, so the quartic is a quadratic in y^2.
+        for z in solve_quadratic(1.0, p, r).iter().filter(|&&z| z >= 0.0) {
+            roots.push(z.sqrt() + offset);
+            roots.push(-z.sqrt() + offset);
+        }
+    } else {
+        let s = (2.0 * m).sqrt();
+        for y in solve_quadratic(1.0, -s, p / 2.0 + m + q / (2.0 * s)).iter()
+            .chain(solve_quadratic(1.0, s, p / 2.0 + m - q / (2.0 * s)).iter()) {
+            roots.push(y + offset);
+        }
+    }
+    let coefficients = [1.0, b, c, d, e];
+    roots.values[..roots.count].iter_mut().for_each(|x| *x = polish(&coefficients, *x));
+    roots.sorted()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use rand_pcg::Pcg64Mcg;
+
+    fn assert_roots(actual: &[f64], expected: &[f64], tolerance: f64) {
+        assert_eq!(actual.len(), expected.len(), "{:?} != {:?}", actual, expected);
+        for (x, y) in actual.iter().zip(expected) {
+            assert!((x - y).abs() <= tolerance * f64::max(1.0, y.abs()), "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_quadratic() {
+        assert_roots(&solve_quadratic(1.0, -3.0, 2.0), &[1.0, 2.0], 0.0);
+        assert_roots(&solve_quadratic(0.0, 2.0, -1.0), &[0.5], 0.0);
+        assert_roots(&solve_quadratic(1.0, 0.0, 1.0), &[], 0.0);
+        assert_roots(&solve_quadratic(1.0, 0.0, 0.0), &[0.0, 0.0], 0.0);
+        assert_roots(&solve_quadratic(0.0, 0.0, 1.0), &[], 0.0);
+        // The small root would be lost to cancellation with the textbook formula.
+        assert_roots(&solve_quadratic(1.0, -1e8, 1.0), &[1e-8, 1e8], 1e-15);
+    }
+
+    #[test]
+    fn test_cubic() {
+        assert_roots(&solve_cubic(1.0, -6.0, 11.0, -6.0), &[1.0, 2.0, 3.0], 1e-12);
+        // (x - 2)(x^2 + 1) has a single real root.
+        assert_roots(&solve_cubic(2.0, -4.0, 2.0, -4.0), &[2.0], 1e-12);
+        assert_roots(&solve_cubic(1.0, -3.0, 3.0, -1.0), &[1.0], 1e-12);
+        assert_roots(&solve_cubic(0.0, 1.0, -3.0, 2.0), &[1.0, 2.0], 1e-12);
+        // Double root: (x - 1)^2 (x + 2).
+        let roots = solve_cubic(1.0, 0.0, -3.0, 2.0);
+        assert!(roots.len() >= 2);
+        assert!((roots[0] + 2.0).abs() < 1e-12 && roots[1..].iter().all(|x| (x - 1.0).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_quartic() {
+        assert_roots(&solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0), &[1.0, 2.0, 3.0, 4.0], 1e-12);
+        // Biquadratic (x^2 - 1)(x^2 - 4).
+        assert_roots(&solve_quartic(1.0, 0.0, -5.0, 0.0, 4.0), &[-2.0, -1.0, 1.0, 2.0], 1e-12);
+        // No real roots: (x^2 + 1)(x^2 + 2).
+        assert_roots(&solve_quartic(1.0, 0.0, 3.0, 0.0, 2.0), &[], 0.0);
+        // Two real roots: (x - 1)(x + 3)(x^2 + x + 1).
+        assert_roots(&solve_quartic(3.0, 9.0, 0.0, -3.0, -9.0), &[-3.0, 1.0], 1e-12);
+        // Double roots: (x - 1)^2 (x - 2)^2, which are only found to about the square root of the precision.
+        let roots = solve_quartic(1.0, -6.0, 13.0, -12.0, 4.0);
+        assert!(!roots.is_empty() && roots.iter().all(|x| (x - 1.0).abs() < 1e-6 || (x - 2.0).abs() < 1e-6), "{:?}", roots);
+        assert_roots(&solve_quartic(0.0, 1.0, -6.0, 11.0, -6.0), &[1.0, 2.0, 3.0], 1e-12);
+    }
+
+    #[test]
+    fn test_quartic_accuracy() {
+        // Quartics with random real roots of various magnitudes are solved to within 1e-10 of the magnitude of the
+        // largest root, unless the roots are close together.
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        for _ in 0..10000 {
+            let scale = 10.0_f64.powi(rng.random_range(-3..4));
+            let mut expected: Vec<f64> = (0..4).map(|_| scale * rng.random_range(-1.0..1.0)).collect();
+            expected.sort_by(f64::total_cmp);
+            // Expand (x - x_0)(x - x_1)(x - x_2)(x - x_3).
+            let mut coefficients = vec![1.0];
+            for &x in &expected {
+                let mut next = vec![0.0; coefficients.len() + 1];
+                for (i, &c) in coefficients.iter().enumerate() {
+                    next[i] += c;
+                    next[i + 1] -= c * x;
+                }
+                coefficients = next;
+            }
+            let roots = solve_quartic(coefficients[0], coefficients[1], coefficients[2], coefficients[3], coefficients[4]);
+            // Close roots are ill-conditioned, so they are only found to about the square root of the precision, and roots
+            // closer together than that may be reported as a pair of complex roots.
+            let separation = expected.windows(2).map(|w| w[1] - w[0]).fold(f64::INFINITY, f64::min);
+            let tolerance = if separation > 1e-2 * scale { 1e-10 * scale } else { 1e-6 * scale };
+            if separation > 1e-3 * scale {
+                assert_eq!(roots.len(), 4, "{:?} != {:?}", roots, expected);
+            }
+            for x in roots.iter() {
+                assert!(expected.iter().any(|y| (x - y).abs() < tolerance), "{} is not in {:?}", x, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_torus_quartic() {
+        // Ray along the x axis through a torus about the z axis with radii 2 and 0.5, starting at x = -10: it enters and
+        // leaves the tube on both sides, at |x| = 1.5 and |x| = 2.5.
+        let (major, minor) = (2.0_f64, 0.5_f64);
+        let (o, d) = (-10.0_f64, 1.0_f64);
+        let k = o * o + major * major - minor * minor;
+        let roots = solve_quartic(
+            d.powi(4),
+            4.0 * d * d * o * d,
+            2.0 * d * d * k + 4.0 * (o * d).powi(2) - 4.0 * major * major * d * d,
+            4.0 * o * d * k - 8.0 * major * major * o * d,
+            k * k - 4.0 * major * major * o * o
+        );
+        assert_roots(&roots, &[7.5, 8.5, 11.5, 12.5], 1e-12);
+    }
+}
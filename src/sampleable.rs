@@ -0,0 +1,69 @@
+use crate::{materials::Tangible, vector4::Vector4};
+use rand::Rng;
+
+/// A point sampled on the surface of an object.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SurfaceSample {
+    pub p: Vector4,
+    pub normal: Vector4,    // Outward-facing surface normal of unit length at p.
+    pub uv: (f32, f32),     // Surface coordinates of p.
+    pub pdf: f32            // Probability density of sampling p, with respect to solid angle as seen from the reference point.
+}
+
+/// Trait for objects whose surfaces may be sampled, e.g. to sample light arriving from emissive objects.
+pub trait Sampleable<R: Rng + ?Sized> {
+    /// Samples a point on the surface of the object for estimating light that travels from the object to `origin`.
+    /// 
+    /// Returns `None` if no point could be sampled, e.g. if `origin` lies on the surface.
+    fn sample(&self, rng: &mut R, origin: Vector4) -> Option<SurfaceSample>;
+
+    /// Returns the probability density, with respect to solid angle, of `sample` choosing the first point on the surface
+    /// of the object along the ray from `origin` in the unit vector `direction`, or `0` if the ray misses the object.
+    fn pdf(&self, origin: Vector4, direction: Vector4) -> f32;
+}
+
+/// Converts a probability density with respect to surface area at `p`, where the surface has the unit normal `normal`,
+/// into a probability density with respect to solid angle as seen from `origin`.
+/// 
+/// Returns `None` if the surface is seen exactly edge-on, where the solid angle density is infinite.
+pub fn area_to_solid_angle_pdf(pdf_area: f32, origin: Vector4, p: Vector4, normal: Vector4) -> Option<f32> {
+    let d = p - origin;
+    let distance2 = d.norm2();
+    let cos_theta = f32::abs(normal.dot(d)) / distance2.sqrt();
+    if cos_theta == 0.0 || distance2 == 0.0 {
+        return None;
+    }
+    Some(pdf_area * distance2 / cos_theta)
+}
+
+/// Weight for combining a sample taken with probability density `pdf_f` with a second strategy that would have taken it
+/// with density `pdf_g`, using the power heuristic with exponent 2 (Veach, "Robust Monte Carlo Methods for Light Transport
+/// Simulation", 1997).
+pub fn power_heuristic(pdf_f: f32, pdf_g: f32) -> f32 {
+    if pdf_f.is_infinite() {
+        return 1.0;
+    }
+    let f2 = pdf_f * pdf_f;
+    let g2 = pdf_g * pdf_g;
+    if f2 + g2 == 0.0 { 0.0 } else { f2 / (f2 + g2) }
+}
+
+/// Trait for tangible objects whose surfaces may be sampled, i.e. objects that may be used as lights.
+pub trait Light<R: Rng + ?Sized>: Tangible<R> + Sampleable<R> {}
+
+impl<R: Rng + ?Sized, T: Tangible<R> + Sampleable<R> + ?Sized> Light<R> for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_power_heuristic() {
+        assert_eq!(power_heuristic(1.0, 1.0), 0.5);
+        assert_eq!(power_heuristic(3.0, 1.0), 0.9);
+        assert_eq!(power_heuristic(1.0, 3.0) + power_heuristic(3.0, 1.0), 1.0);
+        assert_eq!(power_heuristic(2.0, 0.0), 1.0);
+        assert_eq!(power_heuristic(0.0, 2.0), 0.0);
+        assert_eq!(power_heuristic(f32::INFINITY, 2.0), 1.0);
+    }
+}
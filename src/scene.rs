@@ -0,0 +1,953 @@
+use crate::{
+    aabb::Aabb,
+    camera::{AdaptiveSampling, Background, Camera, vfov_to_hfov},
+    constant_medium::ConstantMedium,
+    light_list::LightList,
+    materials::{
+        self,
+        Material,
+        dielectric::Dielectric,
+        diffuse::Diffuse,
+        diffuse_light::DiffuseLight,
+        fuzzy_specular::FuzzySpecular,
+        henyey_greenstein::HenyeyGreenstein,
+        isotropic::Isotropic,
+        lambertian::Lambertian,
+        specular::Specular
+    },
+    matrix4::Matrix4,
+    obj::{Obj, ObjError},
+    renderable_list::RenderableList,
+    surfaces::{
+        cone::Cone,
+        cuboid::Cuboid,
+        cylinder::Cylinder,
+        disk::Disk,
+        moving_sphere::MovingSphere,
+        plane::Plane,
+        quad::Quad,
+        quadric::Quadric,
+        sphere::Sphere,
+        torus::Torus,
+        triangle_mesh::TriangleMesh
+    },
+    transformed::Transformed,
+    vector4::Vector4
+};
+use rand::Rng;
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    error,
+    fmt,
+    fs,
+    io,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc
+};
+use toml::{
+    de::{DeTable, DeValue},
+    Spanned
+};
+
+// The default maximum number of attempts to find the fuzzed reflection direction for fuzzy specular materials.
+const MAX_FUZZING_ITERATIONS: usize = 4;
+
+/// Error produced when loading a scene file, pointing to the offending file, line and column.
+/// `line` and `column` are `0` for errors that do not concern a specific location, e.g. I/O errors.
+#[derive(Debug)]
+pub struct SceneError {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub kind: SceneErrorKind
+}
+
+#[derive(Debug)]
+pub enum SceneErrorKind {
+    Io(io::Error),
+    /// Syntax errors, unknown keys and values of the wrong type, as reported by the TOML parser.
+    Syntax(String),
+    InvalidValue(String),
+    UndefinedMaterial(String),
+    Obj(ObjError)
+}
+
+impl fmt::Display for SceneErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Syntax(message) => write!(f, "{}", message),
+            Self::InvalidValue(message) => write!(f, "invalid value: {}", message),
+            Self::UndefinedMaterial(name) => write!(f, "undefined material '{}'", name),
+            Self::Obj(e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.path.display(), self.kind)
+        } else {
+            write!(f, "{}:{}:{}: {}", self.path.display(), self.line, self.column, self.kind)
+        }
+    }
+}
+
+impl error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match &self.kind {
+            SceneErrorKind::Io(e) => Some(e),
+            SceneErrorKind::Obj(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+/// Camera, objects and lights described by a scene file.
+///
+/// Scene files are TOML documents with a `[camera]` table holding the parameters of `Camera::new`, a `[materials]` table
+/// of named materials and an `[[objects]]` array of objects referring to them by name:
+///
+/// ```toml
+/// [camera]
+/// image_width = 400
+/// aspect_ratio = 1.5
+/// vfov = 40.0                         # Vertical field of view in degrees, or hfov for the horizontal one.
+/// look_from = [0.0, -5.0, 1.0]
+/// look_at = [0.0, 0.0, 0.0]
+/// samples_per_pixel = 64
+/// adaptive = { min_samples = 16, threshold = 0.02 }     # Optional, see `AdaptiveSampling`.
+/// background = { type = "sky", horizon = [1.0, 1.0, 1.0], zenith = [0.5, 0.7, 1.0] }
+///
+/// [materials.glass]
+/// type = "dielectric"
+/// refractive_index = 1.5
+///
+/// [materials.lamp]
+/// type = "diffuse_light"
+/// emission = [4.0, 4.0, 4.0]
+///
+/// [[objects]]
+/// type = "sphere"
+/// center = [0.0, 0.0, 0.0]
+/// radius = 1.0
+/// material = "glass"
+///
+/// [[objects]]
+/// type = "sphere"
+/// center = [0.0, 0.0, 5.0]
+/// radius = 1.0
+/// material = "lamp"
+/// light = true                        # Sample the object explicitly for direct lighting.
+/// ```
+///
+/// The camera may also take a `shutter` interval, such as `shutter = [0.0, 1.0]`, over which moving objects are blurred.
+///
+/// Material types and their keys:
+/// - `lambertian`, `diffuse` and `specular`: `albedo`.
+/// - `fuzzy_specular`: `albedo`, `fuzz` and optionally `max_iterations`.
+/// - `dielectric`: `refractive_index` and optionally `attenuation`.
+/// - `diffuse_light`: `emission`.
+/// - `isotropic`: `albedo`. A phase function for media, scattering light equally in all directions.
+/// - `henyey_greenstein`: `albedo` and the asymmetry `g`, strictly between -1 and 1. A phase function for media,
+///   scattering light forwards for positive `g` and backwards for negative `g`.
+/// - `none`: no keys. Light passes through it unchanged.
+///
+/// Object types and their keys besides `material`:
+/// - `sphere`: `center` and `radius`.
+/// - `moving_sphere`: `radius` and the centres `center0` and `center1` it moves between over the `times`, `[0.0, 1.0]`
+///   by default.
+/// - `plane`: a `point` and the `normal`.
+/// - `quad`: a `corner` and the edge vectors `u` and `v`.
+/// - `disk`: `center`, `normal` and `radius`.
+/// - `cuboid`: the corners `min` and `max`.
+/// - `cylinder`: the centres `base` and `top` of its ends, its `radius` and optionally `capped = false` to leave its
+///   ends open.
+/// - `cone`: like `cylinder`, but with `base_radius` and optionally `top_radius`, which is zero by default.
+/// - `torus`: `center`, `axis`, `major_radius` and `minor_radius`.
+/// - `quadric`: the ten `coefficients` of its equation, see `Quadric`, and the corners `min` and `max` of the box it is
+///   clipped to, which may be infinite.
+/// - `constant_medium`: the `density` of smoke or fog filling a `boundary`, either `{ type = "sphere", center = [...],
+///   radius = ... }` or `{ type = "cuboid", min = [...], max = [...] }`, whose `material` is the phase function.
+/// - `obj`: the `path` of a Wavefront OBJ file relative to the scene file, whose faces without an MTL material are given
+///   `material`, and optionally a `transform`. This is a list of steps applied in order with angles in degrees, such as
+///   `[{ scale = [2.0, 2.0, 2.0] }, { rotate = { axis = [0.0, 0.0, 1.0], angle = 45.0 } }, { translate = [1.0, 0.0, 0.0] }]`.
+///   Objects loading the same file with the same material share its meshes.
+///
+/// Spheres, quads, disks, cuboids and `obj` objects without a `transform` may be lights.
+pub struct Scene<R: Rng + ?Sized> {
+    pub camera: Camera,
+    pub objects: RenderableList<R>,
+    pub lights: LightList<R>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    camera: Spanned<CameraDescription>,
+    #[serde(default)]
+    materials: HashMap<String, Spanned<MaterialDescription>>,
+    #[serde(default)]
+    objects: Vec<Spanned<ObjectDescription>>
+}
+
+fn default_color_depth() -> usize { 255 }
+fn default_decoding_gamma() -> f32 { 2.2 }
+fn default_focus_distance() -> f32 { 10.0 }
+fn default_vup() -> [f32; 3] { [0.0, 0.0, 1.0] }
+fn default_samples_per_pixel() -> usize { 16 }
+fn default_max_depth() -> usize { 64 }
+fn default_t_min() -> f32 { 0.001 }
+fn default_t_max() -> f32 { f32::INFINITY }
+fn default_tile_size() -> usize { 32 }
+fn default_max_iterations() -> usize { MAX_FUZZING_ITERATIONS }
+fn default_capped() -> bool { true }
+fn default_times() -> [f32; 2] { [0.0, 1.0] }
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    aspect_ratio: f32,
+    image_width: usize,
+    #[serde(default = "default_color_depth")]
+    color_depth: usize,
+    #[serde(default = "default_decoding_gamma")]
+    decoding_gamma: f32,
+    vfov: Option<f32>,                  // Degrees.
+    hfov: Option<f32>,                  // Degrees.
+    #[serde(default = "default_focus_distance")]
+    focus_distance: f32,
+    look_from: [f32; 3],
+    look_at: [f32; 3],
+    #[serde(default = "default_vup")]
+    vup: [f32; 3],
+    #[serde(default = "default_samples_per_pixel")]
+    samples_per_pixel: usize,
+    adaptive: Option<AdaptiveDescription>,
+    #[serde(default)]
+    defocus_angle: f32,                 // Degrees.
+    #[serde(default = "default_max_depth")]
+    max_depth: usize,
+    #[serde(default = "default_t_min")]
+    t_min: f32,
+    #[serde(default = "default_t_max")]
+    t_max: f32,
+    background: Option<BackgroundDescription>,
+    #[serde(default)]
+    shutter: [f32; 2],                  // Times at which the shutter opens and closes.
+    #[serde(default = "default_tile_size")]
+    tile_size: usize
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AdaptiveDescription {
+    min_samples: usize,
+    threshold: f32
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum BackgroundDescription {
+    None,
+    Solid { radiance: [f32; 3] },
+    Sky { horizon: [f32; 3], zenith: [f32; 3] }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDescription {
+    Lambertian { albedo: [f32; 3] },
+    Diffuse { albedo: [f32; 3] },
+    Specular { albedo: [f32; 3] },
+    FuzzySpecular {
+        albedo: [f32; 3],
+        fuzz: f32,
+        #[serde(default = "default_max_iterations")]
+        max_iterations: usize
+    },
+    Dielectric {
+        #[serde(default)]
+        attenuation: [f32; 3],
+        refractive_index: f32
+    },
+    DiffuseLight { emission: [f32; 3] },
+    Isotropic { albedo: [f32; 3] },
+    HenyeyGreenstein { albedo: [f32; 3], g: f32 },
+    None
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDescription {
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+        material: String,
+        #[serde(default)]
+        light: bool
+    },
+    MovingSphere {
+        center0: [f32; 3],
+        center1: [f32; 3],
+        #[serde(default = "default_times")]
+        times: [f32; 2],
+        radius: f32,
+        material: String
+    },
+    Plane {
+        point: [f32; 3],
+        normal: [f32; 3],
+        material: String
+    },
+    Quad {
+        corner: [f32; 3],
+        u: [f32; 3],
+        v: [f32; 3],
+        material: String,
+        #[serde(default)]
+        light: bool
+    },
+    Disk {
+        center: [f32; 3],
+        normal: [f32; 3],
+        radius: f32,
+        material: String,
+        #[serde(default)]
+        light: bool
+    },
+    Cuboid {
+        min: [f32; 3],
+        max: [f32; 3],
+        material: String,
+        #[serde(default)]
+        light: bool
+    },
+    Cylinder {
+        base: [f32; 3],
+        top: [f32; 3],
+        radius: f32,
+        #[serde(default = "default_capped")]
+        capped: bool,
+        material: String
+    },
+    Cone {
+        base: [f32; 3],
+        base_radius: f32,
+        top: [f32; 3],
+        #[serde(default)]
+        top_radius: f32,
+        #[serde(default = "default_capped")]
+        capped: bool,
+        material: String
+    },
+    Torus {
+        center: [f32; 3],
+        axis: [f32; 3],
+        major_radius: f32,
+        minor_radius: f32,
+        material: String
+    },
+    Quadric {
+        coefficients: [f32; 10],
+        min: [f32; 3],
+        max: [f32; 3],
+        material: String
+    },
+    ConstantMedium {
+        boundary: BoundaryDescription,
+        density: f32,
+        material: String
+    },
+    Obj {
+        path: PathBuf,
+        material: String,
+        #[serde(default)]
+        light: bool,
+        #[serde(default)]
+        transform: Vec<TransformDescription>
+    }
+}
+
+/// Closed surface filled by a medium.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum BoundaryDescription {
+    Sphere { center: [f32; 3], radius: f32 },
+    Cuboid { min: [f32; 3], max: [f32; 3] }
+}
+
+/// Step of the transform placing an object, with angles in degrees.
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum TransformDescription {
+    Translate([f32; 3]),
+    Scale([f32; 3]),
+    Rotate { axis: [f32; 3], angle: f32 }
+}
+
+fn vector(v: [f32; 3]) -> Vector4 {
+    Vector4::new(v[0], v[1], v[2], 0.0)
+}
+
+/// Returns whether `x` is a positive finite number. Scene files may contain `nan`, which fails every comparison, so values
+/// are checked with this rather than by rejecting those that are `<= 0.0`.
+fn positive(x: f32) -> bool {
+    x.is_finite() && x > 0.0
+}
+
+/// Returns whether `x` is a finite number that is not negative.
+fn non_negative(x: f32) -> bool {
+    x.is_finite() && x >= 0.0
+}
+
+/// Returns the 1-based line and column of the character at byte `offset` of `source`.
+fn line_and_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+}
+
+/// Returns the span of the value reached from `value` by following `keys` through nested tables and arrays, where the
+/// keys of array elements are their indices, or `None` if there is no such value.
+///
+/// The descriptions of materials and objects are internally tagged enums, whose fields serde can not deserialize as
+/// `Spanned` values, so errors concerning a field are located by looking it up in the parsed document instead.
+fn value_span(value: &Spanned<DeValue>, keys: &[&str]) -> Option<Range<usize>> {
+    let Some((key, rest)) = keys.split_first() else {
+        return Some(value.span());
+    };
+    let next = match value.get_ref() {
+        DeValue::Table(table) => table.get(*key)?,
+        DeValue::Array(array) => array.get(key.parse::<usize>().ok()?)?,
+        _ => return None
+    };
+    value_span(next, rest)
+}
+
+impl<R: Rng + ?Sized + 'static> Scene<R> {
+    /// Loads the scene file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|e| SceneError { path: path.to_path_buf(), line: 0, column: 0, kind: SceneErrorKind::Io(e) })?;
+        Self::parse(&source, path)
+    }
+
+    /// Builds a scene from the contents `source` of the scene file at `path`, which is used for error messages and to
+    /// resolve the paths of OBJ files.
+    pub fn parse(source: &str, path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let error = |span: Range<usize>, kind: SceneErrorKind| {
+            let (line, column) = line_and_column(source, span.start);
+            SceneError { path: path.to_path_buf(), line, column, kind }
+        };
+        let invalid = |span: Range<usize>, message: &str| error(span, SceneErrorKind::InvalidValue(message.to_string()));
+
+        let syntax_error = |e: toml::de::Error| error(e.span().unwrap_or(0..0), SceneErrorKind::Syntax(e.message().to_string()));
+        let description: SceneDescription = toml::from_str(source).map_err(syntax_error)?;
+        let document = DeTable::parse(source).map_err(syntax_error)?;
+        let document = Spanned::new(document.span(), DeValue::Table(document.into_inner()));
+
+        let camera_span = description.camera.span();
+        let camera_at = |key: &str| value_span(&document, &["camera", key]).unwrap_or(camera_span.clone());
+        let c = description.camera.into_inner();
+        let hfov = match (c.vfov, c.hfov) {
+            (Some(vfov), None) => vfov_to_hfov(vfov.to_radians(), c.aspect_ratio),
+            (None, Some(hfov)) => hfov.to_radians(),
+            _ => return Err(invalid(camera_span, "the camera needs exactly one of 'vfov' and 'hfov'"))
+        };
+        let non_positive = [
+            ("image_width", c.image_width == 0),
+            ("aspect_ratio", !positive(c.aspect_ratio)),
+            ("samples_per_pixel", c.samples_per_pixel == 0),
+            ("tile_size", c.tile_size == 0)
+        ];
+        if let Some((key, _)) = non_positive.iter().find(|(_, non_positive)| *non_positive) {
+            return Err(invalid(camera_at(key), "'image_width', 'aspect_ratio', 'samples_per_pixel' and 'tile_size' must be positive"));
+        }
+        if !(0.0 < hfov && hfov < std::f32::consts::PI) {
+            return Err(invalid(camera_at(if c.vfov.is_some() { "vfov" } else { "hfov" }), "the field of view must lie between 0 and 180 degrees"));
+        }
+        let background = match c.background {
+            Some(BackgroundDescription::None) => Background::None,
+            Some(BackgroundDescription::Solid { radiance }) => Background::Solid(vector(radiance)),
+            Some(BackgroundDescription::Sky { horizon, zenith }) => Background::Sky { horizon: vector(horizon), zenith: vector(zenith) },
+            None => Background::default()
+        };
+        if c.adaptive.as_ref().is_some_and(|a| a.min_samples < 2 || a.threshold.is_nan() || a.threshold < 0.0) {
+            return Err(invalid(camera_at("adaptive"), "adaptive sampling needs 'min_samples' of at least 2 and a non-negative 'threshold'"));
+        }
+        let [shutter_open, shutter_close] = c.shutter;
+        if !(shutter_open.is_finite() && shutter_close.is_finite() && shutter_open <= shutter_close) {
+            return Err(invalid(camera_at("shutter"), "the 'shutter' must not close before it opens"));
+        }
+        let mut camera = Camera::new(
+            c.aspect_ratio,
+            c.image_width,
+            c.color_depth,
+            c.decoding_gamma,
+            hfov,
+            c.focus_distance,
+            vector(c.look_from),
+            vector(c.look_at),
+            vector(c.vup),
+            c.samples_per_pixel,
+            c.defocus_angle.to_radians(),
+            c.max_depth,
+            c.t_min,
+            c.t_max
+        )
+        .with_background(background)
+        .with_shutter(shutter_open, shutter_close)
+        .with_tile_size(c.tile_size);
+        if let Some(a) = c.adaptive {
+            camera = camera.with_adaptive_sampling(AdaptiveSampling::new(a.min_samples, a.threshold));
+        }
+
+        let mut materials: HashMap<String, Arc<dyn Material<R> + Send + Sync>> = HashMap::new();
+        for (name, m) in description.materials {
+            let span = m.span();
+            let at = |key: &str| value_span(&document, &["materials", &name, key]).unwrap_or(span.clone());
+            let material: Arc<dyn Material<R> + Send + Sync> = match m.into_inner() {
+                MaterialDescription::Lambertian { albedo } => Arc::new(Lambertian::new(vector(albedo))),
+                MaterialDescription::Diffuse { albedo } => Arc::new(Diffuse::new(vector(albedo))),
+                MaterialDescription::Specular { albedo } => Arc::new(Specular::new(vector(albedo))),
+                MaterialDescription::FuzzySpecular { albedo, fuzz, max_iterations } => {
+                    if !non_negative(fuzz) || max_iterations == 0 {
+                        return Err(invalid(at(if !non_negative(fuzz) { "fuzz" } else { "max_iterations" }), "'fuzz' must not be negative and 'max_iterations' must be positive"));
+                    }
+                    Arc::new(FuzzySpecular::new(vector(albedo), fuzz, max_iterations))
+                },
+                MaterialDescription::Dielectric { attenuation, refractive_index } => {
+                    if !positive(refractive_index) {
+                        return Err(invalid(at("refractive_index"), "'refractive_index' must be positive"));
+                    }
+                    Arc::new(Dielectric::new(vector(attenuation), refractive_index))
+                },
+                MaterialDescription::DiffuseLight { emission } => Arc::new(DiffuseLight::new(vector(emission))),
+                MaterialDescription::Isotropic { albedo } => Arc::new(Isotropic::new(vector(albedo))),
+                MaterialDescription::HenyeyGreenstein { albedo, g } => {
+                    if !(g > -1.0 && g < 1.0) {
+                        return Err(invalid(at("g"), "'g' must lie strictly between -1 and 1"));
+                    }
+                    Arc::new(HenyeyGreenstein::new(vector(albedo), g))
+                },
+                MaterialDescription::None => Arc::new(materials::None)
+            };
+            materials.insert(name, material);
+        }
+
+        let mut objects = RenderableList::new();
+        let mut lights = LightList::new();
+        let mut obj_meshes: HashMap<(PathBuf, String), Vec<Arc<TriangleMesh<R>>>> = HashMap::new();
+        for (i, o) in description.objects.into_iter().enumerate() {
+            let span = o.span();
+            let index = i.to_string();
+            let at = |keys: &[&str]| value_span(&document, &[&["objects", index.as_str()], keys].concat()).unwrap_or(span.clone());
+            let material_named = |name: &String| {
+                materials.get(name).cloned().ok_or_else(|| error(at(&["material"]), SceneErrorKind::UndefinedMaterial(name.clone())))
+            };
+            match o.into_inner() {
+                ObjectDescription::Sphere { center, radius, material, light } => {
+                    if !positive(radius) {
+                        return Err(invalid(at(&["radius"]), "'radius' must be positive"));
+                    }
+                    let sphere = Arc::new(Sphere::new(vector(center), radius, material_named(&material)?));
+                    if light {
+                        lights.push(sphere.clone());
+                    }
+                    objects.push(Box::new(sphere));
+                },
+                ObjectDescription::MovingSphere { center0, center1, times, radius, material } => {
+                    if !positive(radius) {
+                        return Err(invalid(at(&["radius"]), "'radius' must be positive"));
+                    }
+                    if !times.iter().all(|t| t.is_finite()) || times[0] > times[1] {
+                        return Err(invalid(at(&["times"]), "the 'times' of a moving sphere must not be in reverse order"));
+                    }
+                    let material = material_named(&material)?;
+                    objects.push(Box::new(MovingSphere::new(vector(center0), vector(center1), times[0], times[1], radius, material)));
+                },
+                ObjectDescription::Plane { point, normal, material } => {
+                    if vector(normal).norm2() == 0.0 {
+                        return Err(invalid(at(&["normal"]), "'normal' must not be zero"));
+                    }
+                    objects.push(Box::new(Plane::new(vector(point), vector(normal), material_named(&material)?)));
+                },
+                ObjectDescription::Quad { corner, u, v, material, light } => {
+                    if vector(u).cross(vector(v)).norm2() == 0.0 {
+                        return Err(invalid(at(&["v"]), "'u' and 'v' must span a parallelogram"));
+                    }
+                    let quad = Arc::new(Quad::new(vector(corner), vector(u), vector(v), material_named(&material)?));
+                    if light {
+                        lights.push(quad.clone());
+                    }
+                    objects.push(Box::new(quad));
+                },
+                ObjectDescription::Disk { center, normal, radius, material, light } => {
+                    if !positive(radius) || vector(normal).norm2() == 0.0 {
+                        return Err(invalid(at(&[if !positive(radius) { "radius" } else { "normal" }]), "'radius' must be positive and 'normal' must not be zero"));
+                    }
+                    let disk = Arc::new(Disk::new(vector(center), vector(normal), radius, material_named(&material)?));
+                    if light {
+                        lights.push(disk.clone());
+                    }
+                    objects.push(Box::new(disk));
+                },
+                ObjectDescription::Cuboid { min, max, material, light } => {
+                    if (0..3).any(|axis| !positive(max[axis] - min[axis])) {
+                        return Err(invalid(at(&["max"]), "'min' must be less than 'max' along every axis"));
+                    }
+                    let cuboid = Arc::new(Cuboid::new(vector(min), vector(max), material_named(&material)?));
+                    if light {
+                        lights.push(cuboid.clone());
+                    }
+                    objects.push(Box::new(cuboid));
+                },
+                ObjectDescription::Cylinder { base, top, radius, capped, material } => {
+                    if !positive(radius) || base == top {
+                        return Err(invalid(at(&[if !positive(radius) { "radius" } else { "top" }]), "'radius' must be positive and 'base' and 'top' must differ"));
+                    }
+                    objects.push(Box::new(Cylinder::new(vector(base), vector(top), radius, capped, material_named(&material)?)));
+                },
+                ObjectDescription::Cone { base, base_radius, top, top_radius, capped, material } => {
+                    if !non_negative(base_radius) || !non_negative(top_radius) || base_radius + top_radius == 0.0 || base == top {
+                        let key = if !non_negative(base_radius) { "base_radius" } else if base == top { "top" } else { "top_radius" };
+                        return Err(invalid(at(&[key]), "radii must not be negative nor both zero and 'base' and 'top' must differ"));
+                    }
+                    let cone = Cone::new(vector(base), base_radius, vector(top), top_radius, capped, material_named(&material)?);
+                    objects.push(Box::new(cone));
+                },
+                ObjectDescription::Torus { center, axis, major_radius, minor_radius, material } => {
+                    if !positive(minor_radius) || !positive(major_radius) || major_radius <= minor_radius || vector(axis).norm2() == 0.0 {
+                        let key = if !positive(minor_radius) { "minor_radius" } else if !positive(major_radius) || major_radius <= minor_radius { "major_radius" } else { "axis" };
+                        return Err(invalid(at(&[key]), "'minor_radius' must be positive and less than 'major_radius' and 'axis' must not be zero"));
+                    }
+                    let torus = Torus::new(vector(center), vector(axis), major_radius, minor_radius, material_named(&material)?);
+                    objects.push(Box::new(torus));
+                },
+                ObjectDescription::Quadric { coefficients, min, max, material } => {
+                    if (0..3).any(|axis| min[axis].is_nan() || max[axis].is_nan() || min[axis] > max[axis]) {
+                        return Err(invalid(at(&["max"]), "'min' must not be greater than 'max' along any axis"));
+                    }
+                    let bounds = Aabb::new(vector(min), vector(max));
+                    objects.push(Box::new(Quadric::new(coefficients, bounds, material_named(&material)?)));
+                },
+                ObjectDescription::ConstantMedium { boundary, density, material } => {
+                    if !positive(density) {
+                        return Err(invalid(at(&["density"]), "'density' must be positive"));
+                    }
+                    let phase = material_named(&material)?;
+                    let none: Arc<dyn Material<R> + Send + Sync> = Arc::new(materials::None);
+                    match boundary {
+                        BoundaryDescription::Sphere { center, radius } => {
+                            if !positive(radius) {
+                                return Err(invalid(at(&["boundary", "radius"]), "'radius' must be positive"));
+                            }
+                            objects.push(Box::new(ConstantMedium::new(Sphere::new(vector(center), radius, none), density, phase)));
+                        },
+                        BoundaryDescription::Cuboid { min, max } => {
+                            if (0..3).any(|axis| !positive(max[axis] - min[axis])) {
+                                return Err(invalid(at(&["boundary", "max"]), "'min' must be less than 'max' along every axis"));
+                            }
+                            objects.push(Box::new(ConstantMedium::new(Cuboid::new(vector(min), vector(max), none), density, phase)));
+                        }
+                    }
+                },
+                ObjectDescription::Obj { path: obj_path, material, light, transform } => {
+                    if light && !transform.is_empty() {
+                        return Err(invalid(at(&["light"]), "objects with a 'transform' can not be lights"));
+                    }
+                    let mut matrix = Matrix4::identity();
+                    for (j, step) in transform.into_iter().enumerate() {
+                        let step = match step {
+                            TransformDescription::Translate(offset) => Matrix4::translation(vector(offset)),
+                            TransformDescription::Scale(factors) => Matrix4::scaling(vector(factors)),
+                            TransformDescription::Rotate { axis, angle } => {
+                                if vector(axis).norm2() == 0.0 {
+                                    return Err(invalid(at(&["transform", &j.to_string(), "rotate", "axis"]), "'axis' must not be zero"));
+                                }
+                                Matrix4::rotation(vector(axis), angle.to_radians())
+                            }
+                        };
+                        matrix = step * matrix;
+                    }
+                    if matrix.inverse().is_none() {
+                        return Err(invalid(at(&["transform"]), "'transform' must be invertible"));
+                    }
+
+                    // Each file is loaded once per material, and its meshes shared by every object referring to it.
+                    let obj_path = path.parent().unwrap_or(Path::new("")).join(obj_path);
+                    let meshes = match obj_meshes.entry((obj_path, material)) {
+                        Entry::Occupied(entry) => entry.into_mut(),
+                        Entry::Vacant(entry) => {
+                            let (obj_path, material) = entry.key();
+                            let obj = Obj::load(obj_path, material_named(material)?)
+                                .map_err(|e| error(at(&["path"]), SceneErrorKind::Obj(e)))?;
+                            entry.insert(obj.meshes.into_iter().map(|m| Arc::new(m.mesh)).collect())
+                        }
+                    };
+                    for mesh in meshes.iter() {
+                        if matrix != Matrix4::identity() {
+                            objects.push(Box::new(Transformed::new(mesh.clone(), matrix)));
+                            continue;
+                        }
+                        if light {
+                            lights.push(mesh.clone());
+                        }
+                        objects.push(Box::new(mesh.clone()));
+                    }
+                }
+            }
+        }
+
+        Ok(Self { camera, objects, lights })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{intersectable::Intersectable, ray::Ray};
+    use rand_pcg::Pcg64Mcg;
+
+    const SCENE: &str = r#"
+[camera]
+aspect_ratio = 1.0
+image_width = 8
+vfov = 90.0
+look_from = [0.0, -3.0, 0.0]
+look_at = [0.0, 0.0, 0.0]
+samples_per_pixel = 2
+background = { type = "solid", radiance = [0.0, 0.0, 0.0] }
+
+[materials.lamp]
+type = "diffuse_light"
+emission = [4.0, 2.0, 1.0]
+
+[materials.metal]
+type = "fuzzy_specular"
+albedo = [0.9, 0.9, 0.9]
+fuzz = 0.1
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.5
+material = "lamp"
+light = true
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, -100.0]
+radius = 98.0
+material = "metal"
+"#;
+
+    fn parse(source: &str) -> Result<Scene<Pcg64Mcg>, SceneError> {
+        Scene::parse(source, "test.toml")
+    }
+
+    fn error_location(source: &str) -> (usize, usize, String) {
+        error_location_at(source, Path::new("test.toml"))
+    }
+
+    fn error_location_at(source: &str, path: &Path) -> (usize, usize, String) {
+        let e = Scene::<Pcg64Mcg>::parse(source, path).err().expect("scene should not load");
+        (e.line, e.column, e.kind.to_string())
+    }
+
+    #[test]
+    fn test_parse() {
+        let scene = parse(SCENE).unwrap();
+        assert_eq!(scene.objects.len(), 2);
+        assert_eq!(scene.lights.len(), 1);
+        // The light is shared between the objects and the lights.
+        let r = Ray::new(Vector4::new(0.0, -3.0, 0.0, 0.0), Vector4::new(0.0, 1.0, 0.0, 0.0));
+        assert!(scene.lights.contains(scene.objects.intersect(r, 0.001, f32::INFINITY).unwrap().object));
+        let r = Ray::new(Vector4::new(0.0, -3.0, 0.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        assert!(!scene.lights.contains(scene.objects.intersect(r, 0.001, f32::INFINITY).unwrap().object));
+
+        // The centre pixel only sees the light.
+        let image = scene.camera.render(&mut Pcg64Mcg::new(0xcafef00dd15ea5e5), &scene.objects, &scene.lights);
+        assert_eq!((image.width(), image.height()), (8, 8));
+        assert_eq!(image.pixel(4, 4), Vector4::new(4.0, 2.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_errors_are_located() {
+        // Unknown keys.
+        let source = SCENE.replace("fuzz = 0.1", "fuzz = 0.1\nroughness = 0.5");
+        let (line, _, message) = error_location(&source);
+        assert_eq!(line, 15);
+        assert!(message.contains("unknown field `roughness`"), "{}", message);
+
+        // Values of the wrong type.
+        let (line, column, message) = error_location(&SCENE.replace("image_width = 8", "image_width = \"8\""));
+        assert_eq!((line, column), (4, 15));
+        assert!(message.contains("invalid type"), "{}", message);
+
+        // Invalid values.
+        let (line, column, message) = error_location(&SCENE.replace("radius = 98.0", "radius = -98.0"));
+        assert_eq!((line, column), (30, 10));
+        assert!(message.contains("'radius' must be positive"), "{}", message);
+        for (value, replaced, location) in [("radius = 98.0", "radius = nan", (30, 10)), ("fuzz = 0.1", "fuzz = nan", (18, 8)), ("aspect_ratio = 1.0", "aspect_ratio = nan", (3, 16))] {
+            let (line, column, message) = error_location(&SCENE.replace(value, replaced));
+            assert_eq!((line, column), location, "{}", message);
+            assert!(message.starts_with("invalid value"), "{}", message);
+        }
+        let (line, _, message) = error_location(&SCENE.replace("vfov = 90.0", "hfov = 90.0\nvfov = 90.0"));
+        assert_eq!(line, 2);
+        assert!(message.contains("exactly one of 'vfov' and 'hfov'"), "{}", message);
+        let (line, column, message) = error_location(&SCENE.replace("image_width = 8", "image_width = 8\nadaptive = { min_samples = 1, threshold = 0.1 }"));
+        assert_eq!((line, column), (5, 12));
+        assert!(message.contains("'min_samples' of at least 2"), "{}", message);
+        let (line, _, message) = error_location(&SCENE.replace("samples_per_pixel = 2", "samples_per_pixel = 0"));
+        assert_eq!(line, 8);
+        assert!(message.contains("must be positive"), "{}", message);
+        let source = SCENE.replace("type = \"sphere\"\ncenter = [0.0, 0.0, -100.0]\nradius = 98.0", "type = \"cuboid\"\nmin = [0.0, 0.0, 0.0]\nmax = [1.0, 0.0, 1.0]");
+        let (line, column, message) = error_location(&source);
+        assert_eq!((line, column), (30, 7));
+        assert!(message.contains("'min' must be less than 'max'"), "{}", message);
+
+        // Undefined materials.
+        let e = parse(&SCENE.replace("material = \"metal\"", "material = \"gold\"")).err().unwrap();
+        assert_eq!(e.to_string(), "test.toml:31:12: undefined material 'gold'");
+    }
+
+    #[test]
+    fn test_analytic_surfaces() {
+        let ground = "type = \"sphere\"\ncenter = [0.0, 0.0, -100.0]\nradius = 98.0";
+        for object in [
+            "type = \"cylinder\"\nbase = [0.0, 0.0, -3.0]\ntop = [0.0, 0.0, -2.0]\nradius = 1.0\ncapped = false",
+            "type = \"cone\"\nbase = [0.0, 0.0, -3.0]\nbase_radius = 1.0\ntop = [0.0, 0.0, -2.0]",
+            "type = \"torus\"\ncenter = [0.0, 0.0, -3.0]\naxis = [0.0, 0.0, 1.0]\nmajor_radius = 2.0\nminor_radius = 0.5",
+            "type = \"quadric\"\ncoefficients = [1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 3.0]\nmin = [-inf, -inf, -inf]\nmax = [inf, inf, -2.0]"
+        ] {
+            let scene = parse(&SCENE.replace(ground, object)).unwrap();
+            assert_eq!((scene.objects.len(), scene.lights.len()), (2, 1));
+        }
+
+        let source = SCENE.replace(ground, "type = \"torus\"\ncenter = [0.0, 0.0, -3.0]\naxis = [0.0, 0.0, 1.0]\nmajor_radius = 0.5\nminor_radius = 2.0");
+        let (line, _, message) = error_location(&source);
+        assert_eq!(line, 31);
+        assert!(message.contains("less than 'major_radius'"), "{}", message);
+        let (line, _, message) = error_location(&SCENE.replace(ground, "type = \"cylinder\"\nbase = [0.0, 0.0, 0.0]\ntop = [0.0, 0.0, 0.0]\nradius = 1.0"));
+        assert_eq!(line, 30);
+        assert!(message.contains("'base' and 'top' must differ"), "{}", message);
+    }
+
+    #[test]
+    fn test_motion_blur() {
+        // The metal sphere moves below the ground while the shutter is open, so rays at different times see it at
+        // different heights.
+        let moving = "type = \"moving_sphere\"\ncenter0 = [0.0, 0.0, -100.0]\ncenter1 = [0.0, 0.0, -102.0]\nradius = 98.0";
+        let source = SCENE
+            .replace("type = \"sphere\"\ncenter = [0.0, 0.0, -100.0]\nradius = 98.0", moving)
+            .replace("samples_per_pixel = 2", "samples_per_pixel = 2\nshutter = [0.0, 1.0]");
+        let scene = parse(&source).unwrap();
+        let r = Ray::new(Vector4::new(0.0, 0.0, 0.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        assert_eq!(scene.objects.intersect(r.with_time(0.0), 1.6, f32::INFINITY).unwrap().t, 2.0);
+        assert_eq!(scene.objects.intersect(r.with_time(0.5), 1.6, f32::INFINITY).unwrap().t, 3.0);
+
+        let (line, _, message) = error_location(&source.replace("shutter = [0.0, 1.0]", "shutter = [1.0, 0.0]"));
+        assert_eq!(line, 9);
+        assert!(message.contains("'shutter' must not close before it opens"), "{}", message);
+        let (line, column, message) = error_location(&source.replace("radius = 98.0", "radius = 98.0\ntimes = [1.0, 0.0]"));
+        assert_eq!((line, column), (33, 9));
+        assert!(message.contains("reverse order"), "{}", message);
+    }
+
+    #[test]
+    fn test_media() {
+        // Fog around the light scatters most of the rays leaving it upwards through 1.4 of the medium.
+        let source = SCENE.replacen("[[objects]]", "[materials.fog]\ntype = \"henyey_greenstein\"\nalbedo = [0.9, 0.9, 0.9]\ng = 0.5\n\n[[objects]]\ntype = \"constant_medium\"\nboundary = { type = \"sphere\", center = [0.0, 0.0, 0.0], radius = 3.0 }\ndensity = 2.0\nmaterial = \"fog\"\n\n[[objects]]", 1);
+        let scene = parse(&source).unwrap();
+        assert_eq!((scene.objects.len(), scene.lights.len()), (3, 1));
+        let scattered = (0..1000)
+            .filter(|&i| {
+                let r = Ray::new(Vector4::new(0.0, 0.0, 1.6, 0.0), Vector4::new(0.0, 0.0, 1.0, 0.0)).with_time(i as f32);
+                scene.objects.intersect(r, 0.001, f32::INFINITY).is_some()
+            })
+            .count();
+        let expected = 1.0 - f32::exp(-2.0 * 1.4);
+        assert!((scattered as f32 / 1000.0 - expected).abs() < 0.03, "{}", scattered);
+
+        let (line, column, message) = error_location(&source.replace("g = 0.5", "g = 1.0"));
+        assert_eq!((line, column), (23, 5));
+        assert!(message.contains("'g' must lie strictly between -1 and 1"), "{}", message);
+        let (line, column, message) = error_location(&source.replace("density = 2.0", "density = 0.0"));
+        assert_eq!((line, column), (28, 11));
+        assert!(message.contains("'density' must be positive"), "{}", message);
+        let (line, column, message) = error_location(&source.replace("radius = 3.0 }", "radius = -3.0 }"));
+        assert_eq!((line, column), (27, 66));
+        assert!(message.contains("'radius' must be positive"), "{}", message);
+    }
+
+    #[test]
+    fn test_obj_paths_are_relative_to_the_scene() {
+        let source = SCENE.replace(
+            "type = \"sphere\"\ncenter = [0.0, 0.0, -100.0]\nradius = 98.0",
+            "type = \"obj\"\npath = \"obj/cube.obj\""
+        );
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/scene.toml");
+        let scene = Scene::<Pcg64Mcg>::parse(&source, &path).unwrap();
+        assert!(scene.objects.len() > 1);
+
+        let e = Scene::<Pcg64Mcg>::parse(&source, "scene.toml").err().unwrap();
+        assert_eq!((e.line, e.column), (29, 8));
+        assert!(matches!(e.kind, SceneErrorKind::Obj(_)));
+    }
+
+    #[test]
+    fn test_obj_transforms() {
+        // Two instances of the same cube, the second scaled and moved below the first, share its meshes.
+        let cube = "type = \"obj\"\npath = \"obj/cube.obj\"\nmaterial = \"metal\"";
+        let source = SCENE.replace(
+            "type = \"sphere\"\ncenter = [0.0, 0.0, -100.0]\nradius = 98.0\nmaterial = \"metal\"",
+            &format!("{cube}\n\n[[objects]]\n{cube}\ntransform = [{{ scale = [2.0, 2.0, 2.0] }}, {{ translate = [0.0, 0.0, -5.0] }}]")
+        );
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/scene.toml");
+        let scene = Scene::<Pcg64Mcg>::parse(&source, &path).unwrap();
+        let meshes = (scene.objects.len() - 1) / 2;
+        assert!(meshes > 0);
+        assert_eq!(scene.objects.len(), 2 * meshes + 1);
+
+        // The cube has a side of 2 and is centred at the origin, so the bottom of the second one is at z = -7.
+        let r = Ray::new(Vector4::new(0.0, 0.0, -10.0, 0.0), Vector4::new(0.0, 0.0, 1.0, 0.0));
+        let hit = scene.objects.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.p.z() + 7.0).abs() < 1e-5 && hit.front_face);
+        assert_eq!(hit.normal, Vector4::new(0.0, 0.0, -1.0, 0.0));
+
+        let transformed = source.replace("translate = [0.0, 0.0, -5.0]", "scale = [1.0, 0.0, 1.0]");
+        let (line, column, message) = error_location_at(&transformed, &path);
+        assert_eq!((line, column), (36, 13));
+        assert!(message.contains("'transform' must be invertible"), "{}", message);
+        let rotated = source.replace("translate = [0.0, 0.0, -5.0]", "rotate = { axis = [0.0, 0.0, 0.0], angle = 90.0 }");
+        let (line, column, message) = error_location_at(&rotated, &path);
+        assert_eq!((line, column), (36, 63));
+        assert!(message.contains("'axis' must not be zero"), "{}", message);
+        let (_, _, message) = error_location_at(&source.replace("translate = [0.0, 0.0, -5.0] }]", "translate = [0.0, 0.0, -5.0] }]\nlight = true"), &path);
+        assert!(message.contains("can not be lights"), "{}", message);
+    }
+
+    #[test]
+    fn test_example_scenes_load() {
+        let scene = Scene::<Pcg64Mcg>::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/spheres.toml")).unwrap();
+        assert_eq!(scene.objects.len(), 4);
+        let scene = Scene::<Pcg64Mcg>::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/cornell_box.toml")).unwrap();
+        assert_eq!((scene.objects.len(), scene.lights.len()), (9, 1));
+        let scene = Scene::<Pcg64Mcg>::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/cornell_smoke.toml")).unwrap();
+        assert_eq!((scene.objects.len(), scene.lights.len()), (8, 1));
+        let scene = Scene::<Pcg64Mcg>::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/shapes.toml")).unwrap();
+        assert_eq!(scene.objects.len(), 5);
+
+        let e = Scene::<Pcg64Mcg>::load("missing.toml").err().unwrap();
+        assert!(matches!(e.kind, SceneErrorKind::Io(_)));
+        assert_eq!(e.line, 0);
+    }
+}
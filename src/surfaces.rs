@@ -1 +1,35 @@
-pub mod sphere;
\ No newline at end of file
+/// Circular cone or truncated cone described by the centres and radii of its ends, optionally closed by disks.
+pub mod cone;
+
+/// Axis-aligned box made of six quads.
+pub mod cuboid;
+
+/// Circular cylinder described by the centres of its ends and its radius, optionally closed by disks.
+pub mod cylinder;
+
+/// Disk described by its centre, normal and radius.
+pub mod disk;
+
+/// Sphere moving in a straight line between two positions over an interval of time, for motion blur.
+pub mod moving_sphere;
+
+/// Infinite plane, which is not bounded and so is kept out of bounding volume hierarchies.
+pub mod plane;
+
+/// Parallelogram described by a corner and two edge vectors.
+pub mod quad;
+
+/// General quadric surface given by the coefficients of its equation and clipped to a box.
+pub mod quadric;
+
+/// Sphere described by its centre and radius.
+pub mod sphere;
+
+/// Torus described by its centre, axis and the radii of its ring and tube, intersected by solving a quartic.
+pub mod torus;
+
+/// Single triangle and the watertight ray-triangle intersection test shared with meshes.
+pub mod triangle;
+
+/// Indexed triangle mesh with optional per-vertex normals and surface coordinates.
+pub mod triangle_mesh;
\ No newline at end of file
@@ -0,0 +1,202 @@
+use crate::{
+    aabb::{Aabb, Bounded},
+    frame::{Frame, azimuth},
+    intersectable::{HitRecord, Intersectable},
+    orientable::Orientable,
+    materials::{Material, Tangible},
+    ray::Ray,
+    roots::solve_quadratic,
+    surfaces::disk::disk_bounding_box,
+    vector4::Vector4
+};
+use rand::Rng;
+use std::sync::Arc;
+
+/// Circular cone around the axis from `base` to `top` whose radius changes linearly from `base_radius` to `top_radius`.
+/// If either radius is zero, the cone comes to a point there, otherwise it is truncated, and the ends with a positive
+/// radius may be closed by disks. The outward-facing side is the one facing away from the axis.
+#[derive(Clone)]
+pub struct Cone<R: Rng + ?Sized> {
+    pub base: Vector4,
+    pub base_radius: f32,
+    pub top: Vector4,
+    pub top_radius: f32,
+    pub capped: bool,
+    frame: Frame,           // Frame at the centre of the base whose z axis points to the top.
+    height: f32,
+    slope: f32,             // Change of the radius per unit of height.
+    material: Arc<dyn Material<R> + Send + Sync>
+}
+
+impl<R: Rng + ?Sized> Cone<R> {
+    pub fn new(
+        base: Vector4,
+        base_radius: f32,
+        top: Vector4,
+        top_radius: f32,
+        capped: bool,
+        material: Arc<dyn Material<R> + Send + Sync>
+    ) -> Self {
+        let height = (top - base).norm();
+        let slope = (top_radius - base_radius) / height;
+        Self { base, base_radius, top, top_radius, capped, frame: Frame::new(base, top - base), height, slope, material }
+    }
+
+    /// Returns the radius of the cone at the height `z` above the base.
+    fn radius_at(&self, z: f32) -> f32 {
+        self.base_radius + self.slope * z
+    }
+
+    /// Returns the normal of the side at the point `p` in the local frame, i.e. the normalised gradient of
+    /// `x^2 + y^2 - radius_at(z)^2`. At the apex, where the gradient vanishes, the normal points along the axis, away from
+    /// the cone.
+    fn side_normal(&self, p: Vector4) -> Vector4 {
+        let n = Vector4::new(p.x(), p.y(), -self.slope * self.radius_at(p.z()), 0.0);
+        if n.norm2() == 0.0 {
+            Vector4::new(0.0, 0.0, -self.slope.signum(), 0.0)
+        } else {
+            n.normalize()
+        }
+    }
+
+    /// Returns the parameter `t` of the first point at which `r` meets the cone within `[t_min, t_max]`, with the normal in
+    /// the local frame and the surface coordinates there. On the side, these are the azimuth and the height scaled to
+    /// `[0, 1]`, and on the caps the azimuth and the distance from the axis scaled to `[0, 1]`.
+    fn hit(&self, r: Ray, t_min: f32, t_max: f32) -> Option<(f32, Vector4, (f32, f32))> {
+        let r = self.frame.ray_to_local(r);
+        let (o, d) = (r.origin, r.direction);
+        let (ox, oy, oz) = (o.x() as f64, o.y() as f64, o.z() as f64);
+        let (dx, dy, dz) = (d.x() as f64, d.y() as f64, d.z() as f64);
+        // Points on the ray satisfy x^2 + y^2 = (r_0 + k z)^2, of which only those with 0 <= z <= h are on the cone rather
+        // than on its mirror image through the apex.
+        let k = self.slope as f64;
+        let radius = self.base_radius as f64 + k * oz;
+        let roots = solve_quadratic(
+            dx * dx + dy * dy - k * k * dz * dz,
+            2.0 * (ox * dx + oy * dy - k * radius * dz),
+            ox * ox + oy * oy - radius * radius
+        );
+        let side = roots.iter().filter_map(|&t| {
+            let p = r.at(t as f32);
+            (0.0..=self.height).contains(&p.z()).then(|| (t as f32, self.side_normal(p), (azimuth(p), p.z() / self.height)))
+        });
+        let caps = [(0.0, self.base_radius, -1.0), (self.height, self.top_radius, 1.0)].into_iter()
+            .filter(|&(_, radius, _)| self.capped && radius > 0.0 && d.z() != 0.0)
+            .filter_map(|(z, radius, nz)| {
+                let t = (z - o.z()) / d.z();
+                let p = r.at(t);
+                let rho = f32::sqrt(p.x() * p.x() + p.y() * p.y());
+                (rho <= radius).then(|| (t, Vector4::new(0.0, 0.0, nz, 0.0), (azimuth(p), rho / radius)))
+            });
+        side.chain(caps)
+            .filter(|&(t, _, _)| t >= t_min && t_max >= t)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+}
+
+impl<R: Rng + ?Sized> Intersectable<R> for Cone<R> {
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        let (t, n, uv) = self.hit(r, t_min, t_max)?;
+        Some(HitRecord::new(r, t, self.frame.vector_to_world(n), uv, self))
+    }
+}
+
+impl<R: Rng + ?Sized> Bounded for Cone<R> {
+    fn bounding_box(&self) -> Aabb {
+        disk_bounding_box(self.base, self.frame.w, self.base_radius)
+            .union(disk_bounding_box(self.top, self.frame.w, self.top_radius))
+    }
+}
+
+impl<R: Rng + ?Sized> Orientable for Cone<R> {
+    /// Returns the normal of the side or, if the cone is capped, of the cap nearest to `p`.
+    fn normal(&self, p: Vector4) -> Vector4 {
+        let q = self.frame.to_local(p);
+        let rho = f32::sqrt(q.x() * q.x() + q.y() * q.y());
+        let side_distance = ((rho - self.radius_at(q.z())) / f32::sqrt(1.0 + self.slope * self.slope)).abs();
+        let cap_distance = |z: f32, radius: f32| if self.capped && radius > 0.0 { (q.z() - z).abs() } else { f32::INFINITY };
+        let (base_distance, top_distance) = (cap_distance(0.0, self.base_radius), cap_distance(self.height, self.top_radius));
+        let n = if side_distance <= f32::min(base_distance, top_distance) {
+            self.side_normal(q)
+        } else if base_distance < top_distance {
+            Vector4::new(0.0, 0.0, -1.0, 0.0)
+        } else {
+            Vector4::new(0.0, 0.0, 1.0, 0.0)
+        };
+        self.frame.vector_to_world(n)
+    }
+}
+
+impl<R: Rng + ?Sized> Tangible<R> for Cone<R> {
+    fn material(&self) -> &Arc<dyn Material<R> + Send + Sync> {
+        &self.material
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian::Lambertian;
+    use rand_pcg::Pcg64Mcg;
+
+    fn cone(top_radius: f32, capped: bool) -> Cone<Pcg64Mcg> {
+        // Base of radius 2 at the origin, top at z = 2.
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        Cone::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 2.0, Vector4::new(0.0, 0.0, 2.0, 0.0), top_radius, capped, material)
+    }
+
+    #[test]
+    fn test_hit_record() {
+        let cone = cone(0.0, true);
+        // The side, at z = 1 where the radius is 1, and its normal at 45 degrees.
+        let r = Ray::new(Vector4::new(5.0, 0.0, 1.0, 0.0), Vector4::new(-1.0, 0.0, 0.0, 0.0));
+        let hit = cone.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-6);
+        let expected = Vector4::new(1.0, 0.0, 1.0, 0.0).normalize();
+        assert!((hit.normal - expected).norm() < 1e-6);
+        assert!(hit.front_face);
+        assert!((hit.v - 0.5).abs() < 1e-6);
+        assert!((cone.normal(hit.p) - expected).norm() < 1e-6);
+
+        // The apex, and the base from below.
+        let r = Ray::new(Vector4::new(0.0, 0.0, 5.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        let hit = cone.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!((hit.t, hit.normal), (3.0, Vector4::new(0.0, 0.0, 1.0, 0.0)));
+        let r = Ray::new(Vector4::new(0.5, 0.5, -5.0, 0.0), Vector4::new(0.0, 0.0, 1.0, 0.0));
+        let hit = cone.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!((hit.t, hit.normal), (5.0, Vector4::new(0.0, 0.0, -1.0, 0.0)));
+        assert!(hit.front_face);
+
+        // The mirror image of the cone above its apex is not part of it.
+        let r = Ray::new(Vector4::new(5.0, 0.0, 3.0, 0.0), Vector4::new(-1.0, 0.0, 0.0, 0.0));
+        assert!(cone.intersect(r, 0.001, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_parallel_to_side() {
+        // A ray parallel to one side makes the quadratic degenerate. Entering through the open base, it leaves through the
+        // opposite side.
+        let cone = cone(0.0, false);
+        let r = Ray::new(Vector4::new(-2.0, 0.0, -1.0, 0.0), Vector4::new(1.0, 0.0, 1.0, 0.0));
+        let hit = cone.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.t - 2.5).abs() < 1e-6);
+        assert!((hit.p - Vector4::new(0.5, 0.0, 1.5, 0.0)).norm() < 1e-6);
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let cone = cone(1.0, true);
+        // The top cap, and the side just below it.
+        let r = Ray::new(Vector4::new(0.9, 0.0, 5.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        let hit = cone.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!((hit.t, hit.normal, hit.v), (3.0, Vector4::new(0.0, 0.0, 1.0, 0.0), 0.9));
+        let r = Ray::new(Vector4::new(1.5, 0.0, 5.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        let hit = cone.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.p.z() - 1.0).abs() < 1e-6);
+        assert!((hit.normal - Vector4::new(2.0, 0.0, 1.0, 0.0).normalize()).norm() < 1e-6);
+
+        let b = cone.bounding_box();
+        assert_eq!((b.min, b.max), (Vector4::new(-2.0, -2.0, 0.0, 0.0), Vector4::new(2.0, 2.0, 2.0, 0.0)));
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{
+    aabb::{Aabb, Bounded},
+    intersectable::{HitRecord, Intersectable},
+    orientable::Orientable,
+    materials::{Material, Tangible},
+    ray::Ray,
+    sampleable::{Sampleable, SurfaceSample, area_to_solid_angle_pdf},
+    surfaces::quad::Quad,
+    vector4::Vector4
+};
+use rand::Rng;
+use std::sync::Arc;
+
+/// Axis-aligned box made of six quads, whose outward-facing sides face away from the box.
+#[derive(Clone)]
+pub struct Cuboid<R: Rng + ?Sized> {
+    pub min: Vector4,
+    pub max: Vector4,
+    faces: [Quad<R>; 6],    // Faces at the minimal and maximal x, y and z in this order.
+    area: f32,
+    material: Arc<dyn Material<R> + Send + Sync>
+}
+
+impl<R: Rng + ?Sized> Cuboid<R> {
+    /// Constructs the box with opposite corners `a` and `b`.
+    pub fn new(
+        a: Vector4,
+        b: Vector4,
+        material: Arc<dyn Material<R> + Send + Sync>
+    ) -> Self {
+        let (min, max) = (a.min(b), a.max(b));
+        let d = max - min;
+        let dx = Vector4::new(d.x(), 0.0, 0.0, 0.0);
+        let dy = Vector4::new(0.0, d.y(), 0.0, 0.0);
+        let dz = Vector4::new(0.0, 0.0, d.z(), 0.0);
+        // The edge vectors of each face are ordered so that their cross product points out of the box.
+        let faces = [
+            Quad::new(min, dz, dy, material.clone()),
+            Quad::new(min + dx, dy, dz, material.clone()),
+            Quad::new(min, dx, dz, material.clone()),
+            Quad::new(min + dy, dz, dx, material.clone()),
+            Quad::new(min, dy, dx, material.clone()),
+            Quad::new(min + dz, dx, dy, material.clone())
+        ];
+        let area = 2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x());
+        Self { min, max, faces, area, material }
+    }
+
+    /// Returns the face first hit by `r` within `[t_min, t_max]`, with the parameter and surface coordinates of the hit.
+    fn hit(&self, r: Ray, t_min: f32, t_max: f32) -> Option<(&Quad<R>, f32, (f32, f32))> {
+        let mut closest = None;
+        let mut t_max = t_max;
+        for face in &self.faces {
+            if let Some((t, uv)) = face.intersect_uv(r, t_min, t_max) {
+                t_max = t;
+                closest = Some((face, t, uv));
+            }
+        }
+        closest
+    }
+}
+
+impl<R: Rng + ?Sized> Intersectable<R> for Cuboid<R> {
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        // The record refers to the box rather than the face, so that the box is recognised when it is used as a light.
+        let (face, t, uv) = self.hit(r, t_min, t_max)?;
+        Some(HitRecord::new(r, t, face.normal(r.at(t)), uv, self))
+    }
+}
+
+impl<R: Rng + ?Sized> Bounded for Cuboid<R> {
+    fn bounding_box(&self) -> Aabb {
+        Aabb::new(self.min, self.max)
+    }
+}
+
+impl<R: Rng + ?Sized> Orientable for Cuboid<R> {
+    /// Returns the normal of the face nearest to `p`.
+    fn normal(&self, p: Vector4) -> Vector4 {
+        let (mut face, mut distance) = (0, f32::INFINITY);
+        for axis in 0..3 {
+            for (i, bound) in [(2 * axis, self.min[axis]), (2 * axis + 1, self.max[axis])] {
+                if (p[axis] - bound).abs() < distance {
+                    (face, distance) = (i, (p[axis] - bound).abs());
+                }
+            }
+        }
+        self.faces[face].normal(p)
+    }
+}
+
+impl<R: Rng + ?Sized> Tangible<R> for Cuboid<R> {
+    fn material(&self) -> &Arc<dyn Material<R> + Send + Sync> {
+        &self.material
+    }
+}
+
+impl<R: Rng + ?Sized> Sampleable<R> for Cuboid<R> {
+    /// Samples the surface uniformly by area. Points on faces turned away from `origin` are hidden by the box itself, and
+    /// are returned with the density of the visible point in their direction, so that they only cost a shadow ray.
+    fn sample(&self, rng: &mut R, origin: Vector4) -> Option<SurfaceSample> {
+        let mut s = rng.random::<f32>() * self.area;
+        let face = self.faces.iter().find(|face| {
+            s -= face.area();
+            s < 0.0
+        }).unwrap_or(&self.faces[5]);
+        let sample = face.sample(rng, origin)?;
+        let pdf = area_to_solid_angle_pdf(self.area.recip(), origin, sample.p, sample.normal)?;
+        Some(SurfaceSample { pdf, ..sample })
+    }
+
+    fn pdf(&self, origin: Vector4, direction: Vector4) -> f32 {
+        self.hit(Ray::new(origin, direction), 0.0, f32::INFINITY)
+            .and_then(|(face, t, _)| {
+                let p = origin + t * direction;
+                area_to_solid_angle_pdf(self.area.recip(), origin, p, face.normal(p))
+            })
+            .unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian::Lambertian;
+    use rand_pcg::Pcg64Mcg;
+
+    fn cuboid() -> Cuboid<Pcg64Mcg> {
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        Cuboid::new(Vector4::new(1.0, 2.0, 4.0, 0.0), Vector4::new(-1.0, -2.0, 0.0, 0.0), material)
+    }
+
+    #[test]
+    fn test_outward_normals() {
+        let cuboid = cuboid();
+        let center = Vector4::new(0.0, 0.0, 2.0, 0.0);
+        let axes = [Vector4::new(1.0, 0.0, 0.0, 0.0), Vector4::new(0.0, 1.0, 0.0, 0.0), Vector4::new(0.0, 0.0, 1.0, 0.0)];
+        for axis in axes {
+            for direction in [axis, -axis] {
+                // Hit from outside, along the axis towards the centre.
+                let r = Ray::new(center + 10.0 * direction, -direction);
+                let hit = cuboid.intersect(r, 0.001, f32::INFINITY).unwrap();
+                assert_eq!(hit.normal, direction);
+                assert!(hit.front_face);
+                assert_eq!(cuboid.normal(hit.p), direction);
+
+                // Hit from inside, the normal still faces outwards.
+                let hit = cuboid.intersect(Ray::new(center, direction), 0.001, f32::INFINITY).unwrap();
+                assert_eq!(hit.normal, direction);
+                assert!(!hit.front_face);
+            }
+        }
+        assert_eq!(cuboid.bounding_box(), Aabb::new(Vector4::new(-1.0, -2.0, 0.0, 0.0), Vector4::new(1.0, 2.0, 4.0, 0.0)));
+    }
+
+    #[test]
+    fn test_edges_and_grazing_rays() {
+        let cuboid = cuboid();
+        // Diagonally at an edge and at a corner.
+        let r = Ray::new(Vector4::new(3.0, 4.0, 2.0, 0.0), Vector4::new(-1.0, -1.0, 0.0, 0.0));
+        assert_eq!(cuboid.intersect(r, 0.0, f32::INFINITY).unwrap().p, Vector4::new(1.0, 2.0, 2.0, 0.0));
+        let r = Ray::new(Vector4::new(2.0, 3.0, 5.0, 0.0), Vector4::new(-1.0, -1.0, -1.0, 0.0));
+        assert_eq!(cuboid.intersect(r, 0.0, f32::INFINITY).unwrap().t, 1.0);
+        // Sliding along the plane of a face, the ray only hits the edge of the face it runs into, and misses just beyond.
+        let r = Ray::new(Vector4::new(-5.0, 2.0, 2.0, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0));
+        let hit = cuboid.intersect(r, 0.0, f32::INFINITY).unwrap();
+        assert_eq!((hit.t, hit.normal), (4.0, Vector4::new(-1.0, 0.0, 0.0, 0.0)));
+        let r = Ray::new(Vector4::new(-5.0, 2.001, 2.0, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0));
+        assert!(cuboid.intersect(r, 0.0, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_sample() {
+        // Seen from this corner, the visible faces make up half of the surface, and the density of the samples on them
+        // matches pdf.
+        let cuboid = cuboid();
+        let origin = Vector4::new(3.0, -5.0, 7.0, 0.0);
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        const SAMPLE_COUNT: usize = 100000;
+        let mut visible_count = 0;
+        for _ in 0..SAMPLE_COUNT {
+            let sample = cuboid.sample(&mut rng, origin).unwrap();
+            let direction = (sample.p - origin).normalize();
+            let hit = cuboid.intersect(Ray::new(origin, direction), 0.0, f32::INFINITY).unwrap();
+            if hit.normal == sample.normal && (hit.p - sample.p).norm() < 1e-4 {
+                visible_count += 1;
+                assert!((cuboid.pdf(origin, direction) / sample.pdf - 1.0).abs() < 1e-3);
+            }
+        }
+        assert!((visible_count as f32 / SAMPLE_COUNT as f32 - 0.5).abs() < 0.1);
+        assert_eq!(cuboid.pdf(origin, Vector4::new(1.0, 0.0, 0.0, 0.0)), 0.0);
+    }
+}
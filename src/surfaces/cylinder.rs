@@ -0,0 +1,165 @@
+use crate::{
+    aabb::{Aabb, Bounded},
+    frame::{Frame, azimuth},
+    intersectable::{HitRecord, Intersectable},
+    orientable::Orientable,
+    materials::{Material, Tangible},
+    ray::Ray,
+    roots::solve_quadratic,
+    surfaces::disk::disk_bounding_box,
+    vector4::Vector4
+};
+use rand::Rng;
+use std::sync::Arc;
+
+/// Circular cylinder around the axis from `base` to `top`, optionally closed by disks at both ends. The outward-facing side
+/// is the one facing away from the axis.
+#[derive(Clone)]
+pub struct Cylinder<R: Rng + ?Sized> {
+    pub base: Vector4,
+    pub top: Vector4,
+    pub radius: f32,
+    pub capped: bool,
+    frame: Frame,           // Frame at the centre of the base whose z axis points to the top.
+    height: f32,
+    material: Arc<dyn Material<R> + Send + Sync>
+}
+
+impl<R: Rng + ?Sized> Cylinder<R> {
+    pub fn new(
+        base: Vector4,
+        top: Vector4,
+        radius: f32,
+        capped: bool,
+        material: Arc<dyn Material<R> + Send + Sync>
+    ) -> Self {
+        let height = (top - base).norm();
+        Self { base, top, radius, capped, frame: Frame::new(base, top - base), height, material }
+    }
+
+    /// Returns the parameter `t` of the first point at which `r` meets the cylinder within `[t_min, t_max]`, with the
+    /// normal in the local frame and the surface coordinates there. On the side, these are the azimuth and the height
+    /// scaled to `[0, 1]`, and on the caps the azimuth and the distance from the axis scaled to `[0, 1]`.
+    fn hit(&self, r: Ray, t_min: f32, t_max: f32) -> Option<(f32, Vector4, (f32, f32))> {
+        let r = self.frame.ray_to_local(r);
+        let (o, d) = (r.origin, r.direction);
+        let (ox, oy, dx, dy) = (o.x() as f64, o.y() as f64, d.x() as f64, d.y() as f64);
+        let roots = solve_quadratic(dx * dx + dy * dy, 2.0 * (ox * dx + oy * dy), ox * ox + oy * oy - (self.radius as f64).powi(2));
+        let side = roots.iter().filter_map(|&t| {
+            let p = r.at(t as f32);
+            (0.0..=self.height).contains(&p.z())
+                .then(|| (t as f32, Vector4::new(p.x(), p.y(), 0.0, 0.0).normalize(), (azimuth(p), p.z() / self.height)))
+        });
+        let caps = [(0.0, -1.0), (self.height, 1.0)].into_iter()
+            .filter(|_| self.capped && d.z() != 0.0)
+            .filter_map(|(z, nz)| {
+                let t = (z - o.z()) / d.z();
+                let p = r.at(t);
+                let rho = f32::sqrt(p.x() * p.x() + p.y() * p.y());
+                (rho <= self.radius).then(|| (t, Vector4::new(0.0, 0.0, nz, 0.0), (azimuth(p), rho / self.radius)))
+            });
+        side.chain(caps)
+            .filter(|&(t, _, _)| t >= t_min && t_max >= t)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+}
+
+impl<R: Rng + ?Sized> Intersectable<R> for Cylinder<R> {
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        let (t, n, uv) = self.hit(r, t_min, t_max)?;
+        Some(HitRecord::new(r, t, self.frame.vector_to_world(n), uv, self))
+    }
+}
+
+impl<R: Rng + ?Sized> Bounded for Cylinder<R> {
+    fn bounding_box(&self) -> Aabb {
+        disk_bounding_box(self.base, self.frame.w, self.radius).union(disk_bounding_box(self.top, self.frame.w, self.radius))
+    }
+}
+
+impl<R: Rng + ?Sized> Orientable for Cylinder<R> {
+    /// Returns the normal of the side or, if the cylinder is capped, of the cap nearest to `p`.
+    fn normal(&self, p: Vector4) -> Vector4 {
+        let q = self.frame.to_local(p);
+        let side_distance = (f32::sqrt(q.x() * q.x() + q.y() * q.y()) - self.radius).abs();
+        let n = if !self.capped || side_distance <= f32::min(q.z().abs(), (q.z() - self.height).abs()) {
+            Vector4::new(q.x(), q.y(), 0.0, 0.0).normalize()
+        } else if q.z() < self.height / 2.0 {
+            Vector4::new(0.0, 0.0, -1.0, 0.0)
+        } else {
+            Vector4::new(0.0, 0.0, 1.0, 0.0)
+        };
+        self.frame.vector_to_world(n)
+    }
+}
+
+impl<R: Rng + ?Sized> Tangible<R> for Cylinder<R> {
+    fn material(&self) -> &Arc<dyn Material<R> + Send + Sync> {
+        &self.material
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian::Lambertian;
+    use rand_pcg::Pcg64Mcg;
+
+    fn cylinder(capped: bool) -> Cylinder<Pcg64Mcg> {
+        // Radius 1 around the x axis, from x = -1 to x = 3.
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        Cylinder::new(Vector4::new(-1.0, 0.0, 0.0, 0.0), Vector4::new(3.0, 0.0, 0.0, 0.0), 1.0, capped, material)
+    }
+
+    #[test]
+    fn test_hit_record() {
+        let cylinder = cylinder(true);
+        // The side, from outside and from inside.
+        let r = Ray::new(Vector4::new(1.0, 0.0, 5.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        let hit = cylinder.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!(hit.t, 4.0);
+        assert!((hit.normal - Vector4::new(0.0, 0.0, 1.0, 0.0)).norm() < 1e-6);
+        assert!(hit.front_face);
+        assert_eq!(hit.v, 0.5);
+        assert!((cylinder.normal(hit.p) - hit.normal).norm() < 1e-6);
+        let r = Ray::new(Vector4::new(1.0, 0.0, 0.0, 0.0), Vector4::new(0.0, -2.0, 0.0, 0.0));
+        let hit = cylinder.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!(hit.t, 0.5);
+        assert!(!hit.front_face);
+
+        // The caps, along the axis and obliquely.
+        let r = Ray::new(Vector4::new(5.0, 0.5, 0.0, 0.0), Vector4::new(-1.0, 0.0, 0.0, 0.0));
+        let hit = cylinder.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!((hit.t, hit.normal, hit.v), (2.0, Vector4::new(1.0, 0.0, 0.0, 0.0), 0.5));
+        assert_eq!(cylinder.normal(hit.p), hit.normal);
+        let r = Ray::new(Vector4::new(-2.0, 0.0, 0.0, 0.0), Vector4::new(1.0, 0.0, 0.5, 0.0));
+        let hit = cylinder.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!((hit.t, hit.normal), (1.0, Vector4::new(-1.0, 0.0, 0.0, 0.0)));
+
+        // Misses beside the cylinder and beyond its ends.
+        let r = Ray::new(Vector4::new(1.0, 1.5, 5.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        assert!(cylinder.intersect(r, 0.001, f32::INFINITY).is_none());
+        let r = Ray::new(Vector4::new(3.5, 0.0, 5.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        assert!(cylinder.intersect(r, 0.001, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_uncapped() {
+        // Along the axis, the open tube is missed, and obliquely through an open end, the inside of the side is hit.
+        let cylinder = cylinder(false);
+        let r = Ray::new(Vector4::new(5.0, 0.5, 0.0, 0.0), Vector4::new(-1.0, 0.0, 0.0, 0.0));
+        assert!(cylinder.intersect(r, 0.001, f32::INFINITY).is_none());
+        let r = Ray::new(Vector4::new(-2.0, 0.0, 0.0, 0.0), Vector4::new(1.0, 0.0, 0.5, 0.0));
+        let hit = cylinder.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-6);
+        assert!((hit.normal - Vector4::new(0.0, 0.0, 1.0, 0.0)).norm() < 1e-6);
+        assert!(!hit.front_face);
+        assert!((cylinder.normal(Vector4::new(3.0, 0.0, 0.9, 0.0)) - Vector4::new(0.0, 0.0, 1.0, 0.0)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let b = cylinder(true).bounding_box();
+        assert_eq!((b.min, b.max), (Vector4::new(-1.0, -1.0, -1.0, 0.0), Vector4::new(3.0, 1.0, 1.0, 0.0)));
+    }
+}
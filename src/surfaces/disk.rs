@@ -0,0 +1,160 @@
+use crate::{
+    aabb::{Aabb, Bounded},
+    intersectable::{HitRecord, Intersectable},
+    orientable::Orientable,
+    materials::{Material, Tangible},
+    random::sample_unit_disk_uniform,
+    ray::Ray,
+    sampleable::{Sampleable, SurfaceSample, area_to_solid_angle_pdf},
+    surfaces::plane::intersect_plane,
+    vector4::Vector4
+};
+use rand::Rng;
+use std::{
+    f32::consts::PI,
+    sync::Arc
+};
+
+/// Disk described by its centre, radius and the normal of its outward-facing side.
+#[derive(Clone)]
+pub struct Disk<R: Rng + ?Sized> {
+    pub center: Vector4,
+    pub radius: f32,
+    normal: Vector4,
+    u: Vector4,             // Unit vectors spanning the plane of the disk, u being the direction of zero azimuth.
+    v: Vector4,
+    material: Arc<dyn Material<R> + Send + Sync>
+}
+
+impl<R: Rng + ?Sized> Disk<R> {
+    /// Constructs a disk facing `normal`, which need not be of unit length.
+    pub fn new(
+        center: Vector4,
+        normal: Vector4,
+        radius: f32,
+        material: Arc<dyn Material<R> + Send + Sync>
+    ) -> Self {
+        let normal = normal.normalize();
+        let (u, v) = normal.orthonormal_basis();
+        Self { center, radius, normal, u, v, material }
+    }
+
+    /// Returns the surface coordinates of `p`, a point on the disk: the azimuth about the normal and the distance from the
+    /// centre, scaled to `[0, 1]`.
+    fn uv(&self, p: Vector4) -> (f32, f32) {
+        let d = p - self.center;
+        let phi = f32::atan2(self.v.dot(d), self.u.dot(d));
+        let phi = if phi < 0.0 { phi + 2.0 * PI } else { phi };
+        (phi / (2.0 * PI), f32::min(d.norm() / self.radius, 1.0))
+    }
+
+    fn hit(&self, r: Ray, t_min: f32, t_max: f32) -> Option<f32> {
+        let t = intersect_plane(r, self.center, self.normal, t_min, t_max)?;
+        ((r.at(t) - self.center).norm2() <= self.radius * self.radius).then_some(t)
+    }
+}
+
+impl<R: Rng + ?Sized> Intersectable<R> for Disk<R> {
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        let t = self.hit(r, t_min, t_max)?;
+        Some(HitRecord::new(r, t, self.normal, self.uv(r.at(t)), self))
+    }
+}
+
+impl<R: Rng + ?Sized> Bounded for Disk<R> {
+    fn bounding_box(&self) -> Aabb {
+        disk_bounding_box(self.center, self.normal, self.radius)
+    }
+}
+
+/// Returns the bounding box of the disk with the unit normal `normal`, which is also that of the circle bounding it.
+pub fn disk_bounding_box(center: Vector4, normal: Vector4, radius: f32) -> Aabb {
+    // Along each axis, the disk extends by the radius times the sine of the angle between the axis and the normal.
+    let extent = |axis: usize| radius * f32::sqrt(f32::max(0.0, 1.0 - normal[axis] * normal[axis]));
+    let e = Vector4::new(extent(0), extent(1), extent(2), 0.0);
+    Aabb::new(center - e, center + e)
+}
+
+impl<R: Rng + ?Sized> Orientable for Disk<R> {
+    fn normal(&self, _p: Vector4) -> Vector4 {
+        self.normal
+    }
+}
+
+impl<R: Rng + ?Sized> Tangible<R> for Disk<R> {
+    fn material(&self) -> &Arc<dyn Material<R> + Send + Sync> {
+        &self.material
+    }
+}
+
+impl<R: Rng + ?Sized> Sampleable<R> for Disk<R> {
+    fn sample(&self, rng: &mut R, origin: Vector4) -> Option<SurfaceSample> {
+        let d = sample_unit_disk_uniform(rng);
+        let p = self.center + self.radius * (d.x() * self.u + d.y() * self.v);
+        let pdf = area_to_solid_angle_pdf((PI * self.radius * self.radius).recip(), origin, p, self.normal)?;
+        Some(SurfaceSample { p, normal: self.normal, uv: self.uv(p), pdf })
+    }
+
+    fn pdf(&self, origin: Vector4, direction: Vector4) -> f32 {
+        self.hit(Ray::new(origin, direction), 0.0, f32::INFINITY)
+            .and_then(|t| area_to_solid_angle_pdf((PI * self.radius * self.radius).recip(), origin, origin + t * direction, self.normal))
+            .unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian::Lambertian;
+    use rand_pcg::Pcg64Mcg;
+
+    fn disk() -> Disk<Pcg64Mcg> {
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        Disk::new(Vector4::new(1.0, 0.0, 0.0, 0.0), Vector4::new(-3.0, 0.0, 0.0, 0.0), 2.0, material)
+    }
+
+    #[test]
+    fn test_hit_record() {
+        let disk = disk();
+        let r = Ray::new(Vector4::new(-1.0, 0.0, 0.0, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0));
+        let hit = disk.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!(hit.t, 2.0);
+        assert_eq!(hit.normal, Vector4::new(-1.0, 0.0, 0.0, 0.0));
+        assert!(hit.front_face);
+        assert_eq!(hit.v, 0.0);
+
+        // On the rim, just outside of it, and lying in the plane of the disk.
+        let r = Ray::new(Vector4::new(-1.0, 0.0, 2.0, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0));
+        let hit = disk.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!(hit.v, 1.0);
+        let r = Ray::new(Vector4::new(-1.0, 1.5, 1.5, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0));
+        assert!(disk.intersect(r, 0.001, f32::INFINITY).is_none());
+        let r = Ray::new(Vector4::new(1.0, -5.0, 0.0, 0.0), Vector4::new(0.0, 1.0, 0.0, 0.0));
+        assert!(disk.intersect(r, 0.001, f32::INFINITY).is_none());
+
+        // From behind.
+        let r = Ray::new(Vector4::new(3.0, 0.5, 0.5, 0.0), Vector4::new(-1.0, 0.0, 0.0, 0.0));
+        assert!(!disk.intersect(r, 0.001, f32::INFINITY).unwrap().front_face);
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let b = disk().bounding_box();
+        assert_eq!(b.min, Vector4::new(1.0, -2.0, -2.0, 0.0));
+        assert_eq!(b.max, Vector4::new(1.0, 2.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn test_sample() {
+        let disk = disk();
+        let origin = Vector4::new(-2.0, 0.5, 0.0, 0.0);
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        for _ in 0..1000 {
+            let sample = disk.sample(&mut rng, origin).unwrap();
+            assert_eq!(sample.p.x(), 1.0);
+            assert!((sample.p - disk.center).norm() <= 2.0 + 1e-6);
+            let pdf = disk.pdf(origin, (sample.p - origin).normalize());
+            assert!((pdf / sample.pdf - 1.0).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{
+    aabb::{Aabb, Bounded},
+    intersectable::{HitRecord, Intersectable},
+    materials::{Material, Tangible},
+    ray::Ray,
+    surfaces::sphere,
+    vector4::Vector4
+};
+use rand::Rng;
+use std::sync::Arc;
+
+/// Sphere whose centre moves in a straight line from `center0` at `time0` to `center1` at `time1`, at constant speed.
+/// Before `time0` and after `time1`, the sphere rests at the ends of its path.
+#[derive(Clone)]
+pub struct MovingSphere<R: Rng + ?Sized> {
+    pub center0: Vector4,
+    pub center1: Vector4,
+    pub time0: f32,
+    pub time1: f32,
+    pub radius: f32,
+    material: Arc<dyn Material<R> + Send + Sync>
+}
+
+impl<R: Rng + ?Sized> MovingSphere<R> {
+    /// Panics if `time1` is before `time0`.
+    pub fn new(
+        center0: Vector4,
+        center1: Vector4,
+        time0: f32,
+        time1: f32,
+        radius: f32,
+        material: Arc<dyn Material<R> + Send + Sync>
+    ) -> Self {
+        assert!(time0 <= time1, "the motion of a sphere must not end before it starts");
+        Self { center0, center1, time0, time1, radius, material }
+    }
+
+    pub fn center(&self, time: f32) -> Vector4 {
+        if self.time1 > self.time0 {
+            let s = ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0);
+            self.center0 + s * (self.center1 - self.center0)
+        } else {
+            self.center0
+        }
+    }
+}
+
+impl<R: Rng + ?Sized> Intersectable<R> for MovingSphere<R> {
+    /// Intersects `r` with the sphere where it is at the time of `r`, with the surface coordinates of `Sphere`.
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        let center = self.center(r.time);
+        let t = sphere::hit(center, self.radius, r, t_min, t_max)?;
+        let n = (r.at(t) - center) / self.radius;
+        Some(HitRecord::new(r, t, n, sphere::uv(n), self))
+    }
+}
+
+impl<R: Rng + ?Sized> Bounded for MovingSphere<R> {
+    /// Returns the box around the sphere along its whole path.
+    fn bounding_box(&self) -> Aabb {
+        let r = Vector4::new(self.radius, self.radius, self.radius, 0.0);
+        Aabb::new(self.center0.min(self.center1) - r, self.center0.max(self.center1) + r)
+    }
+}
+
+impl<R: Rng + ?Sized> Tangible<R> for MovingSphere<R> {
+    fn material(&self) -> &Arc<dyn Material<R> + Send + Sync> {
+        &self.material
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bvh::Bvh,
+        materials::lambertian::Lambertian,
+        renderable_list::RenderableList,
+        surfaces::sphere::Sphere
+    };
+    use rand_pcg::Pcg64Mcg;
+
+    fn moving_sphere() -> MovingSphere<Pcg64Mcg> {
+        // From the origin at time 1 to (0, 0, 4) at time 3.
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        MovingSphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), Vector4::new(0.0, 0.0, 4.0, 0.0), 1.0, 3.0, 1.0, material)
+    }
+
+    #[test]
+    fn test_motion() {
+        let sphere = moving_sphere();
+        assert_eq!(sphere.center(2.0), Vector4::new(0.0, 0.0, 2.0, 0.0));
+        assert_eq!(sphere.center(-5.0), Vector4::new(0.0, 0.0, 0.0, 0.0));
+        assert_eq!(sphere.center(5.0), Vector4::new(0.0, 0.0, 4.0, 0.0));
+
+        // The same ray along the x axis at height 2 hits the sphere only while it passes.
+        let r = Ray::new(Vector4::new(-5.0, 0.0, 2.0, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0));
+        assert!(sphere.intersect(r, 0.001, f32::INFINITY).is_none());
+        assert!(sphere.intersect(r.with_time(1.0), 0.001, f32::INFINITY).is_none());
+        let hit = sphere.intersect(r.with_time(2.0), 0.001, f32::INFINITY).unwrap();
+        assert_eq!((hit.t, hit.normal), (4.0, Vector4::new(-1.0, 0.0, 0.0, 0.0)));
+        assert!(hit.front_face);
+        assert!(sphere.intersect(r.with_time(3.0), 0.001, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        // The box covers the whole path, so rays at any time find the sphere through a hierarchy.
+        let sphere = moving_sphere();
+        let b = sphere.bounding_box();
+        assert_eq!((b.min, b.max), (Vector4::new(-1.0, -1.0, -1.0, 0.0), Vector4::new(1.0, 1.0, 5.0, 0.0)));
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        let mut list = RenderableList::new();
+        list.push(Box::new(sphere));
+        list.push(Box::new(Sphere::new(Vector4::new(10.0, 0.0, 0.0, 0.0), 1.0, material)));
+        let bvh = Bvh::from(list);
+        for time in [1.0, 1.5, 2.0, 2.5, 3.0] {
+            let r = Ray::new(Vector4::new(0.0, -5.0, 2.0 * (time - 1.0), 0.0), Vector4::new(0.0, 1.0, 0.0, 0.0)).with_time(time);
+            assert_eq!(bvh.intersect(r, 0.001, f32::INFINITY).unwrap().t, 4.0);
+        }
+    }
+}
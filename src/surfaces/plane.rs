@@ -0,0 +1,157 @@
+use crate::{
+    aabb::{Aabb, Bounded},
+    intersectable::{HitRecord, Intersectable},
+    orientable::Orientable,
+    materials::{Material, Tangible},
+    ray::Ray,
+    vector4::Vector4
+};
+use rand::Rng;
+use std::sync::Arc;
+
+/// Infinite plane through `point` whose outward-facing side is the one `normal` points to.
+#[derive(Clone)]
+pub struct Plane<R: Rng + ?Sized> {
+    pub point: Vector4,
+    normal: Vector4,
+    u: Vector4,             // Unit vectors spanning the plane, along which the surface coordinates are measured.
+    v: Vector4,
+    material: Arc<dyn Material<R> + Send + Sync>
+}
+
+impl<R: Rng + ?Sized> Plane<R> {
+    /// Constructs the plane through `point` orthogonal to `normal`, which need not be of unit length.
+    pub fn new(
+        point: Vector4,
+        normal: Vector4,
+        material: Arc<dyn Material<R> + Send + Sync>
+    ) -> Self {
+        let normal = normal.normalize();
+        let (u, v) = normal.orthonormal_basis();
+        Self { point, normal, u, v, material }
+    }
+}
+
+/// Returns the parameter `t` at which `r` meets the plane through `point` with the unit normal `normal`, or `None` if `r`
+/// is parallel to the plane or `t` lies outside of `[t_min, t_max]`.
+pub fn intersect_plane(r: Ray, point: Vector4, normal: Vector4, t_min: f32, t_max: f32) -> Option<f32> {
+    let denominator = normal.dot(r.direction);
+    if denominator == 0.0 {
+        return None;
+    }
+    let t = normal.dot(point - r.origin) / denominator;
+    (t >= t_min && t_max >= t).then_some(t)
+}
+
+impl<R: Rng + ?Sized> Intersectable<R> for Plane<R> {
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        let t = intersect_plane(r, self.point, self.normal, t_min, t_max)?;
+        // Surface coordinates repeat every unit length, so that textures tile the plane.
+        let p = r.at(t) - self.point;
+        let uv = (self.u.dot(p).rem_euclid(1.0), self.v.dot(p).rem_euclid(1.0));
+        Some(HitRecord::new(r, t, self.normal, uv, self))
+    }
+}
+
+impl<R: Rng + ?Sized> Bounded for Plane<R> {
+    /// Returns a box that is infinite along every axis, except the axis that the plane is orthogonal to, if any.
+    fn bounding_box(&self) -> Aabb {
+        let mut min = [f32::NEG_INFINITY; 3];
+        let mut max = [f32::INFINITY; 3];
+        for axis in 0..3 {
+            if self.normal[axis].abs() == 1.0 {
+                (min[axis], max[axis]) = (self.point[axis], self.point[axis]);
+            }
+        }
+        let (min, max) = (Vector4::new(min[0], min[1], min[2], 0.0), Vector4::new(max[0], max[1], max[2], 0.0));
+        Aabb { min, max }
+    }
+}
+
+impl<R: Rng + ?Sized> Orientable for Plane<R> {
+    fn normal(&self, _p: Vector4) -> Vector4 {
+        self.normal
+    }
+}
+
+impl<R: Rng + ?Sized> Tangible<R> for Plane<R> {
+    fn material(&self) -> &Arc<dyn Material<R> + Send + Sync> {
+        &self.material
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bvh::Bvh,
+        materials::lambertian::Lambertian,
+        renderable_list::RenderableList,
+        surfaces::sphere::Sphere
+    };
+    use rand_pcg::Pcg64Mcg;
+
+    fn ground() -> Plane<Pcg64Mcg> {
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        Plane::new(Vector4::new(0.0, 0.0, -1.0, 0.0), Vector4::new(0.0, 0.0, 2.0, 0.0), material)
+    }
+
+    #[test]
+    fn test_hit_record() {
+        let plane = ground();
+
+        // Hit from above, the outward side.
+        let r = Ray::new(Vector4::new(3.0, -2.0, 1.0, 0.0), Vector4::new(1.0, 1.0, -1.0, 0.0));
+        let hit = plane.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!(hit.t, 2.0);
+        assert_eq!(hit.p, Vector4::new(5.0, 0.0, -1.0, 0.0));
+        assert_eq!(hit.normal, Vector4::new(0.0, 0.0, 1.0, 0.0));
+        assert!(hit.front_face);
+        assert!((0.0..1.0).contains(&hit.u) && (0.0..1.0).contains(&hit.v));
+
+        // Hit from below, the normal still faces outwards.
+        let r = Ray::new(Vector4::new(0.0, 0.0, -3.0, 0.0), Vector4::new(0.0, 0.0, 1.0, 0.0));
+        let hit = plane.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!(hit.normal, Vector4::new(0.0, 0.0, 1.0, 0.0));
+        assert!(!hit.front_face);
+
+        // Pointing away from the plane, or outside of [t_min, t_max].
+        let r = Ray::new(Vector4::new(0.0, 0.0, 1.0, 0.0), Vector4::new(0.0, 0.0, 1.0, 0.0));
+        assert!(plane.intersect(r, 0.001, f32::INFINITY).is_none());
+        let r = Ray::new(Vector4::new(0.0, 0.0, 1.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        assert!(plane.intersect(r, 0.001, 1.5).is_none());
+    }
+
+    #[test]
+    fn test_grazing_rays() {
+        let plane = ground();
+        // Parallel to the plane, both in it and above it.
+        let r = Ray::new(Vector4::new(0.0, 0.0, -1.0, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0));
+        assert!(plane.intersect(r, 0.0, f32::INFINITY).is_none());
+        let r = Ray::new(Vector4::new(0.0, 0.0, 0.0, 0.0), Vector4::new(1.0, 2.0, 0.0, 0.0));
+        assert!(plane.intersect(r, 0.0, f32::INFINITY).is_none());
+        // Almost parallel, so the hit is far away but still on the plane.
+        let r = Ray::new(Vector4::new(0.0, 0.0, 0.0, 0.0), Vector4::new(1.0, 0.0, -1e-4, 0.0));
+        let hit = plane.intersect(r, 0.0, f32::INFINITY).unwrap();
+        assert!((hit.t - 1e4).abs() < 1.0);
+        assert!((hit.p.z() + 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_in_bvh() {
+        // The plane can not be placed in the hierarchy, but it is still hit, and occluded by closer objects.
+        assert!(!ground().bounding_box().is_finite());
+        assert_eq!(ground().bounding_box().min.z(), -1.0);
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        let mut list = RenderableList::<Pcg64Mcg>::new();
+        list.push(Box::new(ground()));
+        list.push(Box::new(Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 0.5, material)));
+        let bvh = Bvh::from(list);
+        assert_eq!(bvh.len(), 2);
+
+        let r = Ray::new(Vector4::new(10.0, 0.0, 5.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        assert_eq!(bvh.intersect(r, 0.001, f32::INFINITY).unwrap().t, 6.0);
+        let r = Ray::new(Vector4::new(0.0, 0.0, 5.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        assert_eq!(bvh.intersect(r, 0.001, f32::INFINITY).unwrap().t, 4.5);
+    }
+}
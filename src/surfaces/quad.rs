@@ -0,0 +1,164 @@
+use crate::{
+    aabb::{Aabb, Bounded},
+    intersectable::{HitRecord, Intersectable},
+    orientable::Orientable,
+    materials::{Material, Tangible},
+    ray::Ray,
+    sampleable::{Sampleable, SurfaceSample, area_to_solid_angle_pdf},
+    surfaces::plane::intersect_plane,
+    vector4::Vector4
+};
+use rand::Rng;
+use std::sync::Arc;
+
+/// Parallelogram with the corners `corner`, `corner + u`, `corner + u + v` and `corner + v`. The outward-facing side is the
+/// one that `u x v` points to, i.e. the one from which `u` turns counterclockwise into `v`.
+#[derive(Clone)]
+pub struct Quad<R: Rng + ?Sized> {
+    pub corner: Vector4,
+    pub u: Vector4,
+    pub v: Vector4,
+    normal: Vector4,
+    w: Vector4,             // (u x v) / |u x v|^2, which maps points on the plane to their coordinates along u and v.
+    area: f32,
+    material: Arc<dyn Material<R> + Send + Sync>
+}
+
+impl<R: Rng + ?Sized> Quad<R> {
+    pub fn new(
+        corner: Vector4,
+        u: Vector4,
+        v: Vector4,
+        material: Arc<dyn Material<R> + Send + Sync>
+    ) -> Self {
+        let n = u.cross(v);
+        Self { corner, u, v, normal: n.normalize(), w: n / n.norm2(), area: n.norm(), material }
+    }
+
+    /// Returns the parameter `t` and the surface coordinates at which `r` meets the quad, where the surface coordinates
+    /// are the coordinates of the point along `u` and `v`. Points on the edges count as hits.
+    pub fn intersect_uv(&self, r: Ray, t_min: f32, t_max: f32) -> Option<(f32, (f32, f32))> {
+        let t = intersect_plane(r, self.corner, self.normal, t_min, t_max)?;
+        let p = r.at(t) - self.corner;
+        let a = self.w.dot(p.cross(self.v));
+        let b = self.w.dot(self.u.cross(p));
+        ((0.0..=1.0).contains(&a) && (0.0..=1.0).contains(&b)).then_some((t, (a, b)))
+    }
+
+    pub fn area(&self) -> f32 {
+        self.area
+    }
+}
+
+impl<R: Rng + ?Sized> Intersectable<R> for Quad<R> {
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        let (t, uv) = self.intersect_uv(r, t_min, t_max)?;
+        Some(HitRecord::new(r, t, self.normal, uv, self))
+    }
+}
+
+impl<R: Rng + ?Sized> Bounded for Quad<R> {
+    fn bounding_box(&self) -> Aabb {
+        Aabb::new(self.corner, self.corner + self.u + self.v)
+            .include(self.corner + self.u)
+            .include(self.corner + self.v)
+    }
+}
+
+impl<R: Rng + ?Sized> Orientable for Quad<R> {
+    fn normal(&self, _p: Vector4) -> Vector4 {
+        self.normal
+    }
+}
+
+impl<R: Rng + ?Sized> Tangible<R> for Quad<R> {
+    fn material(&self) -> &Arc<dyn Material<R> + Send + Sync> {
+        &self.material
+    }
+}
+
+impl<R: Rng + ?Sized> Sampleable<R> for Quad<R> {
+    fn sample(&self, rng: &mut R, origin: Vector4) -> Option<SurfaceSample> {
+        let (a, b): (f32, f32) = rng.random();
+        let p = self.corner + a * self.u + b * self.v;
+        let pdf = area_to_solid_angle_pdf(self.area.recip(), origin, p, self.normal)?;
+        Some(SurfaceSample { p, normal: self.normal, uv: (a, b), pdf })
+    }
+
+    fn pdf(&self, origin: Vector4, direction: Vector4) -> f32 {
+        self.intersect_uv(Ray::new(origin, direction), 0.0, f32::INFINITY)
+            .and_then(|(t, _)| area_to_solid_angle_pdf(self.area.recip(), origin, origin + t * direction, self.normal))
+            .unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian::Lambertian;
+    use rand_pcg::Pcg64Mcg;
+
+    fn quad() -> Quad<Pcg64Mcg> {
+        // Unit square in the plane z = 1, facing +z.
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        Quad::new(Vector4::new(-0.5, -0.5, 1.0, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0), Vector4::new(0.0, 1.0, 0.0, 0.0), material)
+    }
+
+    #[test]
+    fn test_hit_record() {
+        let quad = quad();
+        let r = Ray::new(Vector4::new(0.25, 0.0, 3.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        let hit = quad.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!(hit.t, 2.0);
+        assert_eq!(hit.normal, Vector4::new(0.0, 0.0, 1.0, 0.0));
+        assert!(hit.front_face);
+        assert_eq!((hit.u, hit.v), (0.75, 0.5));
+
+        // Hit from behind.
+        let r = Ray::new(Vector4::new(0.25, 0.0, -3.0, 0.0), Vector4::new(0.0, 0.0, 1.0, 0.0));
+        assert!(!quad.intersect(r, 0.001, f32::INFINITY).unwrap().front_face);
+
+        // Outside of the quad, though on its plane.
+        let r = Ray::new(Vector4::new(0.75, 0.0, 3.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        assert!(quad.intersect(r, 0.001, f32::INFINITY).is_none());
+        assert_eq!(quad.area(), 1.0);
+    }
+
+    #[test]
+    fn test_edges_and_grazing_rays() {
+        let quad = quad();
+        // Edges and corners are part of the quad.
+        for (x, y) in [(0.5, 0.0), (-0.5, 0.25), (0.0, 0.5), (0.5, -0.5), (-0.5, 0.5)] {
+            let r = Ray::new(Vector4::new(x, y, 2.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+            assert!(quad.intersect(r, 0.0, f32::INFINITY).is_some(), "missed the edge at ({}, {})", x, y);
+        }
+        // Just beyond the edge.
+        let r = Ray::new(Vector4::new(0.5001, 0.0, 2.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        assert!(quad.intersect(r, 0.0, f32::INFINITY).is_none());
+        // Lying in the plane of the quad.
+        let r = Ray::new(Vector4::new(-2.0, 0.0, 1.0, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0));
+        assert!(quad.intersect(r, 0.0, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_sample() {
+        // A parallelogram spanning the first octant, seen from the origin: the solid angle density integrates to the
+        // solid angle of the parallelogram, and matches pdf.
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        let quad = Quad::<Pcg64Mcg>::new(Vector4::new(1.0, -1.0, -1.0, 0.0), Vector4::new(0.0, 2.0, 0.0, 0.0), Vector4::new(0.0, 0.0, 2.0, 0.0), material);
+        let origin = Vector4::new(0.0, 0.0, 0.0, 0.0);
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        const SAMPLE_COUNT: usize = 100000;
+        let mut acc = 0.0;
+        for _ in 0..SAMPLE_COUNT {
+            let sample = quad.sample(&mut rng, origin).unwrap();
+            assert_eq!(sample.p.x(), 1.0);
+            acc += sample.pdf.recip();
+            let pdf = quad.pdf(origin, sample.p.normalize());
+            assert!((pdf / sample.pdf - 1.0).abs() < 1e-3);
+        }
+        // The square subtends one face of a cube around the origin, i.e. 4pi / 6.
+        assert!((acc / SAMPLE_COUNT as f32 / (4.0 * std::f32::consts::PI / 6.0) - 1.0).abs() < 0.02);
+        assert_eq!(quad.pdf(origin, Vector4::new(-1.0, 0.0, 0.0, 0.0)), 0.0);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{
+    aabb::{Aabb, Bounded},
+    intersectable::{HitRecord, Intersectable},
+    orientable::Orientable,
+    materials::{Material, Tangible},
+    ray::Ray,
+    roots::solve_quadratic,
+    surfaces::sphere,
+    vector4::Vector4
+};
+use rand::Rng;
+use std::sync::Arc;
+
+/// General quadric surface `A x^2 + B y^2 + C z^2 + D xy + E xz + F yz + G x + H y + I z + J = 0`, with the coefficients
+/// `A` to `J` in this order, clipped to the box `bounds`. The outward-facing side is the one where the left-hand side is
+/// positive, e.g. the outside of the ellipsoid `x^2 + y^2 / 4 + z^2 - 1 = 0`.
+///
+/// `bounds` may be infinite, e.g. for an infinite cylinder or paraboloid, in which case the surface is kept out of
+/// bounding volume hierarchies like a plane.
+#[derive(Clone)]
+pub struct Quadric<R: Rng + ?Sized> {
+    pub coefficients: [f32; 10],
+    pub bounds: Aabb,
+    material: Arc<dyn Material<R> + Send + Sync>
+}
+
+impl<R: Rng + ?Sized> Quadric<R> {
+    pub fn new(
+        coefficients: [f32; 10],
+        bounds: Aabb,
+        material: Arc<dyn Material<R> + Send + Sync>
+    ) -> Self {
+        Self { coefficients, bounds, material }
+    }
+}
+
+impl<R: Rng + ?Sized> Intersectable<R> for Quadric<R> {
+    /// The surface coordinates of a hit are those of the point with the same normal on a sphere, see `sphere::uv`.
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        // Substituting the ray into the equation gives a quadratic in t, which is solved in double precision.
+        let [a, b, c, d, e, f, g, h, i, j] = self.coefficients.map(f64::from);
+        let (ox, oy, oz) = (r.origin.x() as f64, r.origin.y() as f64, r.origin.z() as f64);
+        let (dx, dy, dz) = (r.direction.x() as f64, r.direction.y() as f64, r.direction.z() as f64);
+        let roots = solve_quadratic(
+            a * dx * dx + b * dy * dy + c * dz * dz + d * dx * dy + e * dx * dz + f * dy * dz,
+            2.0 * (a * ox * dx + b * oy * dy + c * oz * dz)
+                + d * (ox * dy + oy * dx) + e * (ox * dz + oz * dx) + f * (oy * dz + oz * dy)
+                + g * dx + h * dy + i * dz,
+            a * ox * ox + b * oy * oy + c * oz * oz + d * ox * oy + e * ox * oz + f * oy * oz + g * ox + h * oy + i * oz + j
+        );
+        let t = roots.iter()
+            .map(|&t| t as f32)
+            .find(|&t| t >= t_min && t_max >= t && self.bounds.contains(r.at(t)))?;
+        let n = self.normal(r.at(t));
+        Some(HitRecord::new(r, t, n, sphere::uv(n), self))
+    }
+}
+
+impl<R: Rng + ?Sized> Bounded for Quadric<R> {
+    fn bounding_box(&self) -> Aabb {
+        self.bounds
+    }
+}
+
+impl<R: Rng + ?Sized> Orientable for Quadric<R> {
+    /// Returns the normalised gradient of the left-hand side of the equation at `p`. At singular points such as the apex of
+    /// a cone, where the gradient vanishes, the normal is arbitrarily `+z`.
+    fn normal(&self, p: Vector4) -> Vector4 {
+        let [a, b, c, d, e, f, g, h, i, _] = self.coefficients;
+        let (x, y, z) = (p.x(), p.y(), p.z());
+        let gradient = Vector4::new(
+            2.0 * a * x + d * y + e * z + g,
+            2.0 * b * y + d * x + f * z + h,
+            2.0 * c * z + e * x + f * y + i,
+            0.0
+        );
+        if gradient.norm2() == 0.0 {
+            Vector4::new(0.0, 0.0, 1.0, 0.0)
+        } else {
+            gradient.normalize()
+        }
+    }
+}
+
+impl<R: Rng + ?Sized> Tangible<R> for Quadric<R> {
+    fn material(&self) -> &Arc<dyn Material<R> + Send + Sync> {
+        &self.material
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bvh::Bvh,
+        materials::lambertian::Lambertian,
+        renderable_list::RenderableList
+    };
+    use rand_pcg::Pcg64Mcg;
+
+    fn quadric(coefficients: [f32; 10], min: Vector4, max: Vector4) -> Quadric<Pcg64Mcg> {
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        Quadric::new(coefficients, Aabb::new(min, max), material)
+    }
+
+    #[test]
+    fn test_ellipsoid() {
+        // x^2 + y^2 / 4 + z^2 = 1, seen along the y axis from both sides.
+        let ellipsoid = quadric([1.0, 0.25, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -1.0], Vector4::new(-2.0, -2.0, -2.0, 0.0), Vector4::new(2.0, 2.0, 2.0, 0.0));
+        let r = Ray::new(Vector4::new(0.0, -5.0, 0.0, 0.0), Vector4::new(0.0, 1.0, 0.0, 0.0));
+        let hit = ellipsoid.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!((hit.t, hit.normal), (3.0, Vector4::new(0.0, -1.0, 0.0, 0.0)));
+        assert!(hit.front_face);
+        let hit = ellipsoid.intersect(r, 4.0, f32::INFINITY).unwrap();
+        assert_eq!((hit.t, hit.normal), (7.0, Vector4::new(0.0, 1.0, 0.0, 0.0)));
+        assert!(!hit.front_face);
+
+        // The normal is the gradient rather than the direction from the centre.
+        let p = Vector4::new(0.6, 1.6, 0.0, 0.0);
+        assert!((ellipsoid.normal(p) - Vector4::new(1.2, 0.8, 0.0, 0.0).normalize()).norm() < 1e-6);
+        let r = Ray::new(Vector4::new(5.0, 0.0, 0.0, 0.0), Vector4::new(0.0, 1.0, 0.0, 0.0));
+        assert!(ellipsoid.intersect(r, 0.001, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_clipping() {
+        // The hyperboloid of one sheet x^2 + y^2 - z^2 = 1 clipped to |z| <= 1: the ray down its axis misses it, and
+        // the ray along the x axis hits its waist from outside.
+        let (min, max) = (Vector4::new(-2.0, -2.0, -1.0, 0.0), Vector4::new(2.0, 2.0, 1.0, 0.0));
+        let hyperboloid = quadric([1.0, 1.0, -1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -1.0], min, max);
+        let r = Ray::new(Vector4::new(0.0, 0.0, 5.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        assert!(hyperboloid.intersect(r, 0.001, f32::INFINITY).is_none());
+        let r = Ray::new(Vector4::new(5.0, 0.0, 0.0, 0.0), Vector4::new(-1.0, 0.0, 0.0, 0.0));
+        let hit = hyperboloid.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!((hit.t, hit.normal), (4.0, Vector4::new(1.0, 0.0, 0.0, 0.0)));
+
+        // A ray above the bounds meets the surface only outside of them, and so misses it, and so does a ray from within the
+        // waist that leaves through the clipped-off part.
+        let r = Ray::new(Vector4::new(5.0, 0.0, 1.5, 0.0), Vector4::new(-1.0, 0.0, 0.0, 0.0));
+        assert!(hyperboloid.intersect(r, 0.001, f32::INFINITY).is_none());
+        let r = Ray::new(Vector4::new(0.0, 0.0, 0.5, 0.0), Vector4::new(1.0, 0.0, 0.75, 0.0));
+        assert!(hyperboloid.intersect(r, 0.001, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_unbounded() {
+        // The infinite cylinder x^2 + y^2 = 1 is kept out of the hierarchy, but is still hit.
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        let bounds = Aabb::new(Vector4::new(-1.0, -1.0, f32::NEG_INFINITY, 0.0), Vector4::new(1.0, 1.0, f32::INFINITY, 0.0));
+        let cylinder = Quadric::<Pcg64Mcg>::new([1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -1.0], bounds, material);
+        assert!(!cylinder.bounding_box().is_finite());
+        let mut list = RenderableList::new();
+        list.push(Box::new(cylinder));
+        let bvh = Bvh::from(list);
+        let r = Ray::new(Vector4::new(-5.0, 0.0, 1e6, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0));
+        assert_eq!(bvh.intersect(r, 0.001, f32::INFINITY).unwrap().t, 4.0);
+    }
+}
@@ -1,12 +1,18 @@
 use crate::{
-    intersectable::Intersectable,
+    aabb::{Aabb, Bounded},
+    intersectable::{HitRecord, Intersectable},
     orientable::Orientable,
     materials::{Material, Tangible},
+    random::sample_unit_sphere_uniform,
     ray::Ray,
+    sampleable::{Sampleable, SurfaceSample, area_to_solid_angle_pdf},
     vector4::Vector4
 };
 use rand::Rng;
-use std::sync::Arc;
+use std::{
+    f32::consts::PI,
+    sync::Arc
+};
 
 #[derive(Clone)]
 pub struct Sphere<R: Rng + ?Sized> {
@@ -25,30 +31,18 @@ impl<R: Rng + ?Sized> Sphere<R> {
     }
 }
 
-impl<R: Rng + ?Sized> Intersectable for Sphere<R> {
-    // Intersection computed using the quadratic equation (C - P) * (C - P) = R^2, where
-    // C is the centre of the sphere, P = Q + dt is a point on the ray, and R is the radius of the sphere.
-    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<f32> {
-        let oc = self.center - r.origin;
-        let a = r.direction.norm2();
-        let b = -2.0 * r.direction.dot(oc);
-        let c = oc.norm2() - self.radius * self.radius;
-        let d = b * b - 4.0 * a * c;
-
-        if d < 0.0 {
-            None
-        } else {
-            // -b - sqrt(d) <= -b + sqrt(d).
-            let t_1 = (-b - f32::sqrt(d)) / (2.0 * a);
-            let t_2 = (-b + f32::sqrt(d)) / (2.0 * a);
-            if t_1 >= t_min && t_max >= t_1 {
-                Some(t_1)
-            } else if t_2 >= t_min && t_max >= t_2 {
-                Some(t_2)
-            } else {
-                None
-            }
-        }
+impl<R: Rng + ?Sized> Intersectable<R> for Sphere<R> {
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        let t = hit(self.center, self.radius, r, t_min, t_max)?;
+        let n = self.normal(r.at(t));
+        Some(HitRecord::new(r, t, n, uv(n), self))
+    }
+}
+
+impl<R: Rng + ?Sized> Bounded for Sphere<R> {
+    fn bounding_box(&self) -> Aabb {
+        let r = Vector4::new(self.radius, self.radius, self.radius, 0.0);
+        Aabb::new(self.center - r, self.center + r)
     }
 }
 
@@ -62,4 +56,179 @@ impl<R: Rng + ?Sized> Tangible<R> for Sphere<R> {
     fn material(&self) -> &Arc<dyn Material<R> + Send + Sync> {
         &self.material
     }
-}
\ No newline at end of file
+}
+
+impl<R: Rng + ?Sized> Sampleable<R> for Sphere<R> {
+    /// Seen from outside, samples the cone of directions subtended by the sphere uniformly, so that only visible points are
+    /// sampled (Shirley et al., "Monte Carlo Techniques for Direct Lighting Calculations", 1996). From inside, samples the
+    /// surface uniformly by area.
+    fn sample(&self, rng: &mut R, origin: Vector4) -> Option<SurfaceSample> {
+        let oc = self.center - origin;
+        let distance2 = oc.norm2();
+        let radius2 = self.radius * self.radius;
+        if distance2 <= radius2 {
+            let n = sample_unit_sphere_uniform(rng);
+            let p = self.center + self.radius * n;
+            let pdf = area_to_solid_angle_pdf(1.0 / (4.0 * PI * radius2), origin, p, n)?;
+            return Some(SurfaceSample { p, normal: n, uv: uv(n), pdf });
+        }
+
+        let distance = distance2.sqrt();
+        let w = oc / distance;
+        let (u, v) = w.orthonormal_basis();
+        let sin2_theta_max = radius2 / distance2;
+        let cos_theta_max = f32::sqrt(f32::max(0.0, 1.0 - sin2_theta_max));
+        let (s, phi): (f32, f32) = rng.random();
+        let cos_theta = 1.0 - s * (1.0 - cos_theta_max);
+        let sin2_theta = 1.0 - cos_theta * cos_theta;
+        let phi = 2.0 * PI * phi;
+
+        // Distance to the nearest intersection of the sampled direction and the sphere, and the angle between the
+        // outward normal at the intersection and -w.
+        let ds = distance * cos_theta - f32::sqrt(f32::max(0.0, radius2 - distance2 * sin2_theta));
+        let cos_alpha = ((distance2 + radius2 - ds * ds) / (2.0 * distance * self.radius)).clamp(-1.0, 1.0);
+        let sin_alpha = f32::sqrt(1.0 - cos_alpha * cos_alpha);
+        let n = -(sin_alpha * phi.cos() * u + sin_alpha * phi.sin() * v + cos_alpha * w);
+        let p = self.center + self.radius * n;
+        let pdf = 1.0 / (2.0 * PI * (1.0 - cos_theta_max));
+        pdf.is_finite().then_some(SurfaceSample { p, normal: n, uv: uv(n), pdf })
+    }
+
+    fn pdf(&self, origin: Vector4, direction: Vector4) -> f32 {
+        let oc = self.center - origin;
+        let distance2 = oc.norm2();
+        let radius2 = self.radius * self.radius;
+        if distance2 <= radius2 {
+            return match self.intersect(Ray::new(origin, direction), 0.0, f32::INFINITY) {
+                Some(hit) => area_to_solid_angle_pdf(1.0 / (4.0 * PI * radius2), origin, hit.p, hit.normal).unwrap_or(0.0),
+                None => 0.0
+            };
+        }
+
+        let cos_theta_max = f32::sqrt(f32::max(0.0, 1.0 - radius2 / distance2));
+        if direction.dot(oc) < cos_theta_max * distance2.sqrt() {
+            return 0.0;
+        }
+        let pdf = 1.0 / (2.0 * PI * (1.0 - cos_theta_max));
+        if pdf.is_finite() { pdf } else { 0.0 }
+    }
+}
+
+/// Returns the parameter `t` of the first point within `[t_min, t_max]` at which `r` meets the sphere with the given
+/// `center` and `radius`.
+pub fn hit(center: Vector4, radius: f32, r: Ray, t_min: f32, t_max: f32) -> Option<f32> {
+    // Intersection computed using the quadratic equation (C - P) * (C - P) = R^2, where
+    // C is the centre of the sphere, P = Q + dt is a point on the ray, and R is the radius of the sphere.
+    let oc = center - r.origin;
+    let a = r.direction.norm2();
+    let b = -2.0 * r.direction.dot(oc);
+    let c = oc.norm2() - radius * radius;
+    let d = b * b - 4.0 * a * c;
+    if d < 0.0 {
+        return None;
+    }
+
+    // -b - sqrt(d) <= -b + sqrt(d).
+    let t_1 = (-b - f32::sqrt(d)) / (2.0 * a);
+    let t_2 = (-b + f32::sqrt(d)) / (2.0 * a);
+    if t_1 >= t_min && t_max >= t_1 {
+        Some(t_1)
+    } else if t_2 >= t_min && t_max >= t_2 {
+        Some(t_2)
+    } else {
+        None
+    }
+}
+
+/// Returns the surface coordinates of the point on a sphere with outward-facing unit normal `n`, where `u` is the
+/// azimuthal angle about the `z` axis measured counterclockwise from the `x` axis and `v` is the polar angle measured
+/// from the `-z` axis, both scaled to `[0, 1]`.
+pub fn uv(n: Vector4) -> (f32, f32) {
+    let phi = f32::atan2(n.y(), n.x());
+    let phi = if phi < 0.0 { phi + 2.0 * PI } else { phi };
+    let theta = f32::acos((-n.z()).clamp(-1.0, 1.0));
+    (phi / (2.0 * PI), theta / PI)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian::Lambertian;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn test_hit_record() {
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        let sphere = Sphere::<Pcg64Mcg>::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 2.0, material);
+
+        // Hit from the outside.
+        let r = Ray::new(Vector4::new(0.0, 0.0, -5.0, 0.0), Vector4::new(0.0, 0.0, 1.0, 0.0));
+        let hit = sphere.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!(hit.t, 3.0);
+        assert_eq!(hit.p, Vector4::new(0.0, 0.0, -2.0, 0.0));
+        assert_eq!(hit.normal, Vector4::new(0.0, 0.0, -1.0, 0.0));
+        assert!(hit.front_face);
+        assert_eq!(hit.v, 0.0);
+
+        // Hit from the inside, the normal should still face outwards.
+        let r = Ray::new(Vector4::new(0.0, 0.0, 0.0, 0.0), Vector4::new(0.0, 0.0, 1.0, 0.0));
+        let hit = sphere.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!(hit.t, 2.0);
+        assert_eq!(hit.normal, Vector4::new(0.0, 0.0, 1.0, 0.0));
+        assert!(!hit.front_face);
+        assert_eq!(hit.v, 1.0);
+
+        // Miss.
+        let r = Ray::new(Vector4::new(0.0, 3.0, -5.0, 0.0), Vector4::new(0.0, 0.0, 1.0, 0.0));
+        assert!(sphere.intersect(r, 0.001, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_sample() {
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        let sphere = Sphere::<Pcg64Mcg>::new(Vector4::new(1.0, 2.0, 3.0, 0.0), 2.0, material);
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+
+        // From outside, every sampled point should be the first point on the sphere in its direction.
+        let origin = Vector4::new(-3.0, 1.0, 5.0, 0.0);
+        for _ in 0..10000 {
+            let sample = sphere.sample(&mut rng, origin).unwrap();
+            assert!((sample.normal - (sample.p - sphere.center) / sphere.radius).norm() < 1e-5);
+            let hit = sphere.intersect(Ray::new(origin, sample.p - origin), 0.0, f32::INFINITY).unwrap();
+            assert!((hit.p - sample.p).norm() < 1e-4, "{:?} is not visible from {:?}", sample.p, origin);
+        }
+
+        // From inside, the solid angle density integrates to 4pi, i.e. E[1 / pdf] = 4pi.
+        let origin = Vector4::new(1.5, 2.0, 2.0, 0.0);
+        const SAMPLE_COUNT: usize = 100000;
+        let mut acc = 0.0;
+        for _ in 0..SAMPLE_COUNT {
+            acc += sphere.sample(&mut rng, origin).unwrap().pdf.recip();
+        }
+        assert!((acc / SAMPLE_COUNT as f32 / (4.0 * PI) - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_pdf_matches_sample() {
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        let sphere = Sphere::<Pcg64Mcg>::new(Vector4::new(1.0, 2.0, 3.0, 0.0), 2.0, material);
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        for origin in [Vector4::new(-3.0, 1.0, 5.0, 0.0), Vector4::new(1.5, 2.0, 2.0, 0.0)] {
+            for _ in 0..1000 {
+                let sample = sphere.sample(&mut rng, origin).unwrap();
+                let pdf = sphere.pdf(origin, (sample.p - origin).normalize());
+                assert!((pdf / sample.pdf - 1.0).abs() < 1e-3, "{} != {}", pdf, sample.pdf);
+            }
+        }
+        // Directions outside of the cone subtended by the sphere.
+        assert_eq!(sphere.pdf(Vector4::new(-3.0, 1.0, 5.0, 0.0), Vector4::new(-1.0, 0.0, 0.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn test_uv() {
+        assert_eq!(uv(Vector4::new(1.0, 0.0, 0.0, 0.0)), (0.0, 0.5));
+        assert_eq!(uv(Vector4::new(0.0, 1.0, 0.0, 0.0)), (0.25, 0.5));
+        assert_eq!(uv(Vector4::new(-1.0, 0.0, 0.0, 0.0)), (0.5, 0.5));
+        assert_eq!(uv(Vector4::new(0.0, -1.0, 0.0, 0.0)), (0.75, 0.5));
+    }
+}
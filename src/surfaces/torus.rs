@@ -0,0 +1,173 @@
+use crate::{
+    aabb::{Aabb, Bounded},
+    frame::{Frame, azimuth},
+    intersectable::{HitRecord, Intersectable},
+    orientable::Orientable,
+    materials::{Material, Tangible},
+    ray::Ray,
+    roots::solve_quartic,
+    surfaces::disk::disk_bounding_box,
+    vector4::Vector4
+};
+use rand::Rng;
+use std::{
+    f32::consts::PI,
+    sync::Arc
+};
+
+/// Ring torus swept by a circle of radius `minor_radius` whose centre travels around a circle of radius `major_radius`
+/// about `axis` through `center`.
+#[derive(Clone)]
+pub struct Torus<R: Rng + ?Sized> {
+    pub center: Vector4,
+    pub major_radius: f32,
+    pub minor_radius: f32,
+    frame: Frame,           // Frame at the centre whose z axis is the axis of the torus.
+    material: Arc<dyn Material<R> + Send + Sync>
+}
+
+impl<R: Rng + ?Sized> Torus<R> {
+    /// Constructs a torus about `axis`, which need not be of unit length.
+    pub fn new(
+        center: Vector4,
+        axis: Vector4,
+        major_radius: f32,
+        minor_radius: f32,
+        material: Arc<dyn Material<R> + Send + Sync>
+    ) -> Self {
+        Self { center, major_radius, minor_radius, frame: Frame::new(center, axis), material }
+    }
+
+    /// Returns the outward-facing unit normal at the point `p` in the local frame, i.e. the direction from the nearest
+    /// point on the circle traced by the centre of the tube.
+    fn local_normal(&self, p: Vector4) -> Vector4 {
+        let ring = Vector4::new(p.x(), p.y(), 0.0, 0.0).normalize();
+        (p - self.major_radius * ring).normalize()
+    }
+
+    /// Returns the parameter `t` of the first point at which `r` meets the torus within `[t_min, t_max]`, with the point
+    /// in the local frame.
+    ///
+    /// Points on the ray satisfy `(x^2 + y^2 + z^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + y^2)`, which is a quartic in `t`. It is
+    /// solved in double precision along the normalised direction, measured from the point of the ray closest to the centre,
+    /// which keeps its coefficients well-scaled however far the ray starts from the torus.
+    fn hit(&self, r: Ray, t_min: f32, t_max: f32) -> Option<(f32, Vector4)> {
+        let r = self.frame.ray_to_local(r);
+        let length = r.direction.norm() as f64;
+        let d = [r.direction.x() as f64 / length, r.direction.y() as f64 / length, r.direction.z() as f64 / length];
+        let o = [r.origin.x() as f64, r.origin.y() as f64, r.origin.z() as f64];
+        let s_0 = -(o[0] * d[0] + o[1] * d[1] + o[2] * d[2]);
+        let o = [o[0] + s_0 * d[0], o[1] + s_0 * d[1], o[2] + s_0 * d[2]];
+
+        let (major2, minor2) = ((self.major_radius as f64).powi(2), (self.minor_radius as f64).powi(2));
+        let od = o[0] * d[0] + o[1] * d[1] + o[2] * d[2];
+        let k = o[0] * o[0] + o[1] * o[1] + o[2] * o[2] + major2 - minor2;
+        let roots = solve_quartic(
+            1.0,
+            4.0 * od,
+            2.0 * k + 4.0 * od * od - 4.0 * major2 * (d[0] * d[0] + d[1] * d[1]),
+            4.0 * od * k - 8.0 * major2 * (o[0] * d[0] + o[1] * d[1]),
+            k * k - 4.0 * major2 * (o[0] * o[0] + o[1] * o[1])
+        );
+        let t = roots.iter()
+            .map(|s| ((s + s_0) / length) as f32)
+            .find(|&t| t >= t_min && t_max >= t)?;
+        Some((t, r.at(t)))
+    }
+}
+
+impl<R: Rng + ?Sized> Intersectable<R> for Torus<R> {
+    /// The surface coordinates of a hit are the azimuth about the axis and the angle around the tube, measured from the
+    /// outer equator towards the `+z` side of the local frame, both scaled to `[0, 1]`.
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        let (t, p) = self.hit(r, t_min, t_max)?;
+        let n = self.local_normal(p);
+        let radial = (p.x() * n.x() + p.y() * n.y()) / f32::sqrt(p.x() * p.x() + p.y() * p.y());
+        let theta = f32::atan2(n.z(), radial);
+        let theta = if theta < 0.0 { theta + 2.0 * PI } else { theta };
+        Some(HitRecord::new(r, t, self.frame.vector_to_world(n), (azimuth(p), theta / (2.0 * PI)), self))
+    }
+}
+
+impl<R: Rng + ?Sized> Bounded for Torus<R> {
+    fn bounding_box(&self) -> Aabb {
+        let b = disk_bounding_box(self.center, self.frame.w, self.major_radius);
+        let e = Vector4::new(self.minor_radius, self.minor_radius, self.minor_radius, 0.0);
+        Aabb::new(b.min - e, b.max + e)
+    }
+}
+
+impl<R: Rng + ?Sized> Orientable for Torus<R> {
+    fn normal(&self, p: Vector4) -> Vector4 {
+        self.frame.vector_to_world(self.local_normal(self.frame.to_local(p)))
+    }
+}
+
+impl<R: Rng + ?Sized> Tangible<R> for Torus<R> {
+    fn material(&self) -> &Arc<dyn Material<R> + Send + Sync> {
+        &self.material
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian::Lambertian;
+    use rand_pcg::Pcg64Mcg;
+
+    fn torus() -> Torus<Pcg64Mcg> {
+        // About the y axis, with radii 2 and 0.5.
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        Torus::new(Vector4::new(0.0, 0.0, 0.0, 0.0), Vector4::new(0.0, 3.0, 0.0, 0.0), 2.0, 0.5, material)
+    }
+
+    #[test]
+    fn test_hit_record() {
+        let torus = torus();
+        // Through the hole, from outside and from within the tube.
+        let r = Ray::new(Vector4::new(-10.0, 0.0, 0.0, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0));
+        let hit = torus.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.t - 7.5).abs() < 1e-5);
+        assert!((hit.normal - Vector4::new(-1.0, 0.0, 0.0, 0.0)).norm() < 1e-5);
+        assert!(hit.front_face);
+        assert!(hit.v.abs() < 1e-5 || (hit.v - 1.0).abs() < 1e-5);
+        let hit = torus.intersect(r, 8.0, f32::INFINITY).unwrap();
+        assert!((hit.t - 8.5).abs() < 1e-5);
+        assert!((hit.normal - Vector4::new(1.0, 0.0, 0.0, 0.0)).norm() < 1e-5);
+        assert!(!hit.front_face);
+        assert!((hit.v - 0.5).abs() < 1e-5);
+
+        // Along the axis, through the hole, and at the top of the tube.
+        let r = Ray::new(Vector4::new(0.0, -10.0, 0.0, 0.0), Vector4::new(0.0, 1.0, 0.0, 0.0));
+        assert!(torus.intersect(r, 0.001, f32::INFINITY).is_none());
+        let r = Ray::new(Vector4::new(0.0, 5.0, 2.0, 0.0), Vector4::new(0.0, -1.0, 0.0, 0.0));
+        let hit = torus.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.t - 4.5).abs() < 1e-3);
+        assert!((hit.normal - Vector4::new(0.0, 1.0, 0.0, 0.0)).norm() < 1e-3);
+        assert!((torus.normal(hit.p) - hit.normal).norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_distant_rays() {
+        // Hits of rays starting far away still lie on the surface, and are on its outward-facing side.
+        let torus = torus();
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        for _ in 0..1000 {
+            let target = Vector4::new(rng.random_range(-2.5..2.5), rng.random_range(-0.5..0.5), rng.random_range(-2.5..2.5), 0.0);
+            let origin = Vector4::new(rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0), 0.0);
+            let origin = 1000.0 * origin.normalize();
+            let Some(hit) = torus.intersect(Ray::new(origin, target - origin), 0.001, f32::INFINITY) else {
+                continue;
+            };
+            let ring = Vector4::new(hit.p.x(), 0.0, hit.p.z(), 0.0).normalize();
+            assert!(((hit.p - 2.0 * ring).norm() - 0.5).abs() < 1e-3, "{:?} is not on the torus", hit.p);
+            assert!(hit.front_face);
+        }
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let b = torus().bounding_box();
+        assert_eq!((b.min, b.max), (Vector4::new(-2.5, -0.5, -2.5, 0.0), Vector4::new(2.5, 0.5, 2.5, 0.0)));
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{
+    aabb::{Aabb, Bounded},
+    intersectable::{HitRecord, Intersectable},
+    orientable::Orientable,
+    materials::{Material, Tangible},
+    ray::Ray,
+    sampleable::{Sampleable, SurfaceSample, area_to_solid_angle_pdf},
+    vector4::Vector4
+};
+use rand::Rng;
+use std::sync::Arc;
+
+/// Intersection of a ray and a triangle at `t` with barycentric coordinates `b`,
+/// i.e. `r.at(t) = b[0] * p_0 + b[1] * p_1 + b[2] * p_2`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TriangleIntersection {
+    pub t: f32,
+    pub b: [f32; 3]
+}
+
+/// Watertight ray-triangle intersection (Woop, Benthin and Wald, "Watertight Ray/Triangle Intersection", JCGT 2013).
+///
+/// The vertices are transformed into a coordinate system where the ray starts at the origin and points along the
+/// `+z` axis, which reduces the test to a 2D point-in-triangle test at the origin. The edge functions for an edge
+/// shared by two triangles are computed from the same values in both triangles, so a ray can never slip through the edge.
+///
+/// Returns the intersection if `r` intersects the triangle for some `t` in `[t_min, t_max]`, `None` otherwise.
+pub fn intersect_triangle(r: Ray, p: [Vector4; 3], t_min: f32, t_max: f32) -> Option<TriangleIntersection> {
+    // Permute the axes so that the z component of the direction has the largest magnitude.
+    let d = r.direction;
+    let kz = if d.x().abs() > d.y().abs() && d.x().abs() > d.z().abs() {
+        0
+    } else if d.y().abs() > d.z().abs() {
+        1
+    } else {
+        2
+    };
+    let kx = (kz + 1) % 3;
+    let ky = (kx + 1) % 3;
+
+    // Translate the vertices so that the ray starts at the origin, then shear them so that the ray points along +z.
+    let shear_x = -d[kx] / d[kz];
+    let shear_y = -d[ky] / d[kz];
+    let shear_z = d[kz].recip();
+    let q = p.map(|p| {
+        let p = p - r.origin;
+        [p[kx] + shear_x * p[kz], p[ky] + shear_y * p[kz], p[kz]]
+    });
+
+    // Edge functions, recomputed with double precision if any of them is zero to resolve hits on edges consistently.
+    let mut e = [
+        q[1][0] * q[2][1] - q[1][1] * q[2][0],
+        q[2][0] * q[0][1] - q[2][1] * q[0][0],
+        q[0][0] * q[1][1] - q[0][1] * q[1][0]
+    ];
+    if e.contains(&0.0) {
+        let q = q.map(|q| q.map(f64::from));
+        e = [
+            (q[1][0] * q[2][1] - q[1][1] * q[2][0]) as f32,
+            (q[2][0] * q[0][1] - q[2][1] * q[0][0]) as f32,
+            (q[0][0] * q[1][1] - q[0][1] * q[1][0]) as f32
+        ];
+    }
+    if e.iter().any(|&e| e < 0.0) && e.iter().any(|&e| e > 0.0) {
+        return None;
+    }
+    let det = e[0] + e[1] + e[2];
+    if det == 0.0 {
+        return None;
+    }
+
+    let t = (e[0] * shear_z * q[0][2] + e[1] * shear_z * q[1][2] + e[2] * shear_z * q[2][2]) / det;
+    if !(t >= t_min && t_max >= t) {
+        return None;
+    }
+    Some(TriangleIntersection { t, b: e.map(|e| e / det) })
+}
+
+/// Returns the unit normal of the triangle `p`, facing the side from which the vertices appear counterclockwise.
+pub fn triangle_normal(p: [Vector4; 3]) -> Vector4 {
+    (p[1] - p[0]).cross(p[2] - p[0]).normalize()
+}
+
+/// Returns the area of the triangle `p`.
+pub fn triangle_area(p: [Vector4; 3]) -> f32 {
+    0.5 * (p[1] - p[0]).cross(p[2] - p[0]).norm()
+}
+
+/// Samples barycentric coordinates of a point distributed uniformly by area on a triangle.
+pub fn sample_triangle_uniform<R: Rng + ?Sized>(rng: &mut R) -> [f32; 3] {
+    let (s, t): (f32, f32) = rng.random();
+    let s = s.sqrt();
+    let b_1 = t * s;
+    let b_2 = 1.0 - s;
+    [1.0 - b_1 - b_2, b_1, b_2]
+}
+
+/// A single triangle. The outward-facing side is the one from which the vertices appear in counterclockwise order.
+#[derive(Clone)]
+pub struct Triangle<R: Rng + ?Sized> {
+    pub vertices: [Vector4; 3],
+    normal: Vector4,
+    material: Arc<dyn Material<R> + Send + Sync>
+}
+
+impl<R: Rng + ?Sized> Triangle<R> {
+    pub fn new(
+        vertices: [Vector4; 3],
+        material: Arc<dyn Material<R> + Send + Sync>
+    ) -> Self {
+        Self { vertices, normal: triangle_normal(vertices), material }
+    }
+}
+
+impl<R: Rng + ?Sized> Intersectable<R> for Triangle<R> {
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        let intersection = intersect_triangle(r, self.vertices, t_min, t_max)?;
+        // Surface coordinates (0, 0), (1, 0) and (1, 1) at the vertices.
+        let uv = (intersection.b[1] + intersection.b[2], intersection.b[2]);
+        Some(HitRecord::new(r, intersection.t, self.normal, uv, self))
+    }
+}
+
+impl<R: Rng + ?Sized> Bounded for Triangle<R> {
+    fn bounding_box(&self) -> Aabb {
+        Aabb::new(self.vertices[0], self.vertices[1]).include(self.vertices[2])
+    }
+}
+
+impl<R: Rng + ?Sized> Orientable for Triangle<R> {
+    fn normal(&self, _p: Vector4) -> Vector4 {
+        self.normal
+    }
+}
+
+impl<R: Rng + ?Sized> Tangible<R> for Triangle<R> {
+    fn material(&self) -> &Arc<dyn Material<R> + Send + Sync> {
+        &self.material
+    }
+}
+
+impl<R: Rng + ?Sized> Sampleable<R> for Triangle<R> {
+    fn sample(&self, rng: &mut R, origin: Vector4) -> Option<SurfaceSample> {
+        let b = sample_triangle_uniform(rng);
+        let p = b[0] * self.vertices[0] + b[1] * self.vertices[1] + b[2] * self.vertices[2];
+        let pdf = area_to_solid_angle_pdf(triangle_area(self.vertices).recip(), origin, p, self.normal)?;
+        Some(SurfaceSample { p, normal: self.normal, uv: (b[1] + b[2], b[2]), pdf })
+    }
+
+    fn pdf(&self, origin: Vector4, direction: Vector4) -> f32 {
+        intersect_triangle(Ray::new(origin, direction), self.vertices, 0.0, f32::INFINITY)
+            .and_then(|intersection| {
+                let p = origin + intersection.t * direction;
+                area_to_solid_angle_pdf(triangle_area(self.vertices).recip(), origin, p, self.normal)
+            })
+            .unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        materials::lambertian::Lambertian,
+        random::sample_unit_sphere_uniform
+    };
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn test_intersect_triangle() {
+        let p = [
+            Vector4::new(0.0, 0.0, 0.0, 0.0),
+            Vector4::new(1.0, 0.0, 0.0, 0.0),
+            Vector4::new(0.0, 1.0, 0.0, 0.0)
+        ];
+        let r = Ray::new(Vector4::new(0.25, 0.5, 2.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        let intersection = intersect_triangle(r, p, 0.0, f32::INFINITY).unwrap();
+        assert_eq!(intersection.t, 2.0);
+        assert_eq!(intersection.b, [0.25, 0.25, 0.5]);
+
+        // Outside of the [t_min, t_max] interval.
+        assert!(intersect_triangle(r, p, 0.0, 1.0).is_none());
+        assert!(intersect_triangle(r, p, 3.0, f32::INFINITY).is_none());
+
+        // Outside of the triangle.
+        let r = Ray::new(Vector4::new(0.75, 0.5, 2.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        assert!(intersect_triangle(r, p, 0.0, f32::INFINITY).is_none());
+
+        // Parallel to the triangle.
+        let r = Ray::new(Vector4::new(-1.0, 0.25, 0.0, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0));
+        assert!(intersect_triangle(r, p, 0.0, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_sample() {
+        // The triangle spans an octant as seen from the origin, so its solid angle density integrates to 4pi / 8.
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        let triangle = Triangle::<Pcg64Mcg>::new(
+            [Vector4::new(1.0, 0.0, 0.0, 0.0), Vector4::new(0.0, 1.0, 0.0, 0.0), Vector4::new(0.0, 0.0, 1.0, 0.0)],
+            material
+        );
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        const SAMPLE_COUNT: usize = 100000;
+        let mut acc = 0.0;
+        for _ in 0..SAMPLE_COUNT {
+            let sample = triangle.sample(&mut rng, Vector4::new(0.0, 0.0, 0.0, 0.0)).unwrap();
+            assert!((sample.p.x() + sample.p.y() + sample.p.z() - 1.0).abs() < 1e-6);
+            assert!(sample.p.x() >= 0.0 && sample.p.y() >= 0.0 && sample.p.z() >= 0.0);
+            acc += sample.pdf.recip();
+            let pdf = triangle.pdf(Vector4::new(0.0, 0.0, 0.0, 0.0), sample.p.normalize());
+            assert!((pdf / sample.pdf - 1.0).abs() < 1e-3);
+        }
+        assert!((acc / SAMPLE_COUNT as f32 / (std::f32::consts::PI / 2.0) - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_shared_edges_are_watertight() {
+        // Two coplanar triangles on opposite sides of the shared edge from a to b, with the rays aimed at points on the shared edge.
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        let e_1 = Vector4::new(0.8, 0.3, -0.2, 0.0);
+        let e_2 = Vector4::new(-0.1, 0.6, 0.9, 0.0);
+        let a = -0.3 * e_1 - 1.1 * e_2;
+        let b = 0.7 * e_1 + 0.9 * e_2;
+        let c_1 = -1.3 * e_1 + 0.8 * e_2;
+        let c_2 = 1.2 * e_1 - 0.7 * e_2;
+        for _ in 0..100000 {
+            let s: f32 = rng.random_range(0.01..0.99);
+            let target = a + s * (b - a);
+            let origin = 5.0 * sample_unit_sphere_uniform(&mut rng);
+            let r = Ray::new(origin, target - origin);
+            let hit_1 = intersect_triangle(r, [a, b, c_1], 0.0, f32::INFINITY);
+            let hit_2 = intersect_triangle(r, [b, a, c_2], 0.0, f32::INFINITY);
+            assert!(hit_1.is_some() || hit_2.is_some(), "{:?} slipped through the shared edge", r);
+        }
+    }
+}
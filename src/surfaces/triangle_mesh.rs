@@ -0,0 +1,249 @@
+use crate::{
+    aabb::{Aabb, Bounded},
+    bvh::Bvh,
+    intersectable::{HitRecord, Intersectable},
+    materials::{Material, Tangible},
+    ray::Ray,
+    sampleable::{Sampleable, SurfaceSample, area_to_solid_angle_pdf},
+    surfaces::triangle::{intersect_triangle, sample_triangle_uniform, triangle_area, triangle_normal},
+    vector4::Vector4
+};
+use rand::Rng;
+use std::sync::Arc;
+
+/// Indexed triangle mesh made of a single material. Vertex positions, and optionally normals and surface coordinates,
+/// are shared by the triangles, which refer to them by index. The outward-facing side of a triangle is the one from which
+/// its vertices appear in counterclockwise order, unless vertex normals are given, in which case they determine it.
+///
+/// The triangles are stored in a bounding volume hierarchy, so the mesh may be added to a scene as a single object.
+pub struct TriangleMesh<R: Rng + ?Sized> {
+    positions: Vec<Vector4>,
+    normals: Option<Vec<Vector4>>,      // Per-vertex normals used for smooth shading.
+    uvs: Option<Vec<(f32, f32)>>,       // Per-vertex surface coordinates.
+    triangles: Vec<[usize; 3]>,
+    bvh: Bvh<usize>,                    // Indices into triangles.
+    bounds: Aabb,
+    area_cdf: Vec<f32>,                 // Cumulative sums of the triangle areas, for sampling triangles by area.
+    material: Arc<dyn Material<R> + Send + Sync>
+}
+
+impl<R: Rng + ?Sized> TriangleMesh<R> {
+    /// Constructs a mesh from shared vertex attributes and triples of indices into them.
+    ///
+    /// Panics if an index is out of bounds, or if `normals` or `uvs` does not have the same length as `positions`.
+    pub fn new(
+        positions: Vec<Vector4>,
+        normals: Option<Vec<Vector4>>,
+        uvs: Option<Vec<(f32, f32)>>,
+        triangles: Vec<[usize; 3]>,
+        material: Arc<dyn Material<R> + Send + Sync>
+    ) -> Self {
+        assert!(triangles.iter().flatten().all(|&i| i < positions.len()), "triangle vertex index out of bounds");
+        assert!(normals.as_ref().is_none_or(|n| n.len() == positions.len()), "mesh must have one normal per vertex");
+        assert!(uvs.as_ref().is_none_or(|uv| uv.len() == positions.len()), "mesh must have one uv pair per vertex");
+
+        let normals = normals.map(|n| n.into_iter().map(|n| n.normalize()).collect());
+        let bounds_of = |&i: &usize| {
+            let [a, b, c] = triangles[i];
+            Aabb::new(positions[a], positions[b]).include(positions[c])
+        };
+        let bvh = Bvh::with_bounds((0..triangles.len()).collect(), bounds_of);
+        let bounds = (0..triangles.len()).fold(Aabb::empty(), |acc, i| acc.union(bounds_of(&i)));
+        let area_cdf = triangles
+            .iter()
+            .scan(0.0, |acc, t| {
+                *acc += triangle_area(t.map(|v| positions[v]));
+                Some(*acc)
+            })
+            .collect();
+        Self { positions, normals, uvs, triangles, bvh, bounds, area_cdf, material }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    /// Returns the vertex positions of the `i`th triangle.
+    pub fn triangle(&self, i: usize) -> [Vector4; 3] {
+        self.triangles[i].map(|v| self.positions[v])
+    }
+
+    /// Returns the sum of the areas of the triangles.
+    pub fn area(&self) -> f32 {
+        self.area_cdf.last().copied().unwrap_or(0.0)
+    }
+
+    fn point_at(&self, i: usize, b: [f32; 3]) -> Vector4 {
+        let p = self.triangle(i);
+        b[0] * p[0] + b[1] * p[1] + b[2] * p[2]
+    }
+
+    fn uv_at(&self, i: usize, b: [f32; 3]) -> (f32, f32) {
+        match &self.uvs {
+            Some(uvs) => {
+                let uv = self.triangles[i].map(|v| uvs[v]);
+                (
+                    b[0] * uv[0].0 + b[1] * uv[1].0 + b[2] * uv[2].0,
+                    b[0] * uv[0].1 + b[1] * uv[1].1 + b[2] * uv[2].1
+                )
+            },
+            None => (b[1] + b[2], b[2])
+        }
+    }
+
+    /// Returns the outward-facing geometric normal and the shading normal of the `i`th triangle at barycentric coordinates `b`.
+    fn normals_at(&self, i: usize, b: [f32; 3]) -> (Vector4, Vector4) {
+        let geometric_normal = triangle_normal(self.triangle(i));
+        match &self.normals {
+            Some(normals) => {
+                let n = self.triangles[i].map(|v| normals[v]);
+                let shading_normal = (b[0] * n[0] + b[1] * n[1] + b[2] * n[2]).normalize();
+                // Let the vertex normals decide which side of the triangle faces outwards.
+                let normal = if geometric_normal.dot(shading_normal) < 0.0 { -geometric_normal } else { geometric_normal };
+                (normal, shading_normal)
+            },
+            None => (geometric_normal, geometric_normal)
+        }
+    }
+}
+
+impl<R: Rng + ?Sized> Intersectable<R> for TriangleMesh<R> {
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        let (t, (i, intersection)) = self.bvh.traverse(r, t_min, t_max, |&i, r, t_min, t_max| {
+            intersect_triangle(r, self.triangle(i), t_min, t_max).map(|intersection| (intersection.t, (i, intersection)))
+        })?;
+        let (normal, shading_normal) = self.normals_at(i, intersection.b);
+        let mut hit = HitRecord::new(r, t, normal, self.uv_at(i, intersection.b), self);
+        hit.shading_normal = shading_normal;
+        // Interpolate the hit point from the vertices since it is more accurate than r.at(t).
+        hit.p = self.point_at(i, intersection.b);
+        Some(hit)
+    }
+}
+
+impl<R: Rng + ?Sized> Bounded for TriangleMesh<R> {
+    fn bounding_box(&self) -> Aabb {
+        self.bounds
+    }
+}
+
+impl<R: Rng + ?Sized> Tangible<R> for TriangleMesh<R> {
+    fn material(&self) -> &Arc<dyn Material<R> + Send + Sync> {
+        &self.material
+    }
+}
+
+impl<R: Rng + ?Sized> Sampleable<R> for TriangleMesh<R> {
+    /// Samples a triangle with probability proportional to its area, then a point on it uniformly by area.
+    fn sample(&self, rng: &mut R, origin: Vector4) -> Option<SurfaceSample> {
+        let area = self.area();
+        if area == 0.0 {
+            return None;
+        }
+        let s = rng.random::<f32>() * area;
+        let i = self.area_cdf.partition_point(|&a| a <= s).min(self.triangles.len() - 1);
+        let b = sample_triangle_uniform(rng);
+        let p = self.point_at(i, b);
+        let (normal, _) = self.normals_at(i, b);
+        let pdf = area_to_solid_angle_pdf(area.recip(), origin, p, normal)?;
+        Some(SurfaceSample { p, normal, uv: self.uv_at(i, b), pdf })
+    }
+
+    fn pdf(&self, origin: Vector4, direction: Vector4) -> f32 {
+        self.intersect(Ray::new(origin, direction), 0.0, f32::INFINITY)
+            .and_then(|hit| area_to_solid_angle_pdf(self.area().recip(), origin, hit.p, hit.normal))
+            .unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        materials::lambertian::Lambertian,
+        random::sample_unit_sphere_uniform
+    };
+    use rand_pcg::Pcg64Mcg;
+
+    fn octahedron(normals: bool) -> TriangleMesh<Pcg64Mcg> {
+        let positions = vec![
+            Vector4::new(1.0, 0.0, 0.0, 0.0),
+            Vector4::new(-1.0, 0.0, 0.0, 0.0),
+            Vector4::new(0.0, 1.0, 0.0, 0.0),
+            Vector4::new(0.0, -1.0, 0.0, 0.0),
+            Vector4::new(0.0, 0.0, 1.0, 0.0),
+            Vector4::new(0.0, 0.0, -1.0, 0.0)
+        ];
+        let triangles = vec![
+            [0, 2, 4], [2, 1, 4], [1, 3, 4], [3, 0, 4],
+            [2, 0, 5], [1, 2, 5], [3, 1, 5], [0, 3, 5]
+        ];
+        let normals = normals.then(|| positions.clone());
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        TriangleMesh::new(positions, normals, None, triangles, material)
+    }
+
+    #[test]
+    fn test_closed_mesh_is_watertight() {
+        // Every ray starting inside a closed mesh must hit it, including those aimed at its edges and vertices.
+        let mesh = octahedron(false);
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        for _ in 0..100000 {
+            let origin = 0.1 * sample_unit_sphere_uniform(&mut rng);
+            let i = rng.random_range(0..mesh.triangle_count());
+            let p = mesh.triangle(i);
+            // Aim at a random point on a random edge.
+            let s: f32 = rng.random();
+            let target = p[0] + s * (p[1] - p[0]);
+            let r = Ray::new(origin, target - origin);
+            let hit = mesh.intersect(r, 0.0, f32::INFINITY);
+            assert!(hit.is_some(), "{:?} escaped the mesh", r);
+            assert!(!hit.unwrap().front_face);
+        }
+    }
+
+    #[test]
+    fn test_hit_record() {
+        let mesh = octahedron(false);
+        let r = Ray::new(Vector4::new(1.0, 1.0, 1.0, 0.0), Vector4::new(-1.0, -1.0, -1.0, 0.0));
+        let hit = mesh.intersect(r, 0.0, f32::INFINITY).unwrap();
+        assert!((hit.t - 2.0 / 3.0).abs() < 1e-6);
+        assert!(hit.front_face);
+        assert!((hit.normal - Vector4::new(1.0, 1.0, 1.0, 0.0).normalize()).norm() < 1e-6);
+        assert_eq!(hit.normal, hit.shading_normal);
+
+        // With vertex normals pointing away from the centre, the shading normal at a vertex is the vertex position.
+        let mesh = octahedron(true);
+        let r = Ray::new(Vector4::new(0.0, 0.0, 3.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        let hit = mesh.intersect(r, 0.0, f32::INFINITY).unwrap();
+        assert_eq!(hit.t, 2.0);
+        assert!((hit.shading_normal - Vector4::new(0.0, 0.0, 1.0, 0.0)).norm() < 1e-6);
+        assert!(hit.front_face);
+    }
+
+    #[test]
+    fn test_sample() {
+        // The octahedron encloses the origin, so the solid angle density integrates to 4pi.
+        let mesh = octahedron(false);
+        assert!((mesh.area() - 4.0 * 3.0_f32.sqrt()).abs() < 1e-5);
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        const SAMPLE_COUNT: usize = 100000;
+        let mut acc = 0.0;
+        for _ in 0..SAMPLE_COUNT {
+            let sample = mesh.sample(&mut rng, Vector4::new(0.0, 0.0, 0.0, 0.0)).unwrap();
+            assert!((sample.p.x().abs() + sample.p.y().abs() + sample.p.z().abs() - 1.0).abs() < 1e-5);
+            assert!(sample.normal.dot(sample.p) > 0.0);
+            acc += sample.pdf.recip();
+            let pdf = mesh.pdf(Vector4::new(0.0, 0.0, 0.0, 0.0), sample.p.normalize());
+            assert!((pdf / sample.pdf - 1.0).abs() < 1e-3);
+        }
+        assert!((acc / SAMPLE_COUNT as f32 / (4.0 * std::f32::consts::PI) - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_index_out_of_bounds() {
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        TriangleMesh::<Pcg64Mcg>::new(vec![Vector4::new(0.0, 0.0, 0.0, 0.0)], None, None, vec![[0, 0, 1]], material);
+    }
+}
@@ -0,0 +1,34 @@
+use crate::vector4::Vector4;
+
+/// Trait defining a common interface for textures, i.e. colours varying over the surfaces of objects.
+pub trait Texture {
+    /// Returns the linear colour of the texture at the surface coordinates `(u, v)` of the point `p`.
+    fn value(&self, u: f32, v: f32, p: Vector4) -> Vector4;
+}
+
+/// Texture of a single colour.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SolidColor {
+    color: Vector4
+}
+
+impl SolidColor {
+    pub fn new(color: Vector4) -> Self {
+        Self { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f32, _v: f32, _p: Vector4) -> Vector4 {
+        self.color
+    }
+}
+
+/// Three-dimensional checker pattern alternating between two textures.
+pub mod checker;
+
+/// Textures sampled from images loaded from PPM or PNG files.
+pub mod image_texture;
+
+/// Procedural textures built from gradient noise: fractal noise, marble and wood.
+pub mod noise;
@@ -0,0 +1,60 @@
+use crate::{
+    textures::{SolidColor, Texture},
+    vector4::Vector4
+};
+use std::sync::Arc;
+
+/// Checker pattern of cubes with side length `size` filling space, so that it appears on every surface regardless of
+/// its surface coordinates. The cube containing the origin in its minimal corner uses the `even` texture.
+#[derive(Clone)]
+pub struct Checker {
+    size: f32,
+    even: Arc<dyn Texture + Send + Sync>,
+    odd: Arc<dyn Texture + Send + Sync>
+}
+
+impl Checker {
+    pub fn new(
+        size: f32,
+        even: Arc<dyn Texture + Send + Sync>,
+        odd: Arc<dyn Texture + Send + Sync>
+    ) -> Self {
+        Self { size, even, odd }
+    }
+
+    pub fn from_colors(size: f32, even: Vector4, odd: Vector4) -> Self {
+        Self::new(size, Arc::new(SolidColor::new(even)), Arc::new(SolidColor::new(odd)))
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f32, v: f32, p: Vector4) -> Vector4 {
+        let cell = |x: f32| f32::floor(x / self.size) as i64;
+        if (cell(p.x()) + cell(p.y()) + cell(p.z())).rem_euclid(2) == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checker() {
+        let white = Vector4::new(1.0, 1.0, 1.0, 0.0);
+        let black = Vector4::new(0.0, 0.0, 0.0, 0.0);
+        let checker = Checker::from_colors(0.5, white, black);
+        let at = |x, y, z| checker.value(0.0, 0.0, Vector4::new(x, y, z, 0.0));
+        assert_eq!(at(0.25, 0.25, 0.25), white);
+        assert_eq!(at(0.75, 0.25, 0.25), black);
+        assert_eq!(at(0.75, 0.75, 0.25), white);
+        assert_eq!(at(0.75, 0.75, 0.75), black);
+        // Cells on the negative side of the origin continue the pattern.
+        assert_eq!(at(-0.25, 0.25, 0.25), black);
+        assert_eq!(at(-0.25, -0.25, -0.25), black);
+        assert_eq!(at(-0.75, -0.25, -0.25), white);
+    }
+}
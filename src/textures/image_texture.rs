@@ -0,0 +1,286 @@
+use crate::{
+    color::{gamma_to_linear, lerp},
+    hdr::{self, HdrPixels},
+    textures::Texture,
+    vector4::Vector4
+};
+use std::{fs, io, path::Path};
+
+/// How texel coordinates outside of an image are mapped back into it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WrapMode {
+    /// Repeat the image, so that the texture tiles the surface.
+    Repeat,
+    /// Extend the texels on the edges of the image indefinitely.
+    Clamp
+}
+
+/// Texture sampled from an image with bilinear filtering. The image covers the unit square of surface coordinates,
+/// with `(0, 0)` at its bottom-left and `(1, 1)` at its top-right corner.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vector4>,   // Linear colours in row-major order, starting with the top row.
+    wrap_mode: WrapMode
+}
+
+impl ImageTexture {
+    /// Constructs a texture from linear colours in row-major order, starting with the top row.
+    ///
+    /// Panics if the image is empty or if `pixels` does not hold exactly `width * height` colours.
+    pub fn new(width: usize, height: usize, pixels: Vec<Vector4>, wrap_mode: WrapMode) -> Self {
+        assert!(width > 0 && height > 0, "image must not be empty");
+        assert_eq!(pixels.len(), width * height, "image must have width * height pixels");
+        Self { width, height, pixels, wrap_mode }
+    }
+
+    /// Loads a texture from a PPM (`P3` or `P6`), PNG, PFM, Radiance RGBE or OpenEXR file, where the format is determined
+    /// by the contents of the file. The colours in PPM and PNG files are converted to linear colours using `decoding_gamma`,
+    /// while those of the high dynamic range formats are already linear. Alpha channels are ignored.
+    pub fn load<P: AsRef<Path>>(path: P, decoding_gamma: f32, wrap_mode: WrapMode) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        if let Some((width, height, pixels)) = decode_hdr(&bytes)? {
+            if width == 0 || height == 0 {
+                return Err(invalid_data("image is empty"));
+            }
+            return Ok(Self::new(width, height, pixels, wrap_mode));
+        }
+        let (width, height, colors) = if bytes.starts_with(PNG_SIGNATURE) {
+            decode_png(&bytes)?
+        } else if bytes.starts_with(b"P3") || bytes.starts_with(b"P6") {
+            decode_ppm(&bytes)?
+        } else {
+            return Err(invalid_data("unsupported image format, expected PPM, PNG, PFM, Radiance RGBE or OpenEXR"));
+        };
+        if width == 0 || height == 0 {
+            return Err(invalid_data("image is empty"));
+        }
+        let pixels = colors
+            .into_iter()
+            .map(|[r, g, b]| Vector4::new(
+                gamma_to_linear(r, decoding_gamma),
+                gamma_to_linear(g, decoding_gamma),
+                gamma_to_linear(b, decoding_gamma),
+                0.0
+            ))
+            .collect();
+        Ok(Self::new(width, height, pixels, wrap_mode))
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the linear colours of the image in row-major order, starting with the top row.
+    pub fn pixels(&self) -> &[Vector4] {
+        &self.pixels
+    }
+
+    /// Returns the colour of the texel in column `x` and row `y`, counted from the top left, after wrapping the coordinates.
+    fn texel(&self, x: i64, y: i64) -> Vector4 {
+        let (width, height) = (self.width as i64, self.height as i64);
+        let (x, y) = match self.wrap_mode {
+            WrapMode::Repeat => (x.rem_euclid(width), y.rem_euclid(height)),
+            WrapMode::Clamp => (x.clamp(0, width - 1), y.clamp(0, height - 1))
+        };
+        self.pixels[y as usize * self.width + x as usize]
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f32, v: f32, _p: Vector4) -> Vector4 {
+        // Texel centres lie at half-integer coordinates.
+        let x = u * self.width as f32 - 0.5;
+        let y = (1.0 - v) * self.height as f32 - 0.5;
+        let (x_0, y_0) = (x.floor(), y.floor());
+        let (s, t) = (x - x_0, y - y_0);
+        let (x_0, y_0) = (x_0 as i64, y_0 as i64);
+        lerp(
+            lerp(self.texel(x_0, y_0), self.texel(x_0 + 1, y_0), s),
+            lerp(self.texel(x_0, y_0 + 1), self.texel(x_0 + 1, y_0 + 1), s),
+            t
+        )
+    }
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reader for the whitespace-separated tokens of a PPM header, which may be interspersed with comments running to the end of the line.
+struct PpmHeader<'a> {
+    bytes: &'a [u8],
+    position: usize
+}
+
+impl PpmHeader<'_> {
+    fn next_token(&mut self) -> io::Result<&[u8]> {
+        let bytes = self.bytes;
+        loop {
+            while self.position < bytes.len() && bytes[self.position].is_ascii_whitespace() {
+                self.position += 1;
+            }
+            if self.position < bytes.len() && bytes[self.position] == b'#' {
+                while self.position < bytes.len() && bytes[self.position] != b'\n' {
+                    self.position += 1;
+                }
+            } else {
+                break;
+            }
+        }
+        let start = self.position;
+        while self.position < bytes.len() && !bytes[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(invalid_data("unexpected end of PPM data"));
+        }
+        Ok(&bytes[start..self.position])
+    }
+
+    fn next_number(&mut self) -> io::Result<usize> {
+        std::str::from_utf8(self.next_token()?)
+            .ok()
+            .and_then(|token| token.parse().ok())
+            .ok_or_else(|| invalid_data("invalid number in PPM data"))
+    }
+}
+
+/// Decodes a PPM image into its dimensions and RGB colours scaled to `[0, 1]`.
+fn decode_ppm(bytes: &[u8]) -> io::Result<(usize, usize, Vec<[f32; 3]>)> {
+    let mut header = PpmHeader { bytes, position: 0 };
+    let binary = header.next_token()? == b"P6";
+    let width = header.next_number()?;
+    let height = header.next_number()?;
+    let max_value = header.next_number()?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(invalid_data("PPM maximum value must be in [1, 65535]"));
+    }
+    let sample_count = width.checked_mul(height).and_then(|n| n.checked_mul(3)).ok_or_else(|| invalid_data("PPM image is too large"))?;
+
+    let samples: Vec<usize> = if binary {
+        // A single whitespace character separates the header from the samples, which take two bytes if max_value > 255.
+        let start = header.position + 1;
+        let sample_size = if max_value < 256 { 1 } else { 2 };
+        let data = bytes.get(start..start + sample_count * sample_size).ok_or_else(|| invalid_data("unexpected end of PPM data"))?;
+        data.chunks_exact(sample_size).map(|s| s.iter().fold(0, |acc, &b| (acc << 8) | b as usize)).collect()
+    } else {
+        (0..sample_count).map(|_| header.next_number()).collect::<io::Result<_>>()?
+    };
+    if samples.iter().any(|&s| s > max_value) {
+        return Err(invalid_data("PPM sample exceeds the maximum value"));
+    }
+    let colors = samples
+        .chunks_exact(3)
+        .map(|rgb| [rgb[0], rgb[1], rgb[2]].map(|s| s as f32 / max_value as f32))
+        .collect();
+    Ok((width, height, colors))
+}
+
+/// Decodes `bytes` if they hold a high dynamic range image, returning `None` for other formats.
+fn decode_hdr(bytes: &[u8]) -> io::Result<Option<HdrPixels>> {
+    if bytes.starts_with(b"PF") || bytes.starts_with(b"Pf") {
+        hdr::read_pfm(bytes).map(Some)
+    } else if bytes.starts_with(b"#?") {
+        hdr::read_hdr(bytes).map(Some)
+    } else if bytes.starts_with(&hdr::EXR_MAGIC) {
+        hdr::read_exr(bytes).map(Some)
+    } else {
+        Ok(Option::None)
+    }
+}
+
+/// Decodes a PNG image into its dimensions and RGB colours scaled to `[0, 1]`.
+fn decode_png(bytes: &[u8]) -> io::Result<(usize, usize, Vec<[f32; 3]>)> {
+    let to_io_error = |e: png::DecodingError| io::Error::new(io::ErrorKind::InvalidData, e);
+    let mut decoder = png::Decoder::new(bytes);
+    // Expand palettes and bit depths below 8 so that only 8 and 16 bit samples remain.
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(to_io_error)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(to_io_error)?;
+    let buffer = &buffer[..info.buffer_size()];
+
+    let samples: Vec<f32> = match info.bit_depth {
+        png::BitDepth::Sixteen => buffer.chunks_exact(2).map(|s| u16::from_be_bytes([s[0], s[1]]) as f32 / 65535.0).collect(),
+        _ => buffer.iter().map(|&s| s as f32 / 255.0).collect()
+    };
+    let colors = match info.color_type {
+        png::ColorType::Grayscale => samples.iter().map(|&g| [g, g, g]).collect(),
+        png::ColorType::GrayscaleAlpha => samples.chunks_exact(2).map(|s| [s[0], s[0], s[0]]).collect(),
+        png::ColorType::Rgb => samples.chunks_exact(3).map(|s| [s[0], s[1], s[2]]).collect(),
+        png::ColorType::Rgba => samples.chunks_exact(4).map(|s| [s[0], s[1], s[2]]).collect(),
+        png::ColorType::Indexed => return Err(invalid_data("unexpanded PNG palette"))
+    };
+    Ok((info.width as usize, info.height as usize, colors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_data(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/textures").join(name)
+    }
+
+    /// Red, green, blue and white.
+    fn colors() -> [Vector4; 4] {
+        [
+            Vector4::new(1.0, 0.0, 0.0, 0.0),
+            Vector4::new(0.0, 1.0, 0.0, 0.0),
+            Vector4::new(0.0, 0.0, 1.0, 0.0),
+            Vector4::new(1.0, 1.0, 1.0, 0.0)
+        ]
+    }
+
+    #[test]
+    fn test_load() {
+        let [red, green, blue, white] = colors();
+        // The test images hold red and green in the top row, blue and white in the bottom row.
+        for name in ["rgb_p3.ppm", "rgb_p6_16.ppm", "rgba8.png"] {
+            let texture = ImageTexture::load(test_data(name), 1.0, WrapMode::Clamp).unwrap();
+            assert_eq!((texture.width(), texture.height()), (2, 2));
+            assert_eq!(texture.pixels, vec![red, green, blue, white], "{}", name);
+        }
+
+        let texture = ImageTexture::load(test_data("gray16.png"), 2.0, WrapMode::Clamp).unwrap();
+        let gray = (0x8000 as f32 / 65535.0).powi(2);
+        assert_eq!(texture.pixels[2], Vector4::new(gray, gray, gray, 0.0));
+        assert_eq!(texture.pixels[3], white);
+
+        let error = ImageTexture::load(test_data("../obj/cube.obj"), 1.0, WrapMode::Clamp).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_bilinear_filtering() {
+        let [red, green, blue, white] = colors();
+        let clamped = ImageTexture::new(2, 2, vec![red, green, blue, white], WrapMode::Clamp);
+        let repeated = ImageTexture::new(2, 2, vec![red, green, blue, white], WrapMode::Repeat);
+        let p = Vector4::new(0.0, 0.0, 0.0, 0.0);
+        let average = (red + green + blue + white) / 4.0;
+
+        // Texel centres.
+        for texture in [&clamped, &repeated] {
+            assert_eq!(texture.value(0.25, 0.75, p), red);
+            assert_eq!(texture.value(0.75, 0.75, p), green);
+            assert_eq!(texture.value(0.25, 0.25, p), blue);
+            assert_eq!(texture.value(0.75, 0.25, p), white);
+            assert_eq!(texture.value(0.5, 0.5, p), average);
+        }
+
+        // At the corners, clamping extends the corner texels while repeating blends in the opposite edges.
+        assert_eq!(clamped.value(0.0, 0.0, p), blue);
+        assert_eq!(clamped.value(1.0, 1.0, p), green);
+        assert_eq!(repeated.value(0.0, 0.0, p), average);
+        assert_eq!(repeated.value(1.25, 1.75, p), red);
+        assert_eq!(repeated.value(0.0, 0.75, p), (red + green) / 2.0);
+    }
+}
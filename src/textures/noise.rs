@@ -0,0 +1,123 @@
+use crate::{
+    color::lerp,
+    noise::Perlin,
+    textures::Texture,
+    vector4::Vector4
+};
+use std::f32::consts::PI;
+
+/// Number of octaves of noise summed for fractal patterns.
+const OCTAVES: usize = 7;
+
+/// Texture shading `color` by fractional Brownian motion, e.g. for terrain or clouds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseTexture {
+    noise: Perlin,
+    scale: f32,     // Frequency of the first octave of noise.
+    color: Vector4
+}
+
+impl NoiseTexture {
+    pub fn new(noise: Perlin, scale: f32, color: Vector4) -> Self {
+        Self { noise, scale, color }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f32, _v: f32, p: Vector4) -> Vector4 {
+        let fbm = self.noise.fbm(self.scale * p, OCTAVES, 2.0, 0.5);
+        // The octaves sum to at most 2 in magnitude.
+        (0.5 + 0.25 * fbm).clamp(0.0, 1.0) * self.color
+    }
+}
+
+/// Marble with veins running horizontally, i.e. orthogonal to the `z` axis, distorted by turbulence.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Marble {
+    noise: Perlin,
+    scale: f32,         // Frequency of the veins along the z axis.
+    distortion: f32,    // Magnitude of the turbulence displacing the veins.
+    vein_color: Vector4,
+    base_color: Vector4
+}
+
+impl Marble {
+    pub fn new(noise: Perlin, scale: f32, distortion: f32, vein_color: Vector4, base_color: Vector4) -> Self {
+        Self { noise, scale, distortion, vein_color, base_color }
+    }
+}
+
+impl Texture for Marble {
+    fn value(&self, _u: f32, _v: f32, p: Vector4) -> Vector4 {
+        let phase = self.scale * p.z() + self.distortion * self.noise.turbulence(p, OCTAVES);
+        lerp(self.vein_color, self.base_color, 0.5 * (1.0 + phase.sin()))
+    }
+}
+
+/// Wood with growth rings centred on the `z` axis, distorted by turbulence.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Wood {
+    noise: Perlin,
+    ring_spacing: f32,  // Distance between neighbouring rings.
+    distortion: f32,    // Magnitude of the turbulence displacing the rings, in units of ring_spacing.
+    light_color: Vector4,
+    dark_color: Vector4
+}
+
+impl Wood {
+    pub fn new(noise: Perlin, ring_spacing: f32, distortion: f32, light_color: Vector4, dark_color: Vector4) -> Self {
+        Self { noise, ring_spacing, distortion, light_color, dark_color }
+    }
+}
+
+impl Texture for Wood {
+    fn value(&self, _u: f32, _v: f32, p: Vector4) -> Vector4 {
+        let radius = f32::sqrt(p.x() * p.x() + p.y() * p.y()) / self.ring_spacing;
+        let rings = radius + self.distortion * self.noise.turbulence(p / self.ring_spacing, OCTAVES);
+        // Each ring fades from light to dark, with a smooth transition back to light at the ring boundary.
+        let t = rings - rings.floor();
+        let t = if t < 0.8 { t / 0.8 } else { 0.5 * (1.0 + f32::cos(PI * (t - 0.8) / 0.2)) };
+        lerp(self.light_color, self.dark_color, t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn test_patterns_stay_between_colors() {
+        let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
+        let a = Vector4::new(0.1, 0.2, 0.3, 0.0);
+        let b = Vector4::new(0.9, 0.8, 0.7, 0.0);
+        let textures: [Box<dyn Texture>; 3] = [
+            Box::new(NoiseTexture::new(Perlin::new(&mut Pcg64Mcg::seed_from_u64(1)), 4.0, b)),
+            Box::new(Marble::new(Perlin::new(&mut Pcg64Mcg::seed_from_u64(2)), 4.0, 10.0, a, b)),
+            Box::new(Wood::new(Perlin::new(&mut Pcg64Mcg::seed_from_u64(3)), 0.1, 0.5, b, a))
+        ];
+        for texture in textures {
+            let mut min = Vector4::new(f32::INFINITY, f32::INFINITY, f32::INFINITY, 0.0);
+            let mut max = -min;
+            for _ in 0..10000 {
+                let p = Vector4::new(rng.random_range(-2.0..2.0), rng.random_range(-2.0..2.0), rng.random_range(-2.0..2.0), 0.0);
+                let value = texture.value(0.0, 0.0, p);
+                min = min.min(value);
+                max = max.max(value);
+            }
+            // Every value lies between the colours, and the pattern is not constant.
+            assert!(min.x() >= -1e-6 && max.x() <= b.x() + 1e-6);
+            assert!(max.x() - min.x() > 0.25);
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_texture() {
+        let color = Vector4::new(1.0, 1.0, 1.0, 0.0);
+        let marble = |seed| Marble::new(Perlin::new(&mut Pcg64Mcg::seed_from_u64(seed)), 4.0, 10.0, color, 0.5 * color);
+        let p = Vector4::new(0.3, -1.2, 0.7, 0.0);
+        assert_eq!(marble(7).value(0.0, 0.0, p), marble(7).value(0.0, 0.0, p));
+        assert_ne!(marble(7).value(0.0, 0.0, p), marble(8).value(0.0, 0.0, p));
+    }
+}
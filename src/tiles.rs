@@ -0,0 +1,104 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Rectangular block of pixels of an image, the unit of work handed out to rendering threads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tile {
+    pub row: usize,     // Row of the top-left pixel.
+    pub column: usize,  // Column of the top-left pixel.
+    pub height: usize,
+    pub width: usize
+}
+
+impl Tile {
+    /// Returns the row and column of each pixel of the tile, in row-major order.
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (row, column, width) = (self.row, self.column, self.width);
+        (row..row + self.height).flat_map(move |i| (column..column + width).map(move |j| (i, j)))
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        self.width * self.height
+    }
+}
+
+/// Queue of the tiles covering an image, shared by rendering threads that each pop the next tile once they have finished
+/// their previous one, so that no thread sits idle while tiles remain.
+#[derive(Debug)]
+pub struct TileQueue {
+    tiles: Vec<Tile>,
+    next: AtomicUsize   // Index of the next tile to hand out.
+}
+
+impl TileQueue {
+    /// Splits an image into tiles of `tile_size` by `tile_size` pixels in row-major order. Tiles on the right and bottom
+    /// edges are cut short where the image dimensions are not multiples of `tile_size`.
+    ///
+    /// Panics if `tile_size` is zero.
+    pub fn new(image_width: usize, image_height: usize, tile_size: usize) -> Self {
+        assert!(tile_size > 0, "tiles must not be empty");
+        let tiles = (0..image_height)
+            .step_by(tile_size)
+            .flat_map(|row| (0..image_width).step_by(tile_size).map(move |column| Tile {
+                row,
+                column,
+                height: usize::min(tile_size, image_height - row),
+                width: usize::min(tile_size, image_width - column)
+            }))
+            .collect();
+        Self { tiles, next: AtomicUsize::new(0) }
+    }
+
+    /// Returns the next tile, or `None` once every tile has been handed out.
+    pub fn pop(&self) -> Option<Tile> {
+        self.tiles.get(self.next.fetch_add(1, Ordering::Relaxed)).copied()
+    }
+
+    /// Returns the total number of tiles, including those already handed out.
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_tiles_cover_image() {
+        let (width, height) = (70, 33);
+        let queue = TileQueue::new(width, height, 32);
+        assert_eq!(queue.len(), 6);
+        assert_eq!(queue.pop(), Some(Tile { row: 0, column: 0, height: 32, width: 32 }));
+
+        let mut covered = vec![0; width * height];
+        Tile { row: 0, column: 0, height: 32, width: 32 }.pixels().for_each(|(i, j)| covered[i * width + j] += 1);
+        while let Some(tile) = queue.pop() {
+            tile.pixels().for_each(|(i, j)| covered[i * width + j] += 1);
+        }
+        assert!(covered.iter().all(|&c| c == 1));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn test_concurrent_pops() {
+        // Every tile is handed out exactly once, however many threads share the queue.
+        let queue = TileQueue::new(100, 100, 7);
+        let mut popped: Vec<Tile> = thread::scope(|s| {
+            let handles: Vec<_> = (0..8).map(|_| s.spawn(|| iter_queue(&queue))).collect();
+            handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+        });
+        popped.sort_by_key(|t| (t.row, t.column));
+        assert_eq!(popped.len(), queue.len());
+        assert_eq!(popped.iter().map(Tile::pixel_count).sum::<usize>(), 100 * 100);
+        assert!(popped.windows(2).all(|w| w[0] != w[1]));
+    }
+
+    fn iter_queue(queue: &TileQueue) -> Vec<Tile> {
+        std::iter::from_fn(|| queue.pop()).collect()
+    }
+}
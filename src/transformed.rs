@@ -0,0 +1,433 @@
+use crate::{
+    aabb::{Aabb, Bounded},
+    intersectable::{HitRecord, Intersectable},
+    materials::{Material, Tangible},
+    matrix4::Matrix4,
+    orientable::Orientable,
+    ray::Ray,
+    vector4::Vector4
+};
+use rand::Rng;
+use std::sync::Arc;
+
+/// Instance of `object` placed in the scene by an affine transform from object space to world space.
+///
+/// Rays are moved into object space to be intersected, and the hits moved back, so the object itself is never copied.
+/// Wrapping an `Arc` of a mesh in several instances thus shows the mesh many times at the cost of one transform each.
+#[derive(Clone)]
+pub struct Transformed<T> {
+    pub object: T,
+    transform: Matrix4,
+    inverse: Matrix4,
+    normal_transform: Matrix4   // Inverse transpose of transform, which maps normals to world space.
+}
+
+impl<T> Transformed<T> {
+    /// Places `object` by `transform`, which must be affine and invertible.
+    ///
+    /// # Panics
+    /// Panics if `transform` is singular.
+    pub fn new(object: T, transform: Matrix4) -> Self {
+        let inverse = transform.inverse().expect("the transform of an instance must be invertible");
+        Self { object, transform, inverse, normal_transform: inverse.transpose() }
+    }
+
+    pub fn transform(&self) -> Matrix4 {
+        self.transform
+    }
+}
+
+impl<R: Rng + ?Sized, T: Tangible<R> + Send + Sync> Intersectable<R> for Transformed<T> {
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        intersect_transformed(&self.object, self, &self.inverse, &self.normal_transform, r, t_min, t_max)
+    }
+}
+
+impl<T: Bounded> Bounded for Transformed<T> {
+    /// Returns the box around the transformed corners of the box of the object, or a box extending to infinity along
+    /// every axis if that of the object is not finite, e.g. for planes.
+    fn bounding_box(&self) -> Aabb {
+        let b = self.object.bounding_box();
+        if b.is_empty() {
+            return b;
+        }
+        if !b.is_finite() {
+            let infinity = Vector4::new(f32::INFINITY, f32::INFINITY, f32::INFINITY, 0.0);
+            return Aabb { min: -infinity, max: infinity };
+        }
+        (0..8).map(|corner| {
+            let pick = |axis: usize| if corner & (1 << axis) == 0 { b.min[axis] } else { b.max[axis] };
+            self.transform.transform_point(Vector4::new(pick(0), pick(1), pick(2), 0.0))
+        })
+        .fold(Aabb::empty(), |acc, p| acc.include(p))
+    }
+}
+
+impl<T: Orientable> Orientable for Transformed<T> {
+    fn normal(&self, p: Vector4) -> Vector4 {
+        self.normal_transform.transform_vector(self.object.normal(self.inverse.transform_point(p))).normalize()
+    }
+}
+
+impl<R: Rng + ?Sized, T: Tangible<R> + Send + Sync> Tangible<R> for Transformed<T> {
+    fn material(&self) -> &Arc<dyn Material<R> + Send + Sync> {
+        self.object.material()
+    }
+}
+
+/// Position, orientation and size of an animated object at `time`, see `Animated`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe {
+    pub time: f32,
+    pub translation: Vector4,
+    pub rotation: Vector4,      // Unit quaternion with the vector part in x, y and z and the scalar part in w.
+    pub scale: Vector4
+}
+
+impl Keyframe {
+    /// Constructs the keyframe at `time` that leaves the object as it is.
+    pub fn new(time: f32) -> Self {
+        Self {
+            time,
+            translation: Vector4::new(0.0, 0.0, 0.0, 0.0),
+            rotation: Vector4::new(0.0, 0.0, 0.0, 1.0),
+            scale: Vector4::new(1.0, 1.0, 1.0, 0.0)
+        }
+    }
+
+    pub fn with_translation(mut self, translation: Vector4) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    /// Sets the rotation by `angle` radians counterclockwise about `axis`, which need not be of unit length.
+    pub fn with_rotation(mut self, axis: Vector4, angle: f32) -> Self {
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        let axis = sin * axis.normalize();
+        self.rotation = Vector4::new(axis.x(), axis.y(), axis.z(), cos);
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vector4) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Returns the transform that scales, then rotates, then translates the object.
+    pub fn transform(&self) -> Matrix4 {
+        Matrix4::translation(self.translation) * rotation(self.rotation) * Matrix4::scaling(self.scale)
+    }
+
+    /// Returns the inverse of `transform`, which is assembled from the inverses of its steps rather than computed.
+    fn inverse_transform(&self) -> Matrix4 {
+        let inverse_scale = Vector4::new(self.scale.x().recip(), self.scale.y().recip(), self.scale.z().recip(), 0.0);
+        Matrix4::scaling(inverse_scale) * rotation(self.rotation).transpose() * Matrix4::translation(-self.translation)
+    }
+
+    /// Interpolates between `self` at `s = 0` and `next` at `s = 1`, linearly for the translation and scale and along the
+    /// shortest arc for the rotation.
+    fn interpolate(&self, next: &Self, s: f32) -> Self {
+        Self {
+            time: self.time + s * (next.time - self.time),
+            translation: self.translation + s * (next.translation - self.translation),
+            rotation: slerp(self.rotation, next.rotation, s),
+            scale: self.scale + s * (next.scale - self.scale)
+        }
+    }
+}
+
+/// Returns the rotation matrix of the unit quaternion `q`.
+fn rotation(q: Vector4) -> Matrix4 {
+    let (x, y, z, w) = (q.x(), q.y(), q.z(), q.w());
+    Matrix4::from_rows([
+        [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y), 0.0],
+        [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x), 0.0],
+        [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y), 0.0],
+        [0.0, 0.0, 0.0, 1.0]
+    ])
+}
+
+/// Spherical linear interpolation between the unit quaternions `q0` and `q1`, along the shorter of the two arcs between
+/// the rotations they represent.
+fn slerp(q0: Vector4, q1: Vector4, s: f32) -> Vector4 {
+    let cos = q0.dot(q1);
+    let (q1, cos) = if cos < 0.0 { (-q1, -cos) } else { (q1, cos) };
+    if cos > 0.9995 {
+        // The quaternions are so close that linear interpolation is accurate, and the angle between them is not.
+        return (q0 + s * (q1 - q0)).normalize();
+    }
+    let theta = cos.acos();
+    (f32::sin((1.0 - s) * theta) * q0 + f32::sin(s * theta) * q1) / theta.sin()
+}
+
+/// Instance of `object` moved by a transform animated through `keyframes`, e.g. for motion blur. At the time of each ray,
+/// the transform is interpolated between the keyframes around it, and before the first keyframe or after the last one,
+/// the object rests where they place it.
+#[derive(Clone)]
+pub struct Animated<T> {
+    pub object: T,
+    keyframes: Vec<Keyframe>
+}
+
+impl<T> Animated<T> {
+    /// # Panics
+    /// Panics if there are no keyframes, if they are not in the order of their times, or if any of them scales the object
+    /// to zero along some axis.
+    pub fn new(object: T, keyframes: Vec<Keyframe>) -> Self {
+        assert!(!keyframes.is_empty(), "an animation needs at least one keyframe");
+        assert!(keyframes.windows(2).all(|k| k[0].time <= k[1].time), "keyframes must be in the order of their times");
+        assert!(
+            keyframes.iter().all(|k| k.scale.x() * k.scale.y() * k.scale.z() != 0.0),
+            "the transforms of an animation must be invertible"
+        );
+        Self { object, keyframes }
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Returns the keyframe interpolated at `time`.
+    pub fn keyframe(&self, time: f32) -> Keyframe {
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        if next == 0 {
+            return self.keyframes[0];
+        }
+        if next == self.keyframes.len() {
+            return self.keyframes[next - 1];
+        }
+        let (previous, next) = (&self.keyframes[next - 1], &self.keyframes[next]);
+        previous.interpolate(next, (time - previous.time) / (next.time - previous.time))
+    }
+}
+
+impl<R: Rng + ?Sized, T: Tangible<R> + Send + Sync> Intersectable<R> for Animated<T> {
+    fn intersect(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_, R>> {
+        let inverse = self.keyframe(r.time).inverse_transform();
+        intersect_transformed(&self.object, self, &inverse, &inverse.transpose(), r, t_min, t_max)
+    }
+}
+
+impl<T: Bounded> Bounded for Animated<T> {
+    /// Returns a box around the object over the whole animation. Between keyframes with the same rotation, every point of
+    /// the object moves in a straight line, so the boxes around the object at both keyframes bound it. Otherwise, the
+    /// object is bounded by the box swept by a ball around its origin which is large enough to hold it in any orientation.
+    fn bounding_box(&self) -> Aabb {
+        let b = self.object.bounding_box();
+        if b.is_empty() {
+            return b;
+        }
+        if !b.is_finite() {
+            let infinity = Vector4::new(f32::INFINITY, f32::INFINITY, f32::INFINITY, 0.0);
+            return Aabb { min: -infinity, max: infinity };
+        }
+        let corners = (0..8).map(|corner| {
+            let pick = |axis: usize| if corner & (1 << axis) == 0 { b.min[axis] } else { b.max[axis] };
+            Vector4::new(pick(0), pick(1), pick(2), 0.0)
+        });
+        let keyframe_box = |keyframe: &Keyframe| {
+            let transform = keyframe.transform();
+            corners.clone().fold(Aabb::empty(), |acc, p| acc.include(transform.transform_point(p)))
+        };
+
+        let mut bounds = keyframe_box(&self.keyframes[0]);
+        for k in self.keyframes.windows(2) {
+            if k[0].rotation == k[1].rotation || k[0].rotation == -k[1].rotation {
+                bounds = bounds.union(keyframe_box(&k[1]));
+                continue;
+            }
+            // Scaling is linear in time, so the scaled corners are furthest from the origin at either keyframe.
+            let radius = corners.clone()
+                .flat_map(|p| [(k[0].scale * p).norm(), (k[1].scale * p).norm()])
+                .fold(0.0, f32::max);
+            let r = Vector4::new(radius, radius, radius, 0.0);
+            let translations = Aabb::new(k[0].translation, k[1].translation);
+            bounds = bounds.union(Aabb::new(translations.min - r, translations.max + r));
+        }
+        bounds
+    }
+}
+
+impl<R: Rng + ?Sized, T: Tangible<R> + Send + Sync> Tangible<R> for Animated<T> {
+    fn material(&self) -> &Arc<dyn Material<R> + Send + Sync> {
+        self.object.material()
+    }
+}
+
+/// Intersects `r` with `object` after moving it into object space by `inverse`, and moves the hit back into world space
+/// as a hit of `instance`.
+fn intersect_transformed<'a, R: Rng + ?Sized, T: Tangible<R> + Send + Sync>(
+    object: &'a T,
+    instance: &'a (dyn Tangible<R> + Send + Sync),
+    inverse: &Matrix4,
+    normal_transform: &Matrix4,
+    r: Ray,
+    t_min: f32,
+    t_max: f32
+) -> Option<HitRecord<'a, R>> {
+    // The direction is not normalised in object space, so that points along both rays share their parameters t.
+    let local = Ray { origin: inverse.transform_point(r.origin), direction: inverse.transform_vector(r.direction), ..r };
+    let hit = object.intersect(local, t_min, t_max)?;
+    let normal = normal_transform.transform_vector(hit.normal).normalize();
+    Some(HitRecord {
+        p: r.at(hit.t),
+        normal,
+        shading_normal: normal_transform.transform_vector(hit.shading_normal).normalize(),
+        front_face: r.direction.dot(normal) < 0.0,
+        object: instance,
+        ..hit
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bvh::Bvh,
+        materials::lambertian::Lambertian,
+        renderable_list::RenderableList,
+        surfaces::{cuboid::Cuboid, sphere::Sphere, triangle_mesh::TriangleMesh}
+    };
+    use rand_pcg::Pcg64Mcg;
+    use std::f32::consts::PI;
+
+    fn unit_sphere() -> Sphere<Pcg64Mcg> {
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        Sphere::new(Vector4::new(0.0, 0.0, 0.0, 0.0), 1.0, material)
+    }
+
+    #[test]
+    fn test_scaled_sphere() {
+        // The unit sphere stretched into the ellipsoid x^2 / 4 + y^2 + z^2 = 1 and moved to x = 10.
+        let transform = Matrix4::translation(Vector4::new(10.0, 0.0, 0.0, 0.0)) * Matrix4::scaling(Vector4::new(2.0, 1.0, 1.0, 0.0));
+        let ellipsoid = Transformed::new(unit_sphere(), transform);
+        let r = Ray::new(Vector4::new(0.0, 0.0, 0.0, 0.0), Vector4::new(2.0, 0.0, 0.0, 0.0));
+        let hit = ellipsoid.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert_eq!((hit.t, hit.p), (4.0, Vector4::new(8.0, 0.0, 0.0, 0.0)));
+        assert_eq!(hit.normal, Vector4::new(-1.0, 0.0, 0.0, 0.0));
+        assert!(hit.front_face);
+        assert!(std::ptr::addr_eq(hit.object, &ellipsoid));
+
+        // Normals are transformed by the inverse transpose, so they stay orthogonal to the stretched surface: at
+        // (8 + 2 cos a, sin a, 0), the normal is along (cos a / 2, sin a, 0).
+        let a = PI / 3.0;
+        let p = Vector4::new(10.0 + 2.0 * a.cos(), a.sin(), 0.0, 0.0);
+        let r = Ray::new(p + Vector4::new(0.0, 0.0, 5.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        let hit = ellipsoid.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.p - p).norm() < 1e-3);
+        let expected = Vector4::new(a.cos() / 2.0, a.sin(), 0.0, 0.0).normalize();
+        assert!((hit.normal.x() - expected.x()).abs() < 1e-3 && (hit.normal.y() - expected.y()).abs() < 1e-3);
+        assert!((ellipsoid.normal(p) - expected).norm() < 1e-5);
+
+        let b = ellipsoid.bounding_box();
+        assert_eq!((b.min, b.max), (Vector4::new(8.0, -1.0, -1.0, 0.0), Vector4::new(12.0, 1.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn test_rotated_cuboid() {
+        // A unit cube turned by 45 degrees about z: its bounding box grows, and a ray along the diagonal hits an edge.
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        let cube = Cuboid::<Pcg64Mcg>::new(Vector4::new(-0.5, -0.5, -0.5, 0.0), Vector4::new(0.5, 0.5, 0.5, 0.0), material);
+        let rotated = Transformed::new(cube, Matrix4::rotation(Vector4::new(0.0, 0.0, 1.0, 0.0), PI / 4.0));
+        let b = rotated.bounding_box();
+        assert!((b.max.x() - f32::sqrt(0.5)).abs() < 1e-6 && (b.max.z() - 0.5).abs() < 1e-6);
+        let r = Ray::new(Vector4::new(5.0, 0.0, 0.0, 0.0), Vector4::new(-1.0, 0.0, 0.0, 0.0));
+        let hit = rotated.intersect(r, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.t - (5.0 - f32::sqrt(0.5))).abs() < 1e-5);
+        assert!(hit.normal.x() > 0.0 && (hit.normal.norm() - 1.0).abs() < 1e-6);
+        let r = Ray::new(Vector4::new(5.0, 0.0, 0.6, 0.0), Vector4::new(-1.0, 0.0, 0.0, 0.0));
+        assert!(rotated.intersect(r, 0.001, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_instanced_mesh() {
+        // One triangle shared by three instances in a hierarchy, each hit where it was placed.
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        let positions = vec![Vector4::new(-1.0, -1.0, 0.0, 0.0), Vector4::new(1.0, -1.0, 0.0, 0.0), Vector4::new(0.0, 1.0, 0.0, 0.0)];
+        let mesh = Arc::new(TriangleMesh::<Pcg64Mcg>::new(positions, None, None, vec![[0, 1, 2]], material));
+        let mut list = RenderableList::new();
+        for x in [-5.0, 0.0, 5.0] {
+            let transform = Matrix4::translation(Vector4::new(x, 0.0, x, 0.0));
+            list.push(Box::new(Transformed::new(mesh.clone(), transform)));
+        }
+        assert_eq!(Arc::strong_count(&mesh), 4);
+        let bvh = Bvh::from(list);
+        for x in [-5.0, 0.0, 5.0] {
+            let r = Ray::new(Vector4::new(x, 0.0, 10.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+            let hit = bvh.intersect(r, 0.001, f32::INFINITY).unwrap();
+            assert_eq!(hit.p, Vector4::new(x, 0.0, x, 0.0));
+            assert_eq!(hit.normal, Vector4::new(0.0, 0.0, 1.0, 0.0));
+        }
+        let r = Ray::new(Vector4::new(2.5, 0.0, 10.0, 0.0), Vector4::new(0.0, 0.0, -1.0, 0.0));
+        assert!(bvh.intersect(r, 0.001, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_keyframes() {
+        // Half way between the identity and a rotation by 90 degrees, translation by (4, 0, 0) and scaling by 3.
+        let axis = Vector4::new(1.0, 1.0, 1.0, 0.0);
+        let start = Keyframe::new(1.0);
+        let end = Keyframe::new(3.0)
+            .with_rotation(axis, PI / 2.0)
+            .with_translation(Vector4::new(4.0, 0.0, 0.0, 0.0))
+            .with_scale(Vector4::new(3.0, 3.0, 3.0, 0.0));
+        let animated = Animated::new(unit_sphere(), vec![start, end]);
+        let middle = animated.keyframe(2.0);
+        let expected = Matrix4::translation(Vector4::new(2.0, 0.0, 0.0, 0.0))
+            * Matrix4::rotation(axis, PI / 4.0)
+            * Matrix4::scaling(Vector4::new(2.0, 2.0, 2.0, 0.0));
+        for (i, j) in (0..4).flat_map(|i| (0..4).map(move |j| (i, j))) {
+            assert!((middle.transform()[(i, j)] - expected[(i, j)]).abs() < 1e-5);
+            assert!((middle.inverse_transform()[(i, j)] - expected.inverse().unwrap()[(i, j)]).abs() < 1e-5);
+        }
+        assert_eq!(animated.keyframe(0.0), start);
+        assert_eq!(animated.keyframe(5.0), end);
+    }
+
+    #[test]
+    fn test_animated_sphere() {
+        // A unit sphere moving from the origin to (0, 0, 4) between times 0 and 1, while growing to a radius of 2.
+        let end = Keyframe::new(1.0).with_translation(Vector4::new(0.0, 0.0, 4.0, 0.0)).with_scale(Vector4::new(2.0, 2.0, 2.0, 0.0));
+        let animated = Animated::new(unit_sphere(), vec![Keyframe::new(0.0), end]);
+        let r = Ray::new(Vector4::new(-10.0, 0.0, 2.0, 0.0), Vector4::new(1.0, 0.0, 0.0, 0.0));
+        assert!(animated.intersect(r, 0.001, f32::INFINITY).is_none());
+        let hit = animated.intersect(r.with_time(0.5), 0.001, f32::INFINITY).unwrap();
+        assert!((hit.t - 8.5).abs() < 1e-5);
+        assert!((hit.normal - Vector4::new(-1.0, 0.0, 0.0, 0.0)).norm() < 1e-5);
+        assert!(std::ptr::addr_eq(hit.object, &animated));
+
+        // Without rotation, the box is that around both ends of the motion.
+        let b = animated.bounding_box();
+        assert_eq!((b.min, b.max), (Vector4::new(-2.0, -2.0, -1.0, 0.0), Vector4::new(2.0, 2.0, 6.0, 0.0)));
+    }
+
+    #[test]
+    fn test_animated_bounding_box() {
+        // A long box spinning about z while it moves stays within the bounding box at all times.
+        let material = Arc::new(Lambertian::new(Vector4::new(0.5, 0.5, 0.5, 0.0)));
+        let cube = Cuboid::<Pcg64Mcg>::new(Vector4::new(1.0, -0.5, -0.5, 0.0), Vector4::new(3.0, 0.5, 0.5, 0.0), material);
+        let z = Vector4::new(0.0, 0.0, 1.0, 0.0);
+        let keyframes = vec![
+            Keyframe::new(0.0),
+            Keyframe::new(1.0).with_rotation(z, 2.0 * PI / 3.0).with_translation(Vector4::new(1.0, 1.0, 0.0, 0.0)),
+            Keyframe::new(2.0).with_rotation(z, 4.0 * PI / 3.0).with_scale(Vector4::new(1.0, 1.0, 2.0, 0.0))
+        ];
+        let animated = Animated::new(cube, keyframes);
+        let b = animated.bounding_box();
+        let corner_box = animated.object.bounding_box();
+        for step in 0..=100 {
+            let transform = animated.keyframe(step as f32 / 50.0).transform();
+            for corner in 0..8 {
+                let pick = |axis: usize| if corner & (1 << axis) == 0 { corner_box.min[axis] } else { corner_box.max[axis] };
+                let p = transform.transform_point(Vector4::new(pick(0), pick(1), pick(2), 0.0));
+                assert!((p - b.min).min(b.max - p).min(Vector4::new(0.0, 0.0, 0.0, 0.0)).norm() < 1e-5, "{:?} is outside", p);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_singular_transform() {
+        Transformed::new(unit_sphere(), Matrix4::scaling(Vector4::new(1.0, 1.0, 0.0, 0.0)));
+    }
+}
@@ -51,6 +51,11 @@ impl Vector4 {
         unsafe { f32::sqrt(self.value[0] * self.value[0] + self.value[1] * self.value[1] + self.value[2] * self.value[2] + self.value[3] * self.value[3]) }
     }
 
+    /// Returns `self` divided by its norm, to within rounding like `*self / self.norm()`.
+    ///
+    /// The SIMD variants divide by a full-precision square root rather than multiplying by the approximate reciprocal
+    /// square root of `_mm_rsqrt_ps`, which is only accurate to about 12 bits and would leave unit vectors off by up to
+    /// ~1e-4, too much for the intersection and sampling code that relies on them being normalized.
     #[allow(unreachable_code)]
     pub fn normalize(&self) -> Self {
         #[cfg(all(target_arch = "x86_64", target_feature = "sse4.1"))]
@@ -83,11 +88,49 @@ impl Vector4 {
             ]
         } }
     }
+
+    /// Returns the component-wise minimum of `self` and `rhs`.
+    #[allow(unreachable_code)]
+    pub fn min(&self, rhs: Self) -> Self {
+        #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse"))]
+        unsafe { return self.simd_min(rhs) }
+        unsafe { Self { value: [self.value[0].min(rhs.value[0]), self.value[1].min(rhs.value[1]), self.value[2].min(rhs.value[2]), self.value[3].min(rhs.value[3])] } }
+    }
+
+    /// Returns the component-wise maximum of `self` and `rhs`.
+    #[allow(unreachable_code)]
+    pub fn max(&self, rhs: Self) -> Self {
+        #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse"))]
+        unsafe { return self.simd_max(rhs) }
+        unsafe { Self { value: [self.value[0].max(rhs.value[0]), self.value[1].max(rhs.value[1]), self.value[2].max(rhs.value[2]), self.value[3].max(rhs.value[3])] } }
+    }
+
+    /// Returns two unit vectors `(u, v)` such that `(u, v, self)` is a right-handed orthonormal basis of `R^3`.
+    /// 
+    /// `self` must be of unit length. Uses the branchless construction of Duff et al., "Building an Orthonormal Basis, Revisited", JCGT 2017.
+    pub fn orthonormal_basis(&self) -> (Self, Self) {
+        let sign = 1.0_f32.copysign(self.z());
+        let a = -1.0 / (sign + self.z());
+        let b = self.x() * self.y() * a;
+        (
+            Self::new(1.0 + sign * self.x() * self.x() * a, sign * b, -sign * self.x(), 0.0),
+            Self::new(b, sign + self.y() * self.y() * a, -self.y(), 0.0)
+        )
+    }
 }
 
 // Vector arithmetic using x86/x86_64 SSE intrinsics.
 #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse"))]
 impl Vector4 {
+    /// Wraps an SSE register, for use by other SIMD types such as `Matrix4`.
+    pub(crate) fn from_simd(simd: __m128) -> Self {
+        Self { simd }
+    }
+
+    pub(crate) fn simd(&self) -> __m128 {
+        unsafe { self.simd }
+    }
+
     #[target_feature(enable = "sse")]
     fn simd_eq(&self, other: &Self) -> bool {
         unsafe {
@@ -121,6 +164,16 @@ impl Vector4 {
         unsafe { Vector4 { simd: _mm_div_ps(self.simd, _mm_set1_ps(rhs)) } }
     }
     
+    #[target_feature(enable = "sse")]
+    fn simd_min(&self, rhs: Self) -> Self {
+        unsafe { Self { simd: _mm_min_ps(self.simd, rhs.simd) } }
+    }
+
+    #[target_feature(enable = "sse")]
+    fn simd_max(&self, rhs: Self) -> Self {
+        unsafe { Self { simd: _mm_max_ps(self.simd, rhs.simd) } }
+    }
+
     /// Ignores the `w` component when computing the cross product.
     #[target_feature(enable = "sse")]
     fn simd_cross(&self, rhs: Self) -> Self {
@@ -149,7 +202,7 @@ impl Vector4 {
     fn simd_normalize(&self) -> Self {
         unsafe {
             let norm2_vec = _mm_set1_ps(self.simd_dot(*self));
-            Self { simd: _mm_mul_ps(self.simd, _mm_rsqrt_ps(norm2_vec)) } 
+            Self { simd: _mm_div_ps(self.simd, _mm_sqrt_ps(norm2_vec)) } 
         }
     }
 
@@ -185,7 +238,7 @@ impl Vector4 {
     fn simd_normalize_sse41(&self) -> Self {
         unsafe { 
             let norm2_vec = _mm_dp_ps::<0xff>(self.simd, self.simd);
-            Self { simd: _mm_mul_ps(self.simd, _mm_rsqrt_ps(norm2_vec)) } 
+            Self { simd: _mm_div_ps(self.simd, _mm_sqrt_ps(norm2_vec)) } 
         }
     }
 
@@ -207,6 +260,14 @@ impl fmt::Debug for Vector4 {
     }
 }
 
+impl ops::Index<usize> for Vector4 {
+    type Output = f32;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        unsafe { &self.value[index] }
+    }
+}
+
 impl PartialEq for Vector4 {
     #[allow(unreachable_code)]
     fn eq(&self, other: &Self) -> bool {
@@ -538,6 +599,36 @@ mod tests {
         assert_eq!(Vector4::new(1.0, 2.0, 3.0, 4.0) * Vector4::from([3.0; 4]), 3.0 * Vector4::new(1.0, 2.0, 3.0, 4.0));
     }
 
+    #[test]
+    fn test_min_max() {
+        let v1 = Vector4::new(1.0, -2.0, 3.0, -4.0);
+        let v2 = Vector4::new(-1.0, 2.0, -3.0, 4.0);
+        assert_eq!(v1.min(v2), Vector4::new(-1.0, -2.0, -3.0, -4.0));
+        assert_eq!(v1.max(v2), Vector4::new(1.0, 2.0, 3.0, 4.0));
+        assert_eq!(v1.min(v1), v1);
+    }
+
+    #[test]
+    fn test_index() {
+        let v = Vector4::new(1.0, 2.0, 3.0, 4.0);
+        assert_eq!([v[0], v[1], v[2], v[3]], [1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_orthonormal_basis() {
+        for n in [
+            Vector4::new(0.0, 0.0, 1.0, 0.0),
+            Vector4::new(0.0, 0.0, -1.0, 0.0),
+            Vector4::new(1.0, -2.0, 3.0, 0.0).normalize(),
+            Vector4::new(-3.0, 1.0, -0.5, 0.0).normalize()
+        ] {
+            let (u, v) = n.orthonormal_basis();
+            assert!((u.norm() - 1.0).abs() < 1e-6 && (v.norm() - 1.0).abs() < 1e-6);
+            assert!(u.dot(v).abs() < 1e-6 && u.dot(n).abs() < 1e-6 && v.dot(n).abs() < 1e-6);
+            assert!((u.cross(v) - n).norm() < 1e-6);
+        }
+    }
+
     #[test]
     fn test_equality() {
         let v1 = Vector4::new(1.0, 2.0, 3.0, 4.0);